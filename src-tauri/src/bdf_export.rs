@@ -0,0 +1,128 @@
+// BDF 2.1 writer for handing baked fonts to other toolchains (bdfconv, console tools, fontforge).
+// Glyph rows use the same 1-bit MSB-first packing as the generated bitmaps, one hex line per row.
+
+pub struct BdfGlyph {
+    /// `None` writes `ENCODING -1`, used for a fallback glyph that has no codepoint of its own.
+    pub encoding: Option<u32>,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: i32,
+    /// Bottom edge of the bitmap relative to the baseline, as in `BBX`.
+    pub y_offset: i32,
+    pub advance: i32,
+    pub rows: Vec<u8>,
+}
+
+pub struct BdfFont {
+    pub family: String,
+    pub pixel_size: u32,
+    pub ascent: i32,
+    pub descent: i32,
+    pub default_char: Option<u32>,
+    pub copyright: Option<String>,
+    pub comments: Vec<String>,
+    pub glyphs: Vec<BdfGlyph>,
+}
+
+pub fn write_bdf(font: &BdfFont) -> String {
+    let family = font.family.replace(['-', '"', '\n', '\r'], " ");
+    let average_width = if font.glyphs.is_empty() {
+        0
+    } else {
+        font.glyphs.iter().map(|g| g.advance as i64).sum::<i64>() * 10 / font.glyphs.len() as i64
+    };
+    let [width, height, x_offset, y_offset] = bounding_box(&font.glyphs);
+
+    let mut out = String::from("STARTFONT 2.1\n");
+    for comment in &font.comments {
+        let line = format!("COMMENT {}", comment.replace(['\n', '\r'], " "));
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "FONT -misc-{}-medium-r-normal--{}-{}-72-72-p-{}-iso10646-1\n",
+        family,
+        font.pixel_size,
+        font.pixel_size * 10,
+        average_width
+    ));
+    out.push_str(&format!("SIZE {} 72 72\n", font.pixel_size));
+    out.push_str(&format!("FONTBOUNDINGBOX {} {} {} {}\n", width, height, x_offset, y_offset));
+
+    let mut properties = vec![
+        format!("FAMILY_NAME {}", quote(&family)),
+        "WEIGHT_NAME \"Medium\"".to_string(),
+        "SLANT \"R\"".to_string(),
+        format!("PIXEL_SIZE {}", font.pixel_size),
+        format!("POINT_SIZE {}", font.pixel_size * 10),
+        "RESOLUTION_X 72".to_string(),
+        "RESOLUTION_Y 72".to_string(),
+        format!("AVERAGE_WIDTH {}", average_width),
+        format!("FONT_ASCENT {}", font.ascent),
+        format!("FONT_DESCENT {}", font.descent),
+        "CHARSET_REGISTRY \"ISO10646\"".to_string(),
+        "CHARSET_ENCODING \"1\"".to_string(),
+    ];
+    if let Some(copyright) = &font.copyright {
+        properties.push(format!("COPYRIGHT {}", quote(&copyright.replace(['\n', '\r'], " "))));
+    }
+    if let Some(default_char) = font.default_char {
+        properties.push(format!("DEFAULT_CHAR {}", default_char));
+    }
+    out.push_str(&format!("STARTPROPERTIES {}\n", properties.len()));
+    for property in properties {
+        out.push_str(&property);
+        out.push('\n');
+    }
+    out.push_str("ENDPROPERTIES\n");
+
+    out.push_str(&format!("CHARS {}\n", font.glyphs.len()));
+    for glyph in &font.glyphs {
+        match glyph.encoding {
+            Some(cp) if cp <= 0xFFFF => out.push_str(&format!("STARTCHAR uni{:04X}\nENCODING {}\n", cp, cp)),
+            Some(cp) => out.push_str(&format!("STARTCHAR u{:X}\nENCODING {}\n", cp, cp)),
+            None => out.push_str("STARTCHAR fallback\nENCODING -1\n"),
+        }
+        // SWIDTH is in 1/1000 em; at 72 dpi one point is one pixel.
+        let swidth = (glyph.advance as i64 * 1000 / font.pixel_size.max(1) as i64) as i32;
+        out.push_str(&format!("SWIDTH {} 0\n", swidth));
+        out.push_str(&format!("DWIDTH {} 0\n", glyph.advance));
+        out.push_str(&format!(
+            "BBX {} {} {} {}\n",
+            glyph.width, glyph.height, glyph.x_offset, glyph.y_offset
+        ));
+        out.push_str("BITMAP\n");
+        let stride = glyph.width.div_ceil(8) as usize;
+        if stride > 0 {
+            for row in glyph.rows.chunks(stride).take(glyph.height as usize) {
+                for byte in row {
+                    out.push_str(&format!("{:02X}", byte));
+                }
+                out.push('\n');
+            }
+        }
+        out.push_str("ENDCHAR\n");
+    }
+    out.push_str("ENDFONT\n");
+    out
+}
+
+/// Union of all glyph boxes as `[width, height, x_offset, y_offset]`.
+fn bounding_box(glyphs: &[BdfGlyph]) -> [i32; 4] {
+    let inked = glyphs.iter().filter(|g| g.width > 0 && g.height > 0);
+    let (mut left, mut bottom, mut right, mut top) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for glyph in inked {
+        left = left.min(glyph.x_offset);
+        bottom = bottom.min(glyph.y_offset);
+        right = right.max(glyph.x_offset + glyph.width as i32);
+        top = top.max(glyph.y_offset + glyph.height as i32);
+    }
+    if left > right {
+        return [0, 0, 0, 0];
+    }
+    [right - left, top - bottom, left, bottom]
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
@@ -0,0 +1,904 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+
+// Pixel fonts (BDF, PCF, Windows .FNT, PSF1/PSF2) parsed into per-codepoint bitmaps and metrics.
+// Glyph index 0 is reserved for "missing", matching fontdue's lookup convention.
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PCF_MAGIC: [u8; 4] = *b"\x01fcp";
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// One glyph with fontdue-style metrics: `y_min` is the bottom row relative to the baseline.
+#[derive(Debug, Clone, Default)]
+pub struct BitmapGlyph {
+    pub width: u32,
+    pub height: u32,
+    pub x_min: i32,
+    pub y_min: i32,
+    pub advance: i32,
+    /// Row-major coverage, 0 or 255 per pixel.
+    pub gray: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    pub format: &'static str,
+    pub family: String,
+    pub copyright: Option<String>,
+    pub pixel_size: u32,
+    pub ascent: i32,
+    pub descent: i32,
+    glyphs: Vec<BitmapGlyph>,
+    cmap: BTreeMap<u32, u16>,
+}
+
+impl BitmapFont {
+    /// Detects the format from its magic bytes (BDF by its `STARTFONT` keyword, FNT by version).
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        if data.starts_with(&GZIP_MAGIC) {
+            let mut inflated = Vec::new();
+            flate2::read::GzDecoder::new(data)
+                .read_to_end(&mut inflated)
+                .map_err(|e| format!("Failed to decompress gzip font: {}", e))?;
+            return Self::parse(&inflated);
+        }
+        if data.starts_with(b"STARTFONT") {
+            parse_bdf(data)
+        } else if data.starts_with(&PCF_MAGIC) {
+            parse_pcf(data)
+        } else if data.starts_with(&PSF2_MAGIC) {
+            parse_psf2(data)
+        } else if data.starts_with(&PSF1_MAGIC) {
+            parse_psf1(data)
+        } else if data.starts_with(b"MZ") {
+            Err("Windows .FON containers are not supported, extract the .FNT resource first".to_string())
+        } else if matches!(data.get(0..2), Some([0x00, 0x02]) | Some([0x00, 0x03])) {
+            parse_fnt(data)
+        } else {
+            Err("Unrecognized bitmap font format (expected BDF, PCF, FNT or PSF)".to_string())
+        }
+    }
+
+    /// Builds a font from glyphs decoded elsewhere, such as cells cut from an image.
+    pub fn from_glyphs(
+        format: &'static str,
+        family: String,
+        pixel_size: u32,
+        ascent: i32,
+        descent: i32,
+        glyphs: impl IntoIterator<Item = (u32, BitmapGlyph)>,
+    ) -> Result<Self, String> {
+        let mut builder = FontBuilder::new();
+        for (codepoint, glyph) in glyphs {
+            let index = builder.push(glyph)?;
+            builder.map(codepoint, index);
+        }
+        builder.finish(format, family, None, pixel_size, ascent, descent)
+    }
+
+    pub fn glyph_index(&self, codepoint: u32) -> u16 {
+        self.cmap.get(&codepoint).copied().unwrap_or(0)
+    }
+
+    /// Number of glyph indices in use, counting the reserved index 0.
+    pub fn glyph_count(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    pub fn glyph(&self, index: u16) -> Option<&BitmapGlyph> {
+        self.glyphs.get(index as usize).filter(|_| index != 0)
+    }
+}
+
+/// Collects glyphs and the codepoint map while a parser walks its file.
+struct FontBuilder {
+    glyphs: Vec<BitmapGlyph>,
+    cmap: BTreeMap<u32, u16>,
+}
+
+impl FontBuilder {
+    fn new() -> Self {
+        Self {
+            glyphs: vec![BitmapGlyph::default()],
+            cmap: BTreeMap::new(),
+        }
+    }
+
+    fn push(&mut self, glyph: BitmapGlyph) -> Result<u16, String> {
+        let index = u16::try_from(self.glyphs.len()).map_err(|_| "Bitmap font has more than 65535 glyphs".to_string())?;
+        self.glyphs.push(crop(glyph));
+        Ok(index)
+    }
+
+    fn map(&mut self, codepoint: u32, index: u16) {
+        if char::from_u32(codepoint).is_some() {
+            self.cmap.entry(codepoint).or_insert(index);
+        }
+    }
+
+    fn finish(
+        self,
+        format: &'static str,
+        family: String,
+        copyright: Option<String>,
+        pixel_size: u32,
+        ascent: i32,
+        descent: i32,
+    ) -> Result<BitmapFont, String> {
+        if self.cmap.is_empty() {
+            return Err(format!("{} font has no mapped glyphs", format));
+        }
+        Ok(BitmapFont {
+            format,
+            family,
+            copyright,
+            pixel_size: pixel_size.max(1),
+            ascent,
+            descent,
+            glyphs: self.glyphs,
+            cmap: self.cmap,
+        })
+    }
+}
+
+/// Trims blank rows and columns so cells from fixed-size formats don't store empty margins.
+fn crop(glyph: BitmapGlyph) -> BitmapGlyph {
+    let (w, h) = (glyph.width as usize, glyph.height as usize);
+    let ink = |x: usize, y: usize| glyph.gray[y * w + x] != 0;
+    let rows: Vec<usize> = (0..h).filter(|y| (0..w).any(|x| ink(x, *y))).collect();
+    let cols: Vec<usize> = (0..w).filter(|x| (0..h).any(|y| ink(*x, y))).collect();
+    let (Some(&top), Some(&bottom), Some(&left), Some(&right)) = (rows.first(), rows.last(), cols.first(), cols.last())
+    else {
+        return BitmapGlyph {
+            advance: glyph.advance,
+            ..Default::default()
+        };
+    };
+    let (cw, ch) = (right - left + 1, bottom - top + 1);
+    let mut gray = Vec::with_capacity(cw * ch);
+    for y in top..=bottom {
+        gray.extend_from_slice(&glyph.gray[y * w + left..=y * w + right]);
+    }
+    BitmapGlyph {
+        width: cw as u32,
+        height: ch as u32,
+        x_min: glyph.x_min + left as i32,
+        y_min: glyph.y_min + (h - 1 - bottom) as i32,
+        advance: glyph.advance,
+        gray,
+    }
+}
+
+/// Expands MSB-first packed rows of `row_bytes` bytes into 0/255 coverage.
+fn unpack_rows(data: &[u8], width: usize, height: usize, row_bytes: usize) -> Option<Vec<u8>> {
+    let mut gray = vec![0u8; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let byte = *data.get(y * row_bytes + x / 8)?;
+            if byte & (0x80 >> (x % 8)) != 0 {
+                gray[y * width + x] = 255;
+            }
+        }
+    }
+    Some(gray)
+}
+
+fn parse_bdf(data: &[u8]) -> Result<BitmapFont, String> {
+    let text = String::from_utf8_lossy(data);
+    let mut lines = text.lines().map(str::trim);
+    let mut builder = FontBuilder::new();
+    let mut family = String::new();
+    let mut copyright = None;
+    let mut pixel_size = None;
+    let mut ascent = None;
+    let mut descent = None;
+    let mut font_bbox = [0i32; 4];
+    let mut charset_registry = String::new();
+    let mut charset_encoding = String::new();
+
+    while let Some(line) = lines.next() {
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let ints = || rest.split_whitespace().filter_map(|v| v.parse::<i32>().ok()).collect::<Vec<_>>();
+        match keyword {
+            "FONTBOUNDINGBOX" => {
+                let v = ints();
+                if v.len() == 4 {
+                    font_bbox = [v[0], v[1], v[2], v[3]];
+                }
+            }
+            "PIXEL_SIZE" => pixel_size = rest.parse::<u32>().ok(),
+            "FONT_ASCENT" => ascent = rest.parse::<i32>().ok(),
+            "FONT_DESCENT" => descent = rest.parse::<i32>().ok(),
+            "FAMILY_NAME" => family = unquote(rest),
+            "COPYRIGHT" => copyright = Some(unquote(rest)),
+            "CHARSET_REGISTRY" => charset_registry = unquote(rest),
+            "CHARSET_ENCODING" => charset_encoding = unquote(rest),
+            "STARTCHAR" => {
+                let (encoding, glyph) = parse_bdf_char(&mut lines, font_bbox)?;
+                let index = builder.push(glyph)?;
+                if let Some(cp) = encoding {
+                    builder.map(cp, index);
+                }
+            }
+            _ => {}
+        }
+    }
+    check_charset("BDF", &charset_registry, &charset_encoding)?;
+    let ascent = ascent.unwrap_or(font_bbox[1] + font_bbox[3]);
+    let descent = descent.unwrap_or(-font_bbox[3]);
+    let pixel_size = pixel_size.unwrap_or((ascent + descent).max(1) as u32);
+    builder.finish("BDF", family, copyright, pixel_size, ascent, descent)
+}
+
+fn parse_bdf_char<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    font_bbox: [i32; 4],
+) -> Result<(Option<u32>, BitmapGlyph), String> {
+    let mut encoding = None;
+    let mut advance = None;
+    let mut bbox = font_bbox;
+    let mut rows: Vec<Vec<u8>> = Vec::new();
+    let mut in_bitmap = false;
+    for line in lines.by_ref() {
+        if line == "ENDCHAR" {
+            let (w, h) = (bbox[0].max(0) as usize, bbox[1].max(0) as usize);
+            let row_bytes = w.div_ceil(8);
+            let mut packed = Vec::with_capacity(row_bytes * h);
+            for row in rows.iter().take(h) {
+                packed.extend((0..row_bytes).map(|i| row.get(i).copied().unwrap_or(0)));
+            }
+            packed.resize(row_bytes * h, 0);
+            let gray = unpack_rows(&packed, w, h, row_bytes).unwrap_or_default();
+            let glyph = BitmapGlyph {
+                width: w as u32,
+                height: h as u32,
+                x_min: bbox[2],
+                y_min: bbox[3],
+                advance: advance.unwrap_or(bbox[0] + bbox[2]),
+                gray,
+            };
+            return Ok((encoding, glyph));
+        }
+        if in_bitmap {
+            rows.push(decode_hex(line).ok_or_else(|| format!("Invalid BDF bitmap row: {}", line))?);
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let values = |parts: std::str::SplitWhitespace| parts.filter_map(|v| v.parse::<i32>().ok()).collect::<Vec<_>>();
+        match parts.next() {
+            // `ENCODING -1 n` marks a glyph outside the standard encoding.
+            Some("ENCODING") => encoding = values(parts).first().and_then(|v| u32::try_from(*v).ok()),
+            Some("DWIDTH") => advance = values(parts).first().copied(),
+            Some("BBX") => {
+                let v = values(parts);
+                if v.len() == 4 {
+                    bbox = [v[0], v[1], v[2], v[3]];
+                }
+            }
+            Some("BITMAP") => in_bitmap = true,
+            _ => {}
+        }
+    }
+    Err("BDF glyph is missing ENDCHAR".to_string())
+}
+
+/// Accepts the XLFD charsets whose codes are Unicode codepoints: ISO10646, ISO8859-1, or none.
+fn check_charset(format: &str, registry: &str, encoding: &str) -> Result<(), String> {
+    let unicode = match registry.to_ascii_uppercase().as_str() {
+        "" | "ISO10646" => true,
+        "ISO8859" => encoding.is_empty() || encoding == "1",
+        _ => false,
+    };
+    if unicode {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} font charset {}-{} is not Unicode or Latin-1 (ISO8859-1)",
+            format, registry, encoding
+        ))
+    }
+}
+
+fn decode_hex(line: &str) -> Option<Vec<u8>> {
+    let digits = line.as_bytes();
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').replace("\"\"", "\"")
+}
+
+struct PsfCells<'a> {
+    data: &'a [u8],
+    glyph_count: usize,
+    glyph_size: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Builds a PSF font; without a Unicode table glyph N is taken to be codepoint N.
+fn finish_psf(
+    format: &'static str,
+    cells: PsfCells,
+    unicode: Option<Vec<Vec<u32>>>,
+) -> Result<BitmapFont, String> {
+    let row_bytes = cells.width.div_ceil(8);
+    // Console fonts carry no baseline; a quarter of the cell below it matches VGA 8x16 fonts.
+    let descent = (cells.height / 4) as i32;
+    let mut builder = FontBuilder::new();
+    for i in 0..cells.glyph_count {
+        let bytes = cells
+            .data
+            .get(i * cells.glyph_size..(i + 1) * cells.glyph_size)
+            .ok_or_else(|| format!("{} glyph data is truncated", format))?;
+        let gray = unpack_rows(bytes, cells.width, cells.height, row_bytes)
+            .ok_or_else(|| format!("{} glyph data is truncated", format))?;
+        let index = builder.push(BitmapGlyph {
+            width: cells.width as u32,
+            height: cells.height as u32,
+            x_min: 0,
+            y_min: -descent,
+            advance: cells.width as i32,
+            gray,
+        })?;
+        match &unicode {
+            Some(table) => {
+                for cp in table.get(i).into_iter().flatten() {
+                    builder.map(*cp, index);
+                }
+            }
+            None => builder.map(i as u32, index),
+        }
+    }
+    let height = cells.height as i32;
+    builder.finish(format, String::new(), None, cells.height as u32, height - descent, descent)
+}
+
+fn parse_psf1(data: &[u8]) -> Result<BitmapFont, String> {
+    let mode = *data.get(2).ok_or("PSF1 header is truncated")?;
+    let height = *data.get(3).ok_or("PSF1 header is truncated")? as usize;
+    let glyph_count = if mode & 0x01 != 0 { 512 } else { 256 };
+    let glyphs_end = 4 + glyph_count * height;
+    let unicode = if mode & 0x06 != 0 {
+        let table = data.get(glyphs_end..).ok_or("PSF1 Unicode table is missing")?;
+        let mut entries = vec![Vec::new(); glyph_count];
+        let mut values = table.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+        for entry in entries.iter_mut() {
+            let mut in_sequence = false;
+            for value in values.by_ref() {
+                match value {
+                    0xFFFF => break,
+                    0xFFFE => in_sequence = true,
+                    cp if !in_sequence => entry.push(cp as u32),
+                    _ => {}
+                }
+            }
+        }
+        Some(entries)
+    } else {
+        None
+    };
+    let cells = PsfCells {
+        data: &data[4.min(data.len())..],
+        glyph_count,
+        glyph_size: height,
+        width: 8,
+        height,
+    };
+    finish_psf("PSF1", cells, unicode)
+}
+
+fn parse_psf2(data: &[u8]) -> Result<BitmapFont, String> {
+    let field = |i: usize| -> Result<usize, String> {
+        data.get(i * 4..i * 4 + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| "PSF2 header is truncated".to_string())
+    };
+    let header_size = field(2)?;
+    let flags = field(3)?;
+    let glyph_count = field(4)?;
+    let glyph_size = field(5)?;
+    let height = field(6)?;
+    let width = field(7)?;
+    let glyph_data = data.get(header_size..).ok_or("PSF2 glyph data is missing")?;
+    let unicode = if flags & 0x01 != 0 {
+        let table = glyph_data
+            .get(glyph_count * glyph_size..)
+            .ok_or("PSF2 Unicode table is missing")?;
+        let mut entries = vec![Vec::new(); glyph_count];
+        let mut records = table.split(|b| *b == 0xFF);
+        for entry in entries.iter_mut() {
+            let Some(record) = records.next() else { break };
+            // Single codepoints come first; 0xFE introduces multi-codepoint sequences.
+            let singles = record.split(|b| *b == 0xFE).next().unwrap_or(&[]);
+            entry.extend(String::from_utf8_lossy(singles).chars().map(|c| c as u32));
+        }
+        Some(entries)
+    } else {
+        None
+    };
+    let cells = PsfCells {
+        data: glyph_data,
+        glyph_count,
+        glyph_size,
+        width,
+        height,
+    };
+    finish_psf("PSF2", cells, unicode)
+}
+
+fn parse_fnt(data: &[u8]) -> Result<BitmapFont, String> {
+    let u8_at = |o: usize| data.get(o).copied().ok_or_else(|| "FNT header is truncated".to_string());
+    let u16_at = |o: usize| -> Result<u16, String> { Ok(u16::from_le_bytes([u8_at(o)?, u8_at(o + 1)?])) };
+    let u32_at = |o: usize| -> Result<u32, String> { Ok(u16_at(o)? as u32 | (u16_at(o + 2)? as u32) << 16) };
+    let c_string = |o: usize| {
+        let bytes = data.get(o..).unwrap_or(&[]);
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+    };
+
+    let version = u16_at(0)?;
+    if u16_at(66)? & 0x01 != 0 {
+        return Err("FNT vector fonts are not supported".to_string());
+    }
+    let ascent = u16_at(74)? as i32;
+    let charset = u8_at(85)?;
+    let height = u16_at(88)? as usize;
+    let first_char = u8_at(95)? as u32;
+    let last_char = u8_at(96)? as u32;
+    let face_offset = u32_at(105)? as usize;
+    let (table_offset, entry_size) = if version >= 0x300 { (148, 6) } else { (118, 4) };
+
+    let mut builder = FontBuilder::new();
+    for (i, byte) in (first_char..=last_char).enumerate() {
+        let entry = table_offset + i * entry_size;
+        let width = u16_at(entry)? as usize;
+        let offset = if version >= 0x300 { u32_at(entry + 2)? } else { u16_at(entry + 2)? as u32 } as usize;
+        // Glyph bits are stored in byte-wide columns, each `height` bytes tall.
+        let columns = width.div_ceil(8);
+        let bits = data
+            .get(offset..offset + columns * height)
+            .ok_or_else(|| format!("FNT glyph {} is out of bounds", byte))?;
+        let mut packed = vec![0u8; columns * height];
+        for col in 0..columns {
+            for row in 0..height {
+                packed[row * columns + col] = bits[col * height + row];
+            }
+        }
+        let gray = unpack_rows(&packed, width, height, columns).unwrap_or_default();
+        let index = builder.push(BitmapGlyph {
+            width: width as u32,
+            height: height as u32,
+            x_min: 0,
+            y_min: ascent - height as i32,
+            advance: width as i32,
+            gray,
+        })?;
+        builder.map(fnt_codepoint(byte as u8, charset), index);
+    }
+    let copyright = Some(c_string(6)).filter(|s| !s.is_empty());
+    let family = if face_offset != 0 { c_string(face_offset) } else { String::new() };
+    builder.finish("FNT", family, copyright, height as u32, ascent, height as i32 - ascent)
+}
+
+/// Maps an ANSI (charset 0, Windows-1252) byte to Unicode; other charsets are taken as Latin-1.
+fn fnt_codepoint(byte: u8, charset: u8) -> u32 {
+    const CP1252_80_9F: [u16; 32] = [
+        0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039, 0x0152,
+        0x008D, 0x017D, 0x008F, 0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC, 0x2122,
+        0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178,
+    ];
+    match byte {
+        0x80..=0x9F if charset == 0 => CP1252_80_9F[(byte - 0x80) as usize] as u32,
+        _ => byte as u32,
+    }
+}
+
+const PCF_PROPERTIES: u32 = 1 << 0;
+const PCF_ACCELERATORS: u32 = 1 << 1;
+const PCF_METRICS: u32 = 1 << 2;
+const PCF_BITMAPS: u32 = 1 << 3;
+const PCF_BDF_ENCODINGS: u32 = 1 << 5;
+const PCF_BDF_ACCELERATORS: u32 = 1 << 8;
+const PCF_BYTE_MASK: u32 = 1 << 2;
+const PCF_BIT_MASK: u32 = 1 << 3;
+const PCF_COMPRESSED_METRICS: u32 = 0x100;
+
+/// One PCF table: its format word and a reader honouring the format's byte order.
+struct PcfTable<'a> {
+    format: u32,
+    data: &'a [u8],
+}
+
+impl<'a> PcfTable<'a> {
+    fn big_endian(&self) -> bool {
+        self.format & PCF_BYTE_MASK != 0
+    }
+
+    fn u8(&self, o: usize) -> Result<u8, String> {
+        self.data.get(o).copied().ok_or_else(|| "PCF table is truncated".to_string())
+    }
+
+    fn u16(&self, o: usize) -> Result<u16, String> {
+        let b = [self.u8(o)?, self.u8(o + 1)?];
+        Ok(if self.big_endian() { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) })
+    }
+
+    fn u32(&self, o: usize) -> Result<u32, String> {
+        let b = [self.u8(o)?, self.u8(o + 1)?, self.u8(o + 2)?, self.u8(o + 3)?];
+        Ok(if self.big_endian() { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    }
+}
+
+struct PcfMetrics {
+    lsb: i32,
+    rsb: i32,
+    advance: i32,
+    ascent: i32,
+    descent: i32,
+}
+
+fn parse_pcf(data: &[u8]) -> Result<BitmapFont, String> {
+    let le_u32 = |o: usize| -> Result<u32, String> {
+        data.get(o..o + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| "PCF table of contents is truncated".to_string())
+    };
+    let table_count = le_u32(4)? as usize;
+    let mut tables: BTreeMap<u32, PcfTable> = BTreeMap::new();
+    for i in 0..table_count {
+        let entry = 8 + i * 16;
+        let kind = le_u32(entry)?;
+        let size = le_u32(entry + 8)? as usize;
+        let offset = le_u32(entry + 12)? as usize;
+        let table = data.get(offset..offset + size).ok_or("PCF table is out of bounds")?;
+        // Every table repeats its format word, always little-endian, as its first field.
+        let format = le_u32(offset)?;
+        tables.insert(kind, PcfTable { format, data: &table[4.min(table.len())..] });
+    }
+    let table = |kind: u32, name: &str| tables.get(&kind).ok_or_else(|| format!("PCF font has no {} table", name));
+
+    let properties = tables.get(&PCF_PROPERTIES).map(pcf_properties).transpose()?.unwrap_or_default();
+    check_charset(
+        "PCF",
+        &pcf_text_property(&properties, "CHARSET_REGISTRY").unwrap_or_default(),
+        &pcf_text_property(&properties, "CHARSET_ENCODING").unwrap_or_default(),
+    )?;
+    let metrics = pcf_metrics(table(PCF_METRICS, "metrics")?)?;
+    let bitmaps = table(PCF_BITMAPS, "bitmaps")?;
+    let encodings = table(PCF_BDF_ENCODINGS, "encodings")?;
+
+    let mut builder = FontBuilder::new();
+    let mut glyph_indices = Vec::with_capacity(metrics.len());
+    for (i, m) in metrics.iter().enumerate() {
+        let gray = pcf_glyph_bitmap(bitmaps, i, m)?;
+        glyph_indices.push(builder.push(BitmapGlyph {
+            width: (m.rsb - m.lsb).max(0) as u32,
+            height: (m.ascent + m.descent).max(0) as u32,
+            x_min: m.lsb,
+            y_min: -m.descent,
+            advance: m.advance,
+            gray,
+        })?);
+    }
+
+    let min_byte2 = encodings.u16(0)? as u32;
+    let max_byte2 = encodings.u16(2)? as u32;
+    let min_byte1 = encodings.u16(4)? as u32;
+    let max_byte1 = encodings.u16(6)? as u32;
+    if max_byte2 < min_byte2 || max_byte1 < min_byte1 {
+        return Err("PCF encodings table has inverted byte bounds".to_string());
+    }
+    let mut slot = 0usize;
+    for byte1 in min_byte1..=max_byte1 {
+        for byte2 in min_byte2..=max_byte2 {
+            let glyph = encodings.u16(10 + slot * 2)?;
+            slot += 1;
+            if let Some(index) = glyph_indices.get(glyph as usize).filter(|_| glyph != 0xFFFF) {
+                builder.map((byte1 << 8) | byte2, *index);
+            }
+        }
+    }
+
+    let accelerators = tables.get(&PCF_BDF_ACCELERATORS).or_else(|| tables.get(&PCF_ACCELERATORS));
+    let (ascent, descent) = match accelerators {
+        Some(acc) => (acc.u32(8)? as i32, acc.u32(12)? as i32),
+        None => (
+            pcf_int_property(&properties, "FONT_ASCENT").unwrap_or(0),
+            pcf_int_property(&properties, "FONT_DESCENT").unwrap_or(0),
+        ),
+    };
+    let pixel_size = pcf_int_property(&properties, "PIXEL_SIZE").unwrap_or(ascent + descent).max(1) as u32;
+    let family = pcf_text_property(&properties, "FAMILY_NAME").unwrap_or_default();
+    let copyright = pcf_text_property(&properties, "COPYRIGHT");
+    builder.finish("PCF", family, copyright, pixel_size, ascent, descent)
+}
+
+enum PcfProperty {
+    Int(i32),
+    Text(String),
+}
+
+fn pcf_int_property(properties: &BTreeMap<String, PcfProperty>, name: &str) -> Option<i32> {
+    match properties.get(name) {
+        Some(PcfProperty::Int(v)) => Some(*v),
+        _ => None,
+    }
+}
+
+fn pcf_text_property(properties: &BTreeMap<String, PcfProperty>, name: &str) -> Option<String> {
+    match properties.get(name) {
+        Some(PcfProperty::Text(text)) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+fn pcf_properties(table: &PcfTable) -> Result<BTreeMap<String, PcfProperty>, String> {
+    let count = table.u32(0)? as usize;
+    let padding = if count & 3 == 0 { 0 } else { 4 - (count & 3) };
+    let strings_at = 4 + count * 9 + padding + 4;
+    let string = |offset: usize| {
+        let bytes = table.data.get(strings_at + offset..).unwrap_or(&[]);
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).to_string()
+    };
+    let mut properties = BTreeMap::new();
+    for i in 0..count {
+        let record = 4 + i * 9;
+        let name = string(table.u32(record)? as usize);
+        let value = table.u32(record + 5)?;
+        let property = if table.u8(record + 4)? != 0 {
+            PcfProperty::Text(string(value as usize))
+        } else {
+            PcfProperty::Int(value as i32)
+        };
+        properties.insert(name, property);
+    }
+    Ok(properties)
+}
+
+fn pcf_metrics(table: &PcfTable) -> Result<Vec<PcfMetrics>, String> {
+    if table.format & PCF_COMPRESSED_METRICS != 0 {
+        let count = table.u16(0)? as usize;
+        (0..count)
+            .map(|i| {
+                let v = |field: usize| -> Result<i32, String> { Ok(table.u8(2 + i * 5 + field)? as i32 - 0x80) };
+                Ok(PcfMetrics { lsb: v(0)?, rsb: v(1)?, advance: v(2)?, ascent: v(3)?, descent: v(4)? })
+            })
+            .collect()
+    } else {
+        let count = table.u32(0)? as usize;
+        (0..count)
+            .map(|i| {
+                let v = |field: usize| -> Result<i32, String> { Ok(table.u16(4 + i * 12 + field * 2)? as i16 as i32) };
+                Ok(PcfMetrics { lsb: v(0)?, rsb: v(1)?, advance: v(2)?, ascent: v(3)?, descent: v(4)? })
+            })
+            .collect()
+    }
+}
+
+fn pcf_glyph_bitmap(table: &PcfTable, index: usize, m: &PcfMetrics) -> Result<Vec<u8>, String> {
+    let count = table.u32(0)? as usize;
+    if index >= count {
+        return Err("PCF bitmap table has fewer glyphs than the metrics table".to_string());
+    }
+    let offset = table.u32(4 + index * 4)? as usize;
+    let data_start = 4 + count * 4 + 16;
+    let width = (m.rsb - m.lsb).max(0) as usize;
+    let height = (m.ascent + m.descent).max(0) as usize;
+    let pad = 1usize << (table.format & 3);
+    let row_bytes = width.div_ceil(8).div_ceil(pad) * pad;
+    let mut rows = table
+        .data
+        .get(data_start + offset..data_start + offset + row_bytes * height)
+        .ok_or("PCF glyph bitmap is out of bounds")?
+        .to_vec();
+
+    // Normalize to MSB-first bits in MSB-first bytes within each scan unit.
+    let scan_unit = 1usize << ((table.format >> 4) & 3);
+    let msb_bytes = table.format & PCF_BYTE_MASK != 0;
+    let msb_bits = table.format & PCF_BIT_MASK != 0;
+    if scan_unit > 1 && msb_bytes != msb_bits {
+        for unit in rows.chunks_mut(scan_unit) {
+            unit.reverse();
+        }
+    }
+    if !msb_bits {
+        for byte in rows.iter_mut() {
+            *byte = byte.reverse_bits();
+        }
+    }
+    unpack_rows(&rows, width, height, row_bytes).ok_or_else(|| "PCF glyph bitmap is truncated".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(font: &BitmapFont, codepoint: u32) -> &BitmapGlyph {
+        font.glyph(font.glyph_index(codepoint)).unwrap_or_else(|| panic!("U+{:04X} is not mapped", codepoint))
+    }
+
+    fn bdf(registry: &str, encoding: &str) -> Vec<u8> {
+        format!(
+            "STARTFONT 2.1\nFONTBOUNDINGBOX 2 2 0 0\nSTARTPROPERTIES 2\nCHARSET_REGISTRY \"{}\"\n\
+             CHARSET_ENCODING \"{}\"\nENDPROPERTIES\nCHARS 1\nSTARTCHAR e\nENCODING 233\nDWIDTH 3 0\n\
+             BBX 2 2 0 0\nBITMAP\n80\n40\nENDCHAR\nENDFONT\n",
+            registry, encoding
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn bdf_accepts_only_unicode_charsets() {
+        for (registry, encoding) in [("ISO10646", "1"), ("ISO8859", "1"), ("", "")] {
+            let font = BitmapFont::parse(&bdf(registry, encoding)).unwrap();
+            assert_eq!(glyph(&font, 0xE9).gray, [255, 0, 0, 255]);
+        }
+        let err = BitmapFont::parse(&bdf("ISO8859", "2")).unwrap_err();
+        assert!(err.contains("charset ISO8859-2"), "{}", err);
+        assert!(BitmapFont::parse(&bdf("KOI8", "R")).is_err());
+    }
+
+    /// Big-endian, MSB-first PCF with one 3x2 glyph at 0x41, 0x42 unmapped and `properties` as
+    /// text properties.
+    fn pcf(properties: &[(&str, &str)], byte2_bounds: [u16; 2]) -> Vec<u8> {
+        const FORMAT: u32 = PCF_BYTE_MASK | PCF_BIT_MASK;
+        let be16 = |out: &mut Vec<u8>, v: u16| out.extend_from_slice(&v.to_be_bytes());
+        let be32 = |out: &mut Vec<u8>, v: u32| out.extend_from_slice(&v.to_be_bytes());
+
+        let mut props = Vec::new();
+        let mut strings = Vec::new();
+        be32(&mut props, properties.len() as u32);
+        for (name, value) in properties {
+            be32(&mut props, strings.len() as u32);
+            strings.extend_from_slice(name.as_bytes());
+            strings.push(0);
+            props.push(1);
+            be32(&mut props, strings.len() as u32);
+            strings.extend_from_slice(value.as_bytes());
+            strings.push(0);
+        }
+        props.resize(props.len().next_multiple_of(4), 0);
+        be32(&mut props, strings.len() as u32);
+        props.extend_from_slice(&strings);
+
+        let mut metrics = Vec::new();
+        be32(&mut metrics, 1);
+        for v in [0, 3, 4, 2, 0, 0] {
+            be16(&mut metrics, v);
+        }
+
+        let mut bitmaps = Vec::new();
+        be32(&mut bitmaps, 1);
+        be32(&mut bitmaps, 0);
+        for size in [2, 2, 2, 2] {
+            be32(&mut bitmaps, size);
+        }
+        bitmaps.extend_from_slice(&[0b1110_0000, 0b1010_0000]);
+
+        let mut encodings = Vec::new();
+        for v in [byte2_bounds[0], byte2_bounds[1], 0, 0, 0, 0, 0xFFFF] {
+            be16(&mut encodings, v);
+        }
+
+        let tables = [(PCF_PROPERTIES, props), (PCF_METRICS, metrics), (PCF_BITMAPS, bitmaps), (PCF_BDF_ENCODINGS, encodings)];
+        let mut out = PCF_MAGIC.to_vec();
+        out.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+        let mut offset = 8 + tables.len() * 16;
+        for (kind, table) in &tables {
+            for v in [*kind, FORMAT, table.len() as u32 + 4, offset as u32] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            offset += table.len() + 4;
+        }
+        for (_, table) in &tables {
+            out.extend_from_slice(&FORMAT.to_le_bytes());
+            out.extend_from_slice(table);
+        }
+        out
+    }
+
+    #[test]
+    fn pcf_reads_glyphs_properties_and_encodings() {
+        let props = [("FAMILY_NAME", "Tiny"), ("CHARSET_REGISTRY", "ISO10646"), ("CHARSET_ENCODING", "1")];
+        let font = BitmapFont::parse(&pcf(&props, [0x41, 0x42])).unwrap();
+        assert_eq!(font.family, "Tiny");
+        let a = glyph(&font, 0x41);
+        assert_eq!((a.width, a.height, a.x_min, a.y_min, a.advance), (3, 2, 0, 0, 4));
+        assert_eq!(a.gray, [255, 255, 255, 255, 0, 255]);
+        assert_eq!(font.glyph_index(0x42), 0);
+    }
+
+    #[test]
+    fn pcf_rejects_other_charsets_and_inverted_bounds() {
+        let err = BitmapFont::parse(&pcf(&[("CHARSET_REGISTRY", "ISO8859"), ("CHARSET_ENCODING", "5")], [0x41, 0x42]))
+            .unwrap_err();
+        assert!(err.contains("PCF font charset ISO8859-5"), "{}", err);
+        assert!(BitmapFont::parse(&pcf(&[("CHARSET_REGISTRY", "ISO8859"), ("CHARSET_ENCODING", "1")], [0x41, 0x42])).is_ok());
+        let err = BitmapFont::parse(&pcf(&[], [0x42, 0x41])).unwrap_err();
+        assert!(err.contains("inverted byte bounds"), "{}", err);
+    }
+
+    #[test]
+    fn fnt_reads_column_major_glyphs_and_cp1252() {
+        let mut data = vec![0u8; 118];
+        data[0..2].copy_from_slice(&0x200u16.to_le_bytes());
+        data[6..14].copy_from_slice(b"(c) test");
+        data[74..76].copy_from_slice(&2u16.to_le_bytes()); // ascent
+        data[85] = 0; // ANSI charset
+        data[88..90].copy_from_slice(&2u16.to_le_bytes()); // pixel height
+        data[95] = 0x80;
+        data[96] = 0x81;
+        let face = 118 + 2 * 4;
+        data[105..109].copy_from_slice(&(face as u32).to_le_bytes());
+        let glyphs = face + 5;
+        for (width, offset) in [(9u16, glyphs), (1, glyphs + 4)] {
+            data.extend_from_slice(&width.to_le_bytes());
+            data.extend_from_slice(&(offset as u16).to_le_bytes());
+        }
+        data.extend_from_slice(b"Tiny\0");
+        // 0x80: a 9px line on the top row, stored as two byte-wide columns.
+        data.extend_from_slice(&[0xFF, 0x00, 0x80, 0x00]);
+        // 0x81: a 1px column.
+        data.extend_from_slice(&[0x80, 0x80]);
+
+        let font = BitmapFont::parse(&data).unwrap();
+        assert_eq!(font.family, "Tiny");
+        assert_eq!(font.copyright.as_deref(), Some("(c) test"));
+        let euro = glyph(&font, 0x20AC);
+        assert_eq!((euro.width, euro.height, euro.y_min, euro.advance), (9, 1, 1, 9));
+        assert_eq!(euro.gray, [255; 9]);
+        assert_eq!(glyph(&font, 0x81).gray, [255, 255]);
+        assert_eq!(font.glyph_index(0x80), 0);
+    }
+
+    #[test]
+    fn psf1_maps_glyph_numbers_or_its_unicode_table() {
+        let mut data = vec![0x36, 0x04, 0x00, 2];
+        data.resize(4 + 256 * 2, 0);
+        data[4 + 0x41 * 2..4 + 0x41 * 2 + 2].copy_from_slice(&[0x80, 0x40]);
+        let font = BitmapFont::parse(&data).unwrap();
+        let a = glyph(&font, 0x41);
+        assert_eq!((a.width, a.height, a.advance), (2, 2, 8));
+        assert_eq!(a.gray, [255, 0, 0, 255]);
+
+        data[2] = 0x02;
+        for i in 0..256 {
+            if i == 0x41 {
+                data.extend_from_slice(&0x0391u16.to_le_bytes());
+            }
+            data.extend_from_slice(&0xFFFFu16.to_le_bytes());
+        }
+        let font = BitmapFont::parse(&data).unwrap();
+        assert_eq!(glyph(&font, 0x0391).gray, [255, 0, 0, 255]);
+        assert_eq!(font.glyph_index(0x41), 0);
+    }
+
+    #[test]
+    fn psf2_reads_wide_cells_and_utf8_unicode_table() {
+        let mut data = PSF2_MAGIC.to_vec();
+        // version, headersize, flags (unicode table), length, charsize, height, width
+        for v in [0u32, 32, 1, 2, 4, 2, 10] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x00]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0xC0]);
+        data.extend_from_slice(b"A\xFF");
+        data.extend_from_slice("\u{E9}".as_bytes());
+        data.extend_from_slice(b"\xFEe\xCC\x81\xFF");
+
+        let font = BitmapFont::parse(&data).unwrap();
+        let a = glyph(&font, 0x41);
+        assert_eq!((a.width, a.height, a.advance), (10, 1, 10));
+        assert_eq!(a.gray, [255; 10]);
+        let e = glyph(&font, 0xE9);
+        assert_eq!((e.width, e.x_min, e.gray.as_slice()), (2, 8, &[255, 255][..]));
+        assert_eq!(font.glyph_index(0x65), 0);
+    }
+}
@@ -0,0 +1,222 @@
+use ttf_parser::{Face, GlyphId, RasterImageFormat, Tag};
+
+// Size of a BitmapSize record in EBLC/CBLC and the offsets of its fields that are read here.
+const BITMAP_SIZE_RECORD_LEN: usize = 48;
+const BITMAP_SIZE_START_GLYPH: usize = 40;
+const BITMAP_SIZE_PPEM_X: usize = 44;
+// horiAdvance sits at the same offset in small and big glyph metrics.
+const METRICS_ADVANCE: usize = 4;
+
+pub struct StrikeGlyph {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Advance from the strike's own glyph metrics, which hand-tuned strikes may set apart from
+    /// the scaled outline advance.
+    pub advance: i32,
+    pub gray: Vec<u8>,
+}
+
+/// Lists the ppem sizes of the embedded bitmap strikes (EBLC, falling back to CBLC).
+pub fn strike_sizes(face: &Face) -> Vec<u16> {
+    let table = face
+        .raw_face()
+        .table(Tag::from_bytes(b"EBLC"))
+        .or_else(|| face.raw_face().table(Tag::from_bytes(b"CBLC")));
+    table.map(table_sizes).unwrap_or_default()
+}
+
+fn table_sizes(data: &[u8]) -> Vec<u16> {
+    if data.len() < 8 {
+        return Vec::new();
+    }
+    let num_sizes = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let mut sizes: Vec<u16> = (0..num_sizes)
+        .filter_map(|i| data.get(8 + i * BITMAP_SIZE_RECORD_LEN + BITMAP_SIZE_PPEM_X))
+        .map(|ppem| *ppem as u16)
+        .collect();
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+/// Returns the embedded bitmap for `glyph_index` when the font has a strike at exactly `size_px`.
+///
+/// Only monochrome and grayscale strikes are decoded; PNG and BGRA images are left to the outline
+/// rasterizer.
+pub fn strike_glyph(face: &Face, glyph_index: u16, size_px: u32) -> Option<StrikeGlyph> {
+    let ppem = u16::try_from(size_px).ok()?;
+    let image = face.glyph_raster_image(GlyphId(glyph_index), ppem)?;
+    if image.pixels_per_em != ppem {
+        return None;
+    }
+    let width = image.width as usize;
+    let height = image.height as usize;
+    let (bits, packed) = match image.format {
+        RasterImageFormat::BitmapMono => (1, false),
+        RasterImageFormat::BitmapMonoPacked => (1, true),
+        RasterImageFormat::BitmapGray2 => (2, false),
+        RasterImageFormat::BitmapGray2Packed => (2, true),
+        RasterImageFormat::BitmapGray4 => (4, false),
+        RasterImageFormat::BitmapGray4Packed => (4, true),
+        RasterImageFormat::BitmapGray8 => (8, false),
+        RasterImageFormat::PNG | RasterImageFormat::BitmapPremulBgra32 => return None,
+    };
+    let gray = decode_gray(image.data, width, height, bits, packed)?;
+    let raw = face.raw_face();
+    let (location, data) = match raw.table(Tag::from_bytes(b"EBLC")) {
+        Some(eblc) => (eblc, raw.table(Tag::from_bytes(b"EBDT"))?),
+        None => (raw.table(Tag::from_bytes(b"CBLC"))?, raw.table(Tag::from_bytes(b"CBDT"))?),
+    };
+    Some(StrikeGlyph {
+        x: image.x as i32,
+        y: image.y as i32,
+        width: width as u32,
+        height: height as u32,
+        advance: strike_advance(location, data, glyph_index, ppem)? as i32,
+        gray,
+    })
+}
+
+/// Reads the horizontal advance of `glyph_index` in the `ppem` strike: from the glyph's metrics
+/// in the data table, or from the index subtable when its glyphs share one set of big metrics.
+fn strike_advance(location: &[u8], data: &[u8], glyph_index: u16, ppem: u16) -> Option<u8> {
+    let u16_at = |table: &[u8], at: usize| Some(u16::from_be_bytes([*table.get(at)?, *table.get(at + 1)?]));
+    let u32_at = |table: &[u8], at: usize| {
+        let bytes = table.get(at..at + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+    let covers = |first: Option<u16>, last: Option<u16>| {
+        matches!((first, last), (Some(first), Some(last)) if (first..=last).contains(&glyph_index))
+    };
+    let num_sizes = u32_at(location, 4)?;
+    let record = (0..num_sizes).map(|i| 8 + i * BITMAP_SIZE_RECORD_LEN).find(|record| {
+        location.get(record + BITMAP_SIZE_PPEM_X).map(|p| *p as u16) == Some(ppem)
+            && covers(
+                u16_at(location, record + BITMAP_SIZE_START_GLYPH),
+                u16_at(location, record + BITMAP_SIZE_START_GLYPH + 2),
+            )
+    })?;
+
+    // IndexSubTableArray entries: firstGlyphIndex, lastGlyphIndex, offset from the array.
+    let array = u32_at(location, record)?;
+    let entry = (0..u32_at(location, record + 8)?)
+        .map(|i| array + i * 8)
+        .find(|entry| covers(u16_at(location, *entry), u16_at(location, entry + 2)))?;
+    let index = (glyph_index - u16_at(location, entry)?) as usize;
+    let subtable = array + u32_at(location, entry + 4)?;
+    let image_data = u32_at(location, subtable + 4)?;
+    let glyph_offset = match u16_at(location, subtable)? {
+        1 => u32_at(location, subtable + 8 + index * 4)?,
+        3 => u16_at(location, subtable + 8 + index * 2)? as usize,
+        4 => {
+            let count = u32_at(location, subtable + 8)?;
+            let pair = (0..count)
+                .map(|i| subtable + 12 + i * 4)
+                .find(|pair| u16_at(location, *pair) == Some(glyph_index))?;
+            u16_at(location, pair + 2)? as usize
+        }
+        // imageSize, then big metrics shared by every glyph of the subtable.
+        2 | 5 => return location.get(subtable + 12 + METRICS_ADVANCE).copied(),
+        _ => return None,
+    };
+    data.get(image_data + glyph_offset + METRICS_ADVANCE).copied()
+}
+
+fn decode_gray(data: &[u8], width: usize, height: usize, bits: usize, packed: bool) -> Option<Vec<u8>> {
+    let row_bits = width * bits;
+    let max_value = (1u32 << bits) - 1;
+    let mut gray = vec![0u8; width * height];
+    for y in 0..height {
+        let row_start = if packed { y * row_bits } else { y * row_bits.div_ceil(8) * 8 };
+        for x in 0..width {
+            let bit = row_start + x * bits;
+            let byte = *data.get(bit / 8)?;
+            let shift = 8 - bits - (bit % 8);
+            let value = (byte as u32 >> shift) & max_value;
+            gray[y * width + x] = (value * 255 / max_value) as u8;
+        }
+    }
+    Some(gray)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap_size_record(ppem: u8) -> [u8; BITMAP_SIZE_RECORD_LEN] {
+        let mut record = [0u8; BITMAP_SIZE_RECORD_LEN];
+        record[BITMAP_SIZE_PPEM_X] = ppem;
+        // ppemY and bitDepth follow ppemX; keep them distinct so a wrong offset shows up.
+        record[BITMAP_SIZE_PPEM_X + 1] = ppem + 1;
+        record[BITMAP_SIZE_PPEM_X + 2] = 1;
+        record
+    }
+
+    #[test]
+    fn strike_sizes_read_ppem_x_of_each_record() {
+        let mut eblc = vec![0, 2, 0, 0, 0, 0, 0, 4];
+        for ppem in [16, 12, 16] {
+            eblc.extend_from_slice(&bitmap_size_record(ppem));
+        }
+        // The header claims a fourth record that the table does not contain.
+        assert_eq!(table_sizes(&eblc), vec![12, 16]);
+        assert_eq!(table_sizes(&eblc[..6]), Vec::<u16>::new());
+    }
+
+    /// EBLC with one 12px strike of glyphs 1-4: glyphs 1-2 in an index format 1 subtable with
+    /// their own metrics in EBDT, glyphs 3-4 in an index format 2 subtable of shared metrics.
+    fn strike_tables() -> (Vec<u8>, Vec<u8>) {
+        let mut record = [0u8; BITMAP_SIZE_RECORD_LEN];
+        record[..4].copy_from_slice(&56u32.to_be_bytes());
+        record[11] = 2;
+        record[BITMAP_SIZE_START_GLYPH..BITMAP_SIZE_START_GLYPH + 4].copy_from_slice(&[0, 1, 0, 4]);
+        record[BITMAP_SIZE_PPEM_X] = 12;
+        let mut eblc = vec![0, 2, 0, 0, 0, 0, 0, 1];
+        eblc.extend_from_slice(&record);
+        // Index subtable array, then the subtables 16 and 36 bytes past it.
+        eblc.extend_from_slice(&[0, 1, 0, 2, 0, 0, 0, 16, 0, 3, 0, 4, 0, 0, 0, 36]);
+        eblc.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 12]);
+        eblc.extend_from_slice(&[0, 2, 0, 5, 0, 0, 0, 16, 0, 0, 0, 2, 1, 1, 0, 1, 9, 0, 0, 0]);
+
+        // Small metrics (height, width, bearingX, bearingY, advance) and one byte of image each.
+        let ebdt = vec![0, 2, 0, 0, 1, 1, 0, 1, 5, 0x80, 1, 1, 0, 1, 7, 0x80];
+        (eblc, ebdt)
+    }
+
+    #[test]
+    fn strike_advance_reads_glyph_and_shared_metrics() {
+        let (eblc, ebdt) = strike_tables();
+        assert_eq!(strike_advance(&eblc, &ebdt, 1, 12), Some(5));
+        assert_eq!(strike_advance(&eblc, &ebdt, 2, 12), Some(7));
+        assert_eq!(strike_advance(&eblc, &ebdt, 3, 12), Some(9));
+        assert_eq!(strike_advance(&eblc, &ebdt, 4, 12), Some(9));
+        assert_eq!(strike_advance(&eblc, &ebdt, 5, 12), None);
+        assert_eq!(strike_advance(&eblc, &ebdt, 1, 16), None);
+        assert_eq!(strike_advance(&eblc, &ebdt[..8], 1, 12), None);
+    }
+
+    #[test]
+    fn decode_gray_unpacks_every_format() {
+        let mono = [255, 0, 255, 0, 255, 0];
+        assert_eq!(decode_gray(&[0b1010_0000, 0b0100_0000], 3, 2, 1, false), Some(mono.to_vec()));
+        assert_eq!(decode_gray(&[0b1010_1000], 3, 2, 1, true), Some(mono.to_vec()));
+
+        let gray2 = [255, 85, 0, 0, 170, 255];
+        assert_eq!(decode_gray(&[0b1101_0000, 0b0010_1100], 3, 2, 2, false), Some(gray2.to_vec()));
+        assert_eq!(decode_gray(&[0b1101_0000, 0b1011_0000], 3, 2, 2, true), Some(gray2.to_vec()));
+
+        let gray4 = [255, 136, 0, 17, 34, 51];
+        assert_eq!(decode_gray(&[0xF8, 0x00, 0x12, 0x30], 3, 2, 4, false), Some(gray4.to_vec()));
+        assert_eq!(decode_gray(&[0xF8, 0x01, 0x23], 3, 2, 4, true), Some(gray4.to_vec()));
+
+        assert_eq!(decode_gray(&[7, 8, 9, 10], 2, 2, 8, false), Some(vec![7, 8, 9, 10]));
+    }
+
+    #[test]
+    fn decode_gray_rejects_truncated_data() {
+        assert_eq!(decode_gray(&[0b1010_0000], 3, 2, 1, false), None);
+        assert_eq!(decode_gray(&[0xF8, 0x01], 3, 2, 4, true), None);
+    }
+}
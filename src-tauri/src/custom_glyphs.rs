@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::png_io::decode_png;
+
+/// A PNG image placed in the font at `codepoint`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomGlyphSpec {
+    pub codepoint: u32,
+    pub path: String,
+    /// Image rows that hang below the baseline.
+    #[serde(default)]
+    pub baseline_offset: i32,
+    /// Horizontal advance; defaults to the image width plus one pixel.
+    pub advance: Option<u32>,
+}
+
+pub struct CustomGlyph {
+    pub codepoint: u32,
+    pub width: u32,
+    pub height: u32,
+    pub y_min: i32,
+    pub advance: i32,
+    /// Coverage from the alpha channel, or from darkness on opaque images.
+    pub gray: Vec<u8>,
+}
+
+pub fn load_custom_glyph(spec: &CustomGlyphSpec) -> Result<CustomGlyph, String> {
+    if char::from_u32(spec.codepoint).is_none() {
+        return Err(format!("Custom glyph codepoint U+{:04X} is not a character", spec.codepoint));
+    }
+    let data = fs::read(&spec.path).map_err(|e| format!("Failed to read custom glyph {}: {}", spec.path, e))?;
+    let image = decode_png(&data).map_err(|e| format!("Failed to load custom glyph {}: {}", spec.path, e))?;
+    let use_alpha = image.has_transparency();
+    let gray = image
+        .pixels
+        .chunks_exact(4)
+        .map(|p| {
+            if use_alpha {
+                p[3]
+            } else {
+                255 - ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8
+            }
+        })
+        .collect();
+    Ok(CustomGlyph {
+        codepoint: spec.codepoint,
+        width: image.width,
+        height: image.height,
+        y_min: -spec.baseline_offset,
+        advance: spec.advance.unwrap_or(image.width + 1) as i32,
+        gray,
+    })
+}
@@ -0,0 +1,436 @@
+// Self-describing font blob for external flash.
+//
+// Layout (all integers in the blob's endianness, flagged in the header):
+//   header       HEADER_SIZE bytes, see `write_blob`
+//   glyph table  glyph_count * GLYPH_RECORD_SIZE
+//   range table  range_count * RANGE_RECORD_SIZE
+//   bitmaps      1-bit packed, row-major, MSB-first
+//   metadata     UTF-8 `key=value` lines describing how the blob was generated
+// Each section starts at a multiple of the configured alignment. The header CRC-32 covers the
+// glyph table through the end of the bitmaps.
+
+pub const MAGIC: [u8; 4] = *b"CBFN";
+pub const VERSION: u16 = 2;
+pub const HEADER_SIZE: usize = 64;
+pub const GLYPH_RECORD_SIZE: usize = 16;
+pub const RANGE_RECORD_SIZE: usize = 12;
+const FLAG_BIG_ENDIAN: u8 = 0x01;
+const NO_FALLBACK: u32 = u32::MAX;
+const CRC_OFFSET: usize = 52;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobGlyph {
+    pub bitmap_offset: u32,
+    pub width: u16,
+    pub height: u16,
+    pub x_advance: i16,
+    pub x_offset: i16,
+    pub y_offset: i16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobRange {
+    pub start: u32,
+    pub length: u32,
+    pub glyph_id_start: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobFont {
+    pub line_height: i16,
+    pub baseline: i16,
+    pub max_w: u16,
+    pub max_h: u16,
+    pub fallback_index: Option<u32>,
+    pub glyphs: Vec<BlobGlyph>,
+    pub ranges: Vec<BlobRange>,
+    pub bitmaps: Vec<u8>,
+    pub metadata: String,
+}
+
+pub struct BlobLayout {
+    pub big_endian: bool,
+    pub alignment: usize,
+}
+
+/// Section offsets of a written blob, also emitted into the C header.
+pub struct BlobSections {
+    pub glyph_table: usize,
+    pub range_table: usize,
+    pub bitmaps: usize,
+    pub metadata: usize,
+    pub total: usize,
+}
+
+impl BlobLayout {
+    pub fn sections(&self, font: &BlobFont) -> BlobSections {
+        let glyph_table = align_up(HEADER_SIZE, self.alignment);
+        let range_table = align_up(glyph_table + font.glyphs.len() * GLYPH_RECORD_SIZE, self.alignment);
+        let bitmaps = align_up(range_table + font.ranges.len() * RANGE_RECORD_SIZE, self.alignment);
+        let metadata = align_up(bitmaps + font.bitmaps.len(), self.alignment);
+        BlobSections {
+            glyph_table,
+            range_table,
+            bitmaps,
+            metadata,
+            total: metadata + font.metadata.len(),
+        }
+    }
+}
+
+struct BlobWriter {
+    buf: Vec<u8>,
+    big_endian: bool,
+}
+
+impl BlobWriter {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u16(&mut self, v: u16) {
+        let b = if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.bytes(&b);
+    }
+
+    fn i16(&mut self, v: i16) {
+        self.u16(v as u16);
+    }
+
+    fn u32(&mut self, v: u32) {
+        let b = if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.bytes(&b);
+    }
+
+    fn pad_to(&mut self, offset: usize) {
+        self.buf.resize(offset, 0);
+    }
+
+    fn patch_u32(&mut self, offset: usize, v: u32) {
+        let b = if self.big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.buf[offset..offset + 4].copy_from_slice(&b);
+    }
+}
+
+/// Serializes the blob; returns the bytes and the CRC-32 stored in its header.
+pub fn write_blob(font: &BlobFont, layout: &BlobLayout) -> Result<(Vec<u8>, u32), String> {
+    if !layout.alignment.is_power_of_two() || layout.alignment > 255 {
+        return Err(format!("Invalid blob alignment {}: must be a power of two up to 128", layout.alignment));
+    }
+    let sections = layout.sections(font);
+    let to_u32 = |v: usize, what: &str| {
+        u32::try_from(v).map_err(|_| format!("Blob {} too large: {}", what, v))
+    };
+
+    let mut w = BlobWriter {
+        buf: Vec::with_capacity(sections.total),
+        big_endian: layout.big_endian,
+    };
+    w.bytes(&MAGIC);
+    w.u16(VERSION);
+    w.u8(if layout.big_endian { FLAG_BIG_ENDIAN } else { 0 });
+    w.u8(layout.alignment as u8);
+    w.u32(HEADER_SIZE as u32);
+    w.u32(to_u32(font.glyphs.len(), "glyph count")?);
+    w.u32(to_u32(font.ranges.len(), "range count")?);
+    w.u32(to_u32(sections.glyph_table, "glyph table offset")?);
+    w.u32(to_u32(sections.range_table, "range table offset")?);
+    w.u32(to_u32(sections.bitmaps, "bitmap offset")?);
+    w.u32(to_u32(font.bitmaps.len(), "bitmap size")?);
+    w.i16(font.line_height);
+    w.i16(font.baseline);
+    w.u16(font.max_w);
+    w.u16(font.max_h);
+    w.u32(font.fallback_index.unwrap_or(NO_FALLBACK));
+    w.u32(to_u32(sections.total, "size")?);
+    w.u32(0); // CRC-32, patched once the covered sections are written
+    w.u32(to_u32(sections.metadata, "metadata offset")?);
+    w.u32(to_u32(font.metadata.len(), "metadata size")?);
+    debug_assert_eq!(w.buf.len(), HEADER_SIZE);
+
+    w.pad_to(sections.glyph_table);
+    for g in &font.glyphs {
+        w.u32(g.bitmap_offset);
+        w.u16(g.width);
+        w.u16(g.height);
+        w.i16(g.x_advance);
+        w.i16(g.x_offset);
+        w.i16(g.y_offset);
+        w.u16(0);
+    }
+
+    w.pad_to(sections.range_table);
+    for r in &font.ranges {
+        w.u32(r.start);
+        w.u32(r.length);
+        w.u32(r.glyph_id_start);
+    }
+
+    w.pad_to(sections.bitmaps);
+    w.bytes(&font.bitmaps);
+    let crc = crc32fast::hash(&w.buf[sections.glyph_table..]);
+    w.patch_u32(CRC_OFFSET, crc);
+
+    w.pad_to(sections.metadata);
+    w.bytes(font.metadata.as_bytes());
+    Ok((w.buf, crc))
+}
+
+/// Reads a blob back into memory; mirrors the C reference parser.
+#[cfg(test)]
+pub fn parse_blob(bytes: &[u8]) -> Result<BlobFont, String> {
+    if bytes.len() < HEADER_SIZE || bytes[0..4] != MAGIC {
+        return Err("Not a font blob".to_string());
+    }
+    let big_endian = bytes[6] & FLAG_BIG_ENDIAN != 0;
+    let u16_at = |o: usize| -> Result<u16, String> {
+        let b: [u8; 2] = bytes
+            .get(o..o + 2)
+            .and_then(|s| s.try_into().ok())
+            .ok_or_else(|| format!("Blob truncated at {}", o))?;
+        Ok(if big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) })
+    };
+    let u32_at = |o: usize| -> Result<u32, String> {
+        let b: [u8; 4] = bytes
+            .get(o..o + 4)
+            .and_then(|s| s.try_into().ok())
+            .ok_or_else(|| format!("Blob truncated at {}", o))?;
+        Ok(if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    };
+    if u16_at(4)? != VERSION {
+        return Err(format!("Unsupported blob version {}", u16_at(4)?));
+    }
+
+    let glyph_count = u32_at(12)? as usize;
+    let range_count = u32_at(16)? as usize;
+    let glyph_table = u32_at(20)? as usize;
+    let range_table = u32_at(24)? as usize;
+    let bitmap_offset = u32_at(28)? as usize;
+    let bitmap_size = u32_at(32)? as usize;
+    let fallback = u32_at(44)?;
+    let metadata_offset = u32_at(56)? as usize;
+    let metadata_size = u32_at(60)? as usize;
+
+    let covered = bytes
+        .get(glyph_table..bitmap_offset + bitmap_size)
+        .ok_or_else(|| "Blob sections truncated".to_string())?;
+    if crc32fast::hash(covered) != u32_at(CRC_OFFSET)? {
+        return Err("Blob CRC mismatch".to_string());
+    }
+
+    let glyphs = (0..glyph_count)
+        .map(|i| {
+            let o = glyph_table + i * GLYPH_RECORD_SIZE;
+            Ok(BlobGlyph {
+                bitmap_offset: u32_at(o)?,
+                width: u16_at(o + 4)?,
+                height: u16_at(o + 6)?,
+                x_advance: u16_at(o + 8)? as i16,
+                x_offset: u16_at(o + 10)? as i16,
+                y_offset: u16_at(o + 12)? as i16,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let ranges = (0..range_count)
+        .map(|i| {
+            let o = range_table + i * RANGE_RECORD_SIZE;
+            Ok(BlobRange {
+                start: u32_at(o)?,
+                length: u32_at(o + 4)?,
+                glyph_id_start: u32_at(o + 8)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let bitmaps = bytes
+        .get(bitmap_offset..bitmap_offset + bitmap_size)
+        .ok_or_else(|| "Blob bitmap section truncated".to_string())?
+        .to_vec();
+    let metadata = bytes
+        .get(metadata_offset..metadata_offset + metadata_size)
+        .ok_or_else(|| "Blob metadata truncated".to_string())?;
+
+    Ok(BlobFont {
+        line_height: u16_at(36)? as i16,
+        baseline: u16_at(38)? as i16,
+        max_w: u16_at(40)?,
+        max_h: u16_at(42)?,
+        fallback_index: (fallback != NO_FALLBACK).then_some(fallback),
+        glyphs,
+        ranges,
+        bitmaps,
+        metadata: String::from_utf8(metadata.to_vec()).map_err(|_| "Blob metadata is not UTF-8".to_string())?,
+    })
+}
+
+/// C header describing the blob: section offsets, record structs and a reference parser that
+/// reads through a callback, so the blob can stay in SPI flash.
+pub fn c_header(
+    name: &str,
+    font: &BlobFont,
+    layout: &BlobLayout,
+    crc32: u32,
+    notice: &[String],
+    constants: &str,
+) -> String {
+    let sections = layout.sections(font);
+    let upper = name.to_uppercase();
+    let mut out = String::new();
+    out.push_str(&format!("/* Font blob index for {}. */\n", name));
+    if !notice.is_empty() {
+        out.push_str("/* Font license:\n");
+        for line in notice {
+            out.push_str(format!(" *   {}", line.replace("*/", "* /")).trim_end());
+            out.push('\n');
+        }
+        out.push_str(" */\n");
+    }
+    for line in font.metadata.lines() {
+        out.push_str(&format!("/*   {} */\n", line.replace("*/", "* /")));
+    }
+    out.push_str(&format!("#ifndef {}_BLOB_H\n#define {}_BLOB_H\n\n", upper, upper));
+    out.push_str("#include <stdint.h>\n\n");
+    out.push_str(&format!("#define {}_BLOB_SIZE {}u\n", upper, sections.total));
+    out.push_str(&format!("#define {}_BIG_ENDIAN {}\n", upper, layout.big_endian as u8));
+    out.push_str(&format!("#define {}_ALIGNMENT {}u\n", upper, layout.alignment));
+    out.push_str(&format!("#define {}_GLYPH_COUNT {}u\n", upper, font.glyphs.len()));
+    out.push_str(&format!("#define {}_RANGE_COUNT {}u\n", upper, font.ranges.len()));
+    out.push_str(&format!("#define {}_GLYPH_TABLE_OFFSET {}u\n", upper, sections.glyph_table));
+    out.push_str(&format!("#define {}_RANGE_TABLE_OFFSET {}u\n", upper, sections.range_table));
+    out.push_str(&format!("#define {}_BITMAP_OFFSET {}u\n", upper, sections.bitmaps));
+    out.push_str(&format!("#define {}_METADATA_OFFSET {}u\n", upper, sections.metadata));
+    out.push_str(&format!("#define {}_CRC32 0x{:08X}u\n\n", upper, crc32));
+    out.push_str(constants);
+    out.push_str(C_PARSER);
+    out.push_str(&format!("\n#endif /* {}_BLOB_H */\n", upper));
+    out
+}
+
+const C_PARSER: &str = r#"#ifndef CBF_FORMAT_DEFINED
+#define CBF_FORMAT_DEFINED
+
+#define CBF_VERSION 2u
+#define CBF_HEADER_SIZE 64u
+#define CBF_GLYPH_RECORD_SIZE 16u
+#define CBF_RANGE_RECORD_SIZE 12u
+#define CBF_NO_FALLBACK 0xFFFFFFFFu
+
+typedef struct {
+    uint8_t big_endian;
+    uint32_t glyph_count;
+    uint32_t range_count;
+    uint32_t glyph_table_offset;
+    uint32_t range_table_offset;
+    uint32_t bitmap_offset;
+    uint32_t bitmap_size;
+    int16_t line_height;
+    int16_t baseline;
+    uint16_t max_w;
+    uint16_t max_h;
+    uint32_t fallback_index;
+    uint32_t crc32;
+    uint32_t metadata_offset;
+    uint32_t metadata_size;
+} cbf_header;
+
+typedef struct {
+    uint32_t bitmap_offset; /* relative to cbf_header.bitmap_offset */
+    uint16_t width;
+    uint16_t height;
+    int16_t x_advance;
+    int16_t x_offset;
+    int16_t y_offset;
+} cbf_glyph;
+
+/* Reads `len` bytes at `offset` of the blob into `dst`; returns 0 on success. */
+typedef int (*cbf_read_fn)(void* ctx, uint32_t offset, void* dst, uint32_t len);
+
+static inline uint16_t cbf_u16(const uint8_t* p, uint8_t big_endian) {
+    return big_endian ? (uint16_t)((p[0] << 8) | p[1]) : (uint16_t)((p[1] << 8) | p[0]);
+}
+
+static inline uint32_t cbf_u32(const uint8_t* p, uint8_t big_endian) {
+    return big_endian
+        ? ((uint32_t)p[0] << 24) | ((uint32_t)p[1] << 16) | ((uint32_t)p[2] << 8) | p[3]
+        : ((uint32_t)p[3] << 24) | ((uint32_t)p[2] << 16) | ((uint32_t)p[1] << 8) | p[0];
+}
+
+static inline int cbf_read_header(cbf_read_fn read, void* ctx, cbf_header* h) {
+    uint8_t b[CBF_HEADER_SIZE];
+    if (read(ctx, 0, b, CBF_HEADER_SIZE) != 0) return -1;
+    if (b[0] != 'C' || b[1] != 'B' || b[2] != 'F' || b[3] != 'N') return -1;
+    h->big_endian = b[6] & 1u;
+    if (cbf_u16(b + 4, h->big_endian) != CBF_VERSION) return -1;
+    h->glyph_count = cbf_u32(b + 12, h->big_endian);
+    h->range_count = cbf_u32(b + 16, h->big_endian);
+    h->glyph_table_offset = cbf_u32(b + 20, h->big_endian);
+    h->range_table_offset = cbf_u32(b + 24, h->big_endian);
+    h->bitmap_offset = cbf_u32(b + 28, h->big_endian);
+    h->bitmap_size = cbf_u32(b + 32, h->big_endian);
+    h->line_height = (int16_t)cbf_u16(b + 36, h->big_endian);
+    h->baseline = (int16_t)cbf_u16(b + 38, h->big_endian);
+    h->max_w = cbf_u16(b + 40, h->big_endian);
+    h->max_h = cbf_u16(b + 42, h->big_endian);
+    h->fallback_index = cbf_u32(b + 44, h->big_endian);
+    h->crc32 = cbf_u32(b + 52, h->big_endian);
+    h->metadata_offset = cbf_u32(b + 56, h->big_endian);
+    h->metadata_size = cbf_u32(b + 60, h->big_endian);
+    return 0;
+}
+
+/* Recomputes the CRC-32 of the glyph table, range table and bitmaps; returns 0 when it matches. */
+static inline int cbf_verify(cbf_read_fn read, void* ctx, const cbf_header* h) {
+    uint8_t chunk[64];
+    uint32_t crc = 0xFFFFFFFFu;
+    uint32_t offset = h->glyph_table_offset;
+    uint32_t end = h->bitmap_offset + h->bitmap_size;
+    while (offset < end) {
+        uint32_t len = end - offset < sizeof(chunk) ? end - offset : (uint32_t)sizeof(chunk);
+        if (read(ctx, offset, chunk, len) != 0) return -1;
+        for (uint32_t i = 0; i < len; ++i) {
+            crc ^= chunk[i];
+            for (int k = 0; k < 8; ++k) crc = (crc >> 1) ^ (0xEDB88320u & (0u - (crc & 1u)));
+        }
+        offset += len;
+    }
+    return (crc ^ 0xFFFFFFFFu) == h->crc32 ? 0 : -1;
+}
+
+static inline int cbf_read_glyph(cbf_read_fn read, void* ctx, const cbf_header* h, uint32_t index, cbf_glyph* g) {
+    uint8_t b[CBF_GLYPH_RECORD_SIZE];
+    if (index >= h->glyph_count) return -1;
+    if (read(ctx, h->glyph_table_offset + index * CBF_GLYPH_RECORD_SIZE, b, CBF_GLYPH_RECORD_SIZE) != 0) return -1;
+    g->bitmap_offset = cbf_u32(b, h->big_endian);
+    g->width = cbf_u16(b + 4, h->big_endian);
+    g->height = cbf_u16(b + 6, h->big_endian);
+    g->x_advance = (int16_t)cbf_u16(b + 8, h->big_endian);
+    g->x_offset = (int16_t)cbf_u16(b + 10, h->big_endian);
+    g->y_offset = (int16_t)cbf_u16(b + 12, h->big_endian);
+    return 0;
+}
+
+/* Returns the glyph index for `codepoint`, the fallback index, or -1. */
+static inline int32_t cbf_find_glyph(cbf_read_fn read, void* ctx, const cbf_header* h, uint32_t codepoint) {
+    uint32_t lo = 0, hi = h->range_count;
+    while (lo < hi) {
+        uint8_t b[CBF_RANGE_RECORD_SIZE];
+        uint32_t mid = (lo + hi) / 2;
+        if (read(ctx, h->range_table_offset + mid * CBF_RANGE_RECORD_SIZE, b, CBF_RANGE_RECORD_SIZE) != 0) return -1;
+        uint32_t start = cbf_u32(b, h->big_endian);
+        uint32_t length = cbf_u32(b + 4, h->big_endian);
+        if (codepoint < start) hi = mid;
+        else if (codepoint - start >= length) lo = mid + 1;
+        else return (int32_t)(cbf_u32(b + 8, h->big_endian) + (codepoint - start));
+    }
+    return h->fallback_index == CBF_NO_FALLBACK ? -1 : (int32_t)h->fallback_index;
+}
+
+#endif /* CBF_FORMAT_DEFINED */
+"#;
+
+fn align_up(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
@@ -0,0 +1,135 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use ttf_parser::{Face, Tag};
+
+use crate::bitmap_strikes::strike_sizes;
+use crate::font_license::FontLicense;
+use crate::font_pipeline::{load_font_from_source, FontSource};
+use crate::provenance::font_names;
+use crate::unicode_blocks::UNICODE_BLOCKS;
+
+#[derive(Debug, Serialize)]
+pub struct FontInspection {
+    family: String,
+    style: String,
+    units_per_em: u16,
+    ascent: i16,
+    descent: i16,
+    line_gap: i16,
+    glyph_count: u16,
+    monospaced: bool,
+    has_kerning: bool,
+    variation_axes: Vec<VariationAxisInfo>,
+    strike_sizes: Vec<u16>,
+    codepoint_count: u32,
+    /// Supported codepoints as inclusive `[first, last]` runs.
+    codepoint_ranges: Vec<[u32; 2]>,
+    blocks: Vec<BlockCoverage>,
+    license: Option<FontLicense>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariationAxisInfo {
+    tag: String,
+    min: f32,
+    default: f32,
+    max: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockCoverage {
+    name: &'static str,
+    start: u32,
+    end: u32,
+    covered: u32,
+    /// Assigned codepoints in the block.
+    total: u32,
+}
+
+#[tauri::command]
+pub fn inspect_font(source: FontSource) -> Result<FontInspection, String> {
+    let loaded = load_font_from_source(&source)?;
+    let face = loaded
+        .face()
+        .ok_or_else(|| "Failed to parse font tables".to_string())?;
+    let (family, style) = font_names(&face);
+    let codepoints = mapped_codepoints(&face);
+
+    Ok(FontInspection {
+        family,
+        style,
+        units_per_em: face.units_per_em(),
+        ascent: face.ascender(),
+        descent: face.descender(),
+        line_gap: face.line_gap(),
+        glyph_count: face.number_of_glyphs(),
+        monospaced: face.is_monospaced(),
+        has_kerning: has_kerning(&face),
+        variation_axes: face
+            .variation_axes()
+            .into_iter()
+            .map(|axis| VariationAxisInfo {
+                tag: axis.tag.to_string(),
+                min: axis.min_value,
+                default: axis.def_value,
+                max: axis.max_value,
+            })
+            .collect(),
+        strike_sizes: strike_sizes(&face),
+        codepoint_count: codepoints.len() as u32,
+        codepoint_ranges: compress_ranges(&codepoints),
+        blocks: block_coverage(&codepoints),
+        license: loaded.license().cloned(),
+    })
+}
+
+/// Unicode codepoints the cmap maps to a real glyph.
+pub fn mapped_codepoints(face: &Face) -> BTreeSet<u32> {
+    let mut codepoints = BTreeSet::new();
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|cp| {
+                if subtable.glyph_index(cp).is_some_and(|g| g.0 != 0) {
+                    codepoints.insert(cp);
+                }
+            });
+        }
+    }
+    codepoints
+}
+
+fn has_kerning(face: &Face) -> bool {
+    let gpos_kern = face
+        .tables()
+        .gpos
+        .is_some_and(|gpos| gpos.features.find(Tag::from_bytes(b"kern")).is_some());
+    gpos_kern || face.tables().kern.is_some_and(|kern| !kern.subtables.is_empty())
+}
+
+pub fn compress_ranges(codepoints: &BTreeSet<u32>) -> Vec<[u32; 2]> {
+    let mut ranges: Vec<[u32; 2]> = Vec::new();
+    for &cp in codepoints {
+        match ranges.last_mut() {
+            Some(last) if last[1] + 1 == cp => last[1] = cp,
+            _ => ranges.push([cp, cp]),
+        }
+    }
+    ranges
+}
+
+/// Coverage of every block the font has at least one codepoint in.
+fn block_coverage(codepoints: &BTreeSet<u32>) -> Vec<BlockCoverage> {
+    UNICODE_BLOCKS
+        .iter()
+        .filter_map(|&(start, end, total, name)| {
+            let covered = codepoints.range(start..=end).count() as u32;
+            (covered > 0).then_some(BlockCoverage {
+                name,
+                start,
+                end,
+                covered,
+                total,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+use ttf_parser::{name_id, Face, Permissions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Embedding {
+    Installable,
+    Restricted,
+    PreviewAndPrint,
+    Editable,
+}
+
+/// Licensing data from the `name` table and the OS/2 `fsType` embedding bits.
+#[derive(Debug, Clone, Serialize)]
+pub struct FontLicense {
+    pub copyright: Option<String>,
+    pub license: Option<String>,
+    pub license_url: Option<String>,
+    pub embedding: Option<Embedding>,
+    pub subsetting_allowed: bool,
+    pub bitmap_embedding_only: bool,
+}
+
+impl FontLicense {
+    pub fn read(face: &Face) -> Self {
+        let name = |id: u16| {
+            face.names()
+                .into_iter()
+                .filter(|n| n.name_id == id)
+                .find_map(|n| n.to_string())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        Self {
+            copyright: name(name_id::COPYRIGHT_NOTICE),
+            license: name(name_id::LICENSE),
+            license_url: name(name_id::LICENSE_URL),
+            embedding: face.permissions().map(|p| match p {
+                Permissions::Installable => Embedding::Installable,
+                Permissions::Restricted => Embedding::Restricted,
+                Permissions::PreviewAndPrint => Embedding::PreviewAndPrint,
+                Permissions::Editable => Embedding::Editable,
+            }),
+            subsetting_allowed: face.is_subsetting_allowed(),
+            bitmap_embedding_only: !face.is_outline_embedding_allowed(),
+        }
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        match self.embedding {
+            Some(Embedding::Restricted) => warnings.push(
+                "Font license is restricted (fsType): embedding is not permitted without the vendor's consent"
+                    .to_string(),
+            ),
+            Some(Embedding::PreviewAndPrint) => warnings.push(
+                "Font license allows preview & print embedding only (fsType); check before shipping".to_string(),
+            ),
+            _ => {}
+        }
+        if !self.subsetting_allowed {
+            warnings.push("Font license forbids subsetting (fsType), but the output is a subset".to_string());
+        }
+        warnings
+    }
+
+    /// Copyright and license text as plain lines for the generated file's header comment.
+    pub fn notice_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for text in [&self.copyright, &self.license, &self.license_url].into_iter().flatten() {
+            lines.extend(text.lines().map(|l| l.trim_end().to_string()));
+        }
+        lines
+    }
+}
@@ -8,16 +8,53 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::bdf_export::{self, BdfFont, BdfGlyph};
+use crate::bitmap_fonts::BitmapFont;
+use crate::bitmap_strikes::{strike_glyph, strike_sizes};
+use crate::custom_glyphs::{load_custom_glyph, CustomGlyph, CustomGlyphSpec};
+use crate::font_blob::{self, BlobFont, BlobGlyph, BlobLayout, BlobRange};
+use crate::font_license::FontLicense;
+use crate::framebuffer::{drawing_size, emulate, pack_framebuffer, PanelSpec};
 use crate::fs_utils::{sanitize_filename, write_atomic};
+use crate::glyph_layout::CompactLayout;
+use crate::glyph_overrides::{find_override, GlyphOverride};
+use crate::icon_names::{self, resolve_icons, IconGlyph};
+use crate::legacy_encodings::LegacyEncoding;
+use crate::lookup_tables::{build_lookup, LookupStats, LookupTable};
+use crate::pixel_grid::{detect_pixel_grid, snap_coverage, PixelGrid};
+use crate::png_io::encode_png;
+use crate::provenance::{module_crc32, sha256_hex, Provenance};
 use crate::settings::resolve_save_path;
+use crate::sprite_sheet::{import_sprite_sheet, SpriteSheetSpec};
+use crate::storage_attrs::StorageAttrs;
+use crate::system_fonts;
+use crate::text_render::{parse_color, render_text, RenderedText, SampleGlyph, SampleOptions};
+use crate::string_tables::{self, encode_strings, load_strings_file, missing_glyphs, EncodedString, NamedString};
+use crate::woff::unwrap_web_font;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "mode")]
 pub enum FontSource {
+    /// First face of an installed family, or the face with `postscript_name` when given.
     #[serde(rename = "system")]
-    System { family: String },
+    System {
+        family: String,
+        #[serde(default)]
+        postscript_name: Option<String>,
+    },
+    /// Font file; `font_index` picks a face of a TrueType/OpenType collection.
     #[serde(rename = "file")]
-    File { path: String },
+    File {
+        path: String,
+        #[serde(default)]
+        font_index: u32,
+    },
+    /// BDF, PCF, Windows .FNT or PSF1/PSF2 file (optionally gzipped), rendered pixel-exact.
+    #[serde(rename = "bitmap")]
+    Bitmap { path: String },
+    /// PNG grid or strip of hand-drawn glyphs.
+    #[serde(rename = "sprite_sheet")]
+    SpriteSheet(SpriteSheetSpec),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,6 +84,64 @@ pub struct FontJob {
     gamma: f32,
     #[serde(default = "default_oversample")]
     oversample: u32,
+    #[serde(default = "default_use_bitmap_strikes")]
+    use_bitmap_strikes: bool,
+    #[serde(default)]
+    snap_to_pixel_grid: bool,
+    #[serde(default = "default_glyph_layout")]
+    glyph_layout: String,
+    #[serde(default = "default_lookup_strategy")]
+    lookup_strategy: String,
+    #[serde(default = "default_lookup_merge_gap")]
+    lookup_merge_gap: u32,
+    #[serde(default)]
+    synthesize_tofu: bool,
+    #[serde(default = "default_blob_endianness")]
+    blob_endianness: String,
+    #[serde(default = "default_blob_alignment")]
+    blob_alignment: u32,
+    #[serde(default = "default_storage_preset")]
+    storage_preset: String,
+    #[serde(default)]
+    storage_section: String,
+    #[serde(default)]
+    storage_alignment: u32,
+    #[serde(default)]
+    storage_qualifier: String,
+    /// Glyph names of icons to include, e.g. "wifi" or "battery_full".
+    #[serde(default)]
+    icon_names: Vec<String>,
+    #[serde(default = "default_icon_constants")]
+    icon_constants: String,
+    /// Images placed at chosen codepoints, replacing any glyph the font has there.
+    #[serde(default)]
+    custom_glyphs: Vec<CustomGlyphSpec>,
+    /// Hand-tuned bitmaps and pixel patches, applied after rasterization.
+    #[serde(default)]
+    glyph_overrides: Vec<GlyphOverride>,
+    /// UI strings exported as glyph index arrays; their characters join the charset.
+    #[serde(default)]
+    strings: Vec<NamedString>,
+    /// Strings file read in addition to `strings`, see `load_strings_file`.
+    #[serde(default)]
+    strings_file: String,
+    /// "unicode", or a legacy set ("gbk", "shift_jis", "big5", "cp1251") whose codes key the
+    /// exported ranges and lookups instead of codepoints.
+    #[serde(default = "default_output_encoding")]
+    output_encoding: String,
+    /// "em" takes `size_px` as the em size; "cap_height" or "x_height" as the pixel height of
+    /// "H" or "x", picking the em size that draws it that tall.
+    #[serde(default = "default_size_mode")]
+    size_mode: String,
+    /// Line height in pixels; 0 uses `cell_height`, or the font's line spacing.
+    #[serde(default)]
+    line_height: u32,
+    /// Pixels the baseline moves down from the font's ascent, taking the glyphs with it.
+    #[serde(default)]
+    baseline_shift: i32,
+    /// Clips glyphs to this many rows from the top of the line; 0 leaves them whole.
+    #[serde(default)]
+    cell_height: u32,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,6 +149,9 @@ pub struct GeneratedResult {
     ok: bool,
     warnings: Vec<String>,
     stats: GeneratedStats,
+    pixel_grid: Option<PixelGrid>,
+    provenance: Provenance,
+    license: Option<FontLicense>,
     preview: Option<GeneratedPreview>,
     c: Option<GeneratedC>,
 }
@@ -66,6 +164,11 @@ pub struct GeneratedStats {
     max_h: u32,
     line_height: i32,
     baseline: i32,
+    size_px: u32,
+    dedup_saved_bytes: u32,
+    strike_glyphs: u32,
+    outline_glyphs: u32,
+    lookup: LookupStats,
 }
 
 #[derive(Debug, Serialize)]
@@ -79,11 +182,28 @@ pub struct PreviewGlyph {
     w: u32,
     h: u32,
     advance: u32,
+    source: GlyphSource,
     bitmap_b64: String,
     mono_b64: String,
     raw_b64: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GlyphSource {
+    Outline,
+    Strike,
+    Synthesized,
+    Bitmap,
+    Custom,
+    Override,
+}
+
+enum FallbackGlyph {
+    Glyph { codepoint: u32, glyph_index: u16 },
+    Tofu,
+}
+
 #[derive(Debug, Serialize)]
 pub struct GeneratedC {
     header: String,
@@ -94,9 +214,12 @@ struct PackedGlyph {
     codepoint: u32,
     offset: usize,
     len: usize,
+    source: GlyphSource,
+    /// Codepoint of the glyph whose identical bitmap bytes this one reuses.
+    shared_with: Option<u32>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 struct GlyphEntry {
     offset: usize,
     width: i32,
@@ -108,13 +231,14 @@ struct GlyphEntry {
 
 struct GlyphRangeEntry {
     start: u32,
-    length: u16,
-    glyph_id_start: u16,
+    length: u32,
+    glyph_id_start: u32,
 }
 
 struct GlyphData {
     bitmaps: Vec<u8>,
     packed_glyphs: Vec<PackedGlyph>,
+    dedup_saved_bytes: usize,
     glyphs: Vec<GlyphEntry>,
     codepoints: Vec<u32>,
     ranges: Vec<GlyphRangeEntry>,
@@ -123,11 +247,257 @@ struct GlyphData {
     max_h: u32,
 }
 
+/// Outline fonts go through fontdue; bitmap fonts hand back their stored pixels at any size.
+pub(crate) enum SourceFont {
+    Outline(Font),
+    Bitmap(BitmapFont),
+}
+
+impl SourceFont {
+    fn lookup_glyph_index(&self, ch: char) -> u16 {
+        match self {
+            SourceFont::Outline(font) => font.lookup_glyph_index(ch),
+            SourceFont::Bitmap(font) => font.glyph_index(ch as u32),
+        }
+    }
+
+    fn rasterize_indexed(&self, glyph_index: u16, size_px: f32) -> (fontdue::Metrics, Vec<u8>) {
+        match self {
+            SourceFont::Outline(font) => font.rasterize_indexed(glyph_index, size_px),
+            SourceFont::Bitmap(font) => match font.glyph(glyph_index) {
+                Some(glyph) => (
+                    fontdue::Metrics {
+                        xmin: glyph.x_min,
+                        ymin: glyph.y_min,
+                        width: glyph.width as usize,
+                        height: glyph.height as usize,
+                        advance_width: glyph.advance as f32,
+                        ..Default::default()
+                    },
+                    glyph.gray.clone(),
+                ),
+                None => (fontdue::Metrics::default(), Vec::new()),
+            },
+        }
+    }
+
+    fn metrics_indexed(&self, glyph_index: u16, size_px: f32) -> fontdue::Metrics {
+        match self {
+            SourceFont::Outline(font) => font.metrics_indexed(glyph_index, size_px),
+            SourceFont::Bitmap(_) => self.rasterize_indexed(glyph_index, size_px).0,
+        }
+    }
+
+    pub(crate) fn as_bitmap(&self) -> Option<&BitmapFont> {
+        match self {
+            SourceFont::Bitmap(font) => Some(font),
+            SourceFont::Outline(_) => None,
+        }
+    }
+
+    fn glyph_count(&self) -> usize {
+        match self {
+            SourceFont::Outline(font) => font.glyph_count() as usize,
+            SourceFont::Bitmap(font) => font.glyph_count(),
+        }
+    }
+}
+
+/// The source font plus custom image glyphs, which take the glyph indices after the font's own.
+struct GlyphSet<'a> {
+    font: &'a SourceFont,
+    custom: Vec<CustomGlyph>,
+    custom_base: u16,
+}
+
+impl<'a> GlyphSet<'a> {
+    fn new(font: &'a SourceFont, custom: Vec<CustomGlyph>) -> Result<Self, String> {
+        let custom_base = font.glyph_count();
+        if custom_base + custom.len() > u16::MAX as usize {
+            return Err(format!(
+                "Font has {} glyphs, no glyph indices left for {} custom glyphs",
+                custom_base,
+                custom.len()
+            ));
+        }
+        Ok(Self {
+            font,
+            custom,
+            custom_base: custom_base as u16,
+        })
+    }
+
+    fn custom(&self, glyph_index: u16) -> Option<&CustomGlyph> {
+        self.custom.get(glyph_index.checked_sub(self.custom_base)? as usize)
+    }
+
+    fn lookup_glyph_index(&self, ch: char) -> u16 {
+        match self.custom.iter().rposition(|g| g.codepoint == ch as u32) {
+            Some(i) => self.custom_base + i as u16,
+            None => self.font.lookup_glyph_index(ch),
+        }
+    }
+
+    fn rasterize_indexed(&self, glyph_index: u16, size_px: f32) -> (fontdue::Metrics, Vec<u8>) {
+        match self.custom(glyph_index) {
+            Some(glyph) => (
+                fontdue::Metrics {
+                    xmin: 0,
+                    ymin: glyph.y_min,
+                    width: glyph.width as usize,
+                    height: glyph.height as usize,
+                    advance_width: glyph.advance as f32,
+                    ..Default::default()
+                },
+                glyph.gray.clone(),
+            ),
+            None => self.font.rasterize_indexed(glyph_index, size_px),
+        }
+    }
+
+    fn metrics_indexed(&self, glyph_index: u16, size_px: f32) -> fontdue::Metrics {
+        match self.custom(glyph_index) {
+            Some(_) => self.rasterize_indexed(glyph_index, size_px).0,
+            None => self.font.metrics_indexed(glyph_index, size_px),
+        }
+    }
+
+    fn as_bitmap(&self) -> Option<&BitmapFont> {
+        self.font.as_bitmap()
+    }
+}
+
+pub struct LoadedFont {
+    font: SourceFont,
+    data: Vec<u8>,
+    /// Face of `data` when it is a collection.
+    index: u32,
+    /// Hash of the file `data` came from, which differs from `data` for WOFF/WOFF2.
+    file_sha256: String,
+    license: Option<FontLicense>,
+}
+
+impl LoadedFont {
+    fn from_bytes(data: Vec<u8>, index: u32, file_sha256: String) -> Result<Self, &'static str> {
+        let settings = FontSettings {
+            collection_index: index,
+            ..FontSettings::default()
+        };
+        let font = Font::from_bytes(data.as_slice(), settings)?;
+        let license = ttf_parser::Face::parse(&data, index).ok().map(|face| FontLicense::read(&face));
+        Ok(Self {
+            font: SourceFont::Outline(font),
+            data,
+            index,
+            file_sha256,
+            license,
+        })
+    }
+
+    fn from_sprite_sheet(data: Vec<u8>, spec: &SpriteSheetSpec) -> Result<Self, String> {
+        let font = import_sprite_sheet(&data, spec)?;
+        Ok(Self {
+            font: SourceFont::Bitmap(font),
+            file_sha256: sha256_hex(&data),
+            data,
+            index: 0,
+            license: None,
+        })
+    }
+
+    fn from_bitmap(data: Vec<u8>) -> Result<Self, String> {
+        let font = BitmapFont::parse(&data)?;
+        let license = font.copyright.clone().map(|copyright| FontLicense {
+            copyright: Some(copyright),
+            license: None,
+            license_url: None,
+            embedding: None,
+            subsetting_allowed: true,
+            bitmap_embedding_only: false,
+        });
+        Ok(Self {
+            font: SourceFont::Bitmap(font),
+            file_sha256: sha256_hex(&data),
+            data,
+            index: 0,
+            license,
+        })
+    }
+
+    pub(crate) fn face(&self) -> Option<ttf_parser::Face<'_>> {
+        ttf_parser::Face::parse(&self.data, self.index).ok()
+    }
+
+    pub(crate) fn license(&self) -> Option<&FontLicense> {
+        self.license.as_ref()
+    }
+}
+
+struct RasterSettings<'a> {
+    size_px: u32,
+    binarize_mode: &'a str,
+    threshold: u8,
+    gamma: f32,
+    oversample: u32,
+    pixel_exact: bool,
+    overrides: &'a [GlyphOverride],
+    /// Baseline row and height of the cell glyphs are clipped to.
+    cell: Option<(i32, u32)>,
+}
+
+impl<'a> RasterSettings<'a> {
+    fn from_job(job: &'a FontJob) -> Self {
+        Self {
+            size_px: job.size_px,
+            binarize_mode: &job.binarize_mode,
+            threshold: job.threshold,
+            gamma: job.gamma,
+            oversample: job.oversample,
+            pixel_exact: false,
+            overrides: &job.glyph_overrides,
+            cell: None,
+        }
+    }
+
+    fn mono_threshold(&self) -> u8 {
+        if self.binarize_mode == "mask_1bit" {
+            1
+        } else {
+            self.threshold
+        }
+    }
+}
+
+struct BakedFont<'a> {
+    glyph_set: GlyphSet<'a>,
+    settings: RasterSettings<'a>,
+    strike_face: Option<ttf_parser::Face<'a>>,
+    codepoint_map: BTreeMap<u32, u16>,
+    glyph_data: GlyphData,
+    warnings: Vec<String>,
+    line_height: i32,
+    baseline: i32,
+    pixel_grid: Option<PixelGrid>,
+    lookup: LookupTable,
+    /// Packed `CompactGlyph` layout, or `None` for the runtime's `Glyph` struct.
+    layout: Option<CompactLayout>,
+    storage: StorageAttrs,
+    provenance: Provenance,
+    license: Option<FontLicense>,
+    icons: Vec<IconGlyph>,
+    strings: Vec<EncodedString>,
+    encoding: Option<LegacyEncoding>,
+    /// Legacy code to codepoint of every ranged character when `encoding` is set.
+    legacy_chars: BTreeMap<u32, u32>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ExportResult {
     ok: bool,
     warnings: Vec<String>,
     output_path: Option<String>,
+    /// Files written alongside `output_path`, e.g. the C header of a binary blob.
+    companion_paths: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -137,6 +507,63 @@ pub struct ExportFontArgs {
     filename: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RenderSampleArgs {
+    job: FontJob,
+    text: String,
+    /// Target display size in pixels; 0 fits the image to the text.
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    #[serde(default = "default_sample_fg")]
+    fg: String,
+    #[serde(default = "default_sample_bg")]
+    bg: String,
+    #[serde(default = "default_sample_wrap")]
+    wrap: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SampleRender {
+    ok: bool,
+    warnings: Vec<String>,
+    png_b64: String,
+    width: u32,
+    height: u32,
+    lines: u32,
+    /// The text did not fit the target display size and was clipped.
+    overflow: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmulateDisplayArgs {
+    panel: PanelSpec,
+    /// Framebuffer bytes to show as they are, base64. Without them `text` is drawn with `job`
+    /// and packed into the panel's memory first.
+    framebuffer_b64: Option<String>,
+    job: Option<FontJob>,
+    #[serde(default)]
+    text: String,
+    #[serde(default = "default_sample_fg")]
+    fg: String,
+    #[serde(default = "default_sample_bg")]
+    bg: String,
+    #[serde(default = "default_sample_wrap")]
+    wrap: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmulatedDisplay {
+    ok: bool,
+    warnings: Vec<String>,
+    png_b64: String,
+    width: u32,
+    height: u32,
+    /// The framebuffer bytes the picture was decoded from.
+    framebuffer_b64: String,
+}
+
 const PREVIEW_MAX_GLYPHS: usize = 256;
 const PREVIEW_MAX_PIXELS_TOTAL: usize = 4 * 1024 * 1024; // 4MB raw grayscale
 fn default_binarize_mode() -> String {
@@ -155,28 +582,97 @@ fn default_oversample() -> u32 {
     2
 }
 
-#[tauri::command]
-pub fn generate_font(job: FontJob) -> Result<GeneratedResult, String> {
-    let font = load_font_from_source(&job.source)?;
+fn default_use_bitmap_strikes() -> bool {
+    true
+}
 
-    if job.range.start > job.range.end {
-        return Err("Invalid range: start must be <= end".to_string());
-    }
+fn default_glyph_layout() -> String {
+    "standard".to_string()
+}
 
-    let (codepoint_map, mut warnings) = collect_codepoints(&job, &font);
-    let fallback_cp = job
-        .fallback_char
-        .as_deref()
-        .and_then(|s| s.trim().chars().next())
-        .map(|c| c as u32);
-    let glyph_data = build_glyph_data(&font, job.size_px, &codepoint_map, fallback_cp, &job.binarize_mode, job.threshold, job.gamma, job.oversample);
-    let (glyphs, preview_truncated) = build_preview(&font, job.size_px, &codepoint_map, &job.binarize_mode, job.threshold, job.gamma, job.oversample);
+fn default_lookup_strategy() -> String {
+    "ranges".to_string()
+}
+
+fn default_lookup_merge_gap() -> u32 {
+    4
+}
+
+fn default_blob_endianness() -> String {
+    "little".to_string()
+}
+
+fn default_blob_alignment() -> u32 {
+    4
+}
+
+fn default_storage_preset() -> String {
+    "none".to_string()
+}
+
+fn default_icon_constants() -> String {
+    "enum".to_string()
+}
+
+fn default_output_encoding() -> String {
+    "unicode".to_string()
+}
+
+fn default_size_mode() -> String {
+    "em".to_string()
+}
+
+fn default_sample_fg() -> String {
+    "#FFFFFF".to_string()
+}
+
+fn default_sample_bg() -> String {
+    "#000000".to_string()
+}
+
+fn default_sample_wrap() -> bool {
+    true
+}
+
+#[tauri::command]
+pub fn generate_font(job: FontJob) -> Result<GeneratedResult, String> {
+    let loaded = load_font_from_source(&job.source)?;
+    let mut baked = bake_font(&job, &loaded)?;
+    let mut warnings = std::mem::take(&mut baked.warnings);
+    let glyph_data = &baked.glyph_data;
+
+    let (glyphs, preview_truncated) = build_preview(
+        &baked.glyph_set,
+        baked.strike_face.as_ref(),
+        &baked.codepoint_map,
+        &baked.settings,
+    );
     if let Some((count, bytes)) = preview_truncated {
         warnings.push(format!("Preview truncated (glyphs={}, bytes={})", count, bytes));
     }
 
-    let (line_height, baseline) = line_metrics(&font, job.size_px);
-    let cpp_module = generate_cpp_module(&job, &glyph_data, line_height, baseline);
+    let source = match job.output_kind.as_str() {
+        "binary_blob" => {
+            let blob = blob_for(&baked)?;
+            let layout = blob_layout(&job)?;
+            let (_, crc) = font_blob::write_blob(&blob, &layout)?;
+            font_blob::c_header(
+                &job.export_name,
+                &blob,
+                &layout,
+                crc,
+                &notice_lines(&baked),
+                &blob_constants(&job, &baked),
+            )
+        }
+        "bdf" => bdf_export::write_bdf(&bdf_for(&job, &baked)),
+        _ => generate_cpp_module(&job, &baked),
+    };
+    let strike_glyphs = glyph_data
+        .packed_glyphs
+        .iter()
+        .filter(|g| g.source == GlyphSource::Strike)
+        .count() as u32;
 
     Ok(GeneratedResult {
         ok: true,
@@ -186,13 +682,25 @@ pub fn generate_font(job: FontJob) -> Result<GeneratedResult, String> {
             bytes: glyph_data.bitmaps.len() as u32,
             max_w: glyph_data.max_w,
             max_h: glyph_data.max_h,
-            line_height,
-            baseline,
+            line_height: baked.line_height,
+            baseline: baked.baseline,
+            size_px: baked.settings.size_px,
+            dedup_saved_bytes: glyph_data.dedup_saved_bytes as u32,
+            strike_glyphs,
+            outline_glyphs: glyph_data
+                .packed_glyphs
+                .iter()
+                .filter(|g| g.source == GlyphSource::Outline)
+                .count() as u32,
+            lookup: baked.lookup.stats.clone(),
         },
+        pixel_grid: baked.pixel_grid,
+        provenance: baked.provenance,
+        license: baked.license,
         preview: Some(GeneratedPreview { glyphs }),
         c: Some(GeneratedC {
             header: String::new(),
-            source: cpp_module,
+            source,
         }),
     })
 }
@@ -219,74 +727,563 @@ pub fn export_font(
         fs::create_dir_all(parent)
             .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
     }
-    let font = load_font_from_source(&args.job.source)?;
-    if args.job.range.start > args.job.range.end {
+    let loaded = load_font_from_source(&args.job.source)?;
+    let baked = bake_font(&args.job, &loaded)?;
+    let mut companion_paths = Vec::new();
+
+    let file_path = match args.job.output_kind.as_str() {
+        "binary_blob" => {
+            let blob = blob_for(&baked)?;
+            let layout = blob_layout(&args.job)?;
+            let blob_path = file_path.with_extension("bin");
+            let header_path = file_path.with_extension("h");
+            let (bytes, crc) = font_blob::write_blob(&blob, &layout)?;
+            write_atomic(&blob_path, &bytes)?;
+            write_atomic(
+                &header_path,
+                font_blob::c_header(
+                    &args.job.export_name,
+                    &blob,
+                    &layout,
+                    crc,
+                    &notice_lines(&baked),
+                    &blob_constants(&args.job, &baked),
+                )
+                .as_bytes(),
+            )?;
+            companion_paths.push(header_path.to_string_lossy().to_string());
+            blob_path
+        }
+        "bdf" => {
+            let bdf_path = file_path.with_extension("bdf");
+            write_atomic(&bdf_path, bdf_export::write_bdf(&bdf_for(&args.job, &baked)).as_bytes())?;
+            bdf_path
+        }
+        _ => {
+            let cpp_module = generate_cpp_module(&args.job, &baked);
+            write_atomic(&file_path, cpp_module.as_bytes())?;
+            file_path
+        }
+    };
+
+    Ok(ExportResult {
+        ok: true,
+        warnings: baked.warnings,
+        output_path: Some(file_path.to_string_lossy().to_string()),
+        companion_paths,
+    })
+}
+
+/// Renders sample text to a PNG for sign-off on what ships.
+#[tauri::command]
+pub fn render_sample(args: RenderSampleArgs) -> Result<SampleRender, String> {
+    let options = SampleOptions {
+        width: args.width,
+        height: args.height,
+        fg: parse_color(&args.fg)?,
+        bg: parse_color(&args.bg)?,
+        wrap: args.wrap,
+    };
+    let (rendered, warnings) = draw_sample(&args.job, &args.text, &options)?;
+    Ok(SampleRender {
+        ok: true,
+        warnings,
+        png_b64: BASE64_STANDARD.encode(encode_png(&rendered.image)?),
+        width: rendered.image.width,
+        height: rendered.image.height,
+        lines: rendered.lines,
+        overflow: rendered.overflow,
+    })
+}
+
+/// Shows what a display controller makes of framebuffer bytes: given ones, or sample text
+/// packed with the panel's selected bit layout and byte order.
+#[tauri::command]
+pub fn emulate_display(args: EmulateDisplayArgs) -> Result<EmulatedDisplay, String> {
+    let mut warnings = Vec::new();
+    let framebuffer = match (&args.framebuffer_b64, &args.job) {
+        (Some(encoded), _) => BASE64_STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("Invalid framebuffer data: {}", e))?,
+        (None, Some(job)) => {
+            let (width, height) = drawing_size(&args.panel)?;
+            let options = SampleOptions {
+                width,
+                height,
+                fg: parse_color(&args.fg)?,
+                bg: parse_color(&args.bg)?,
+                wrap: args.wrap,
+            };
+            let (rendered, sample_warnings) = draw_sample(job, &args.text, &options)?;
+            warnings.extend(sample_warnings);
+            pack_framebuffer(&args.panel, &rendered.image)?
+        }
+        (None, None) => return Err("Display emulation needs framebuffer bytes or a font job".to_string()),
+    };
+    let (image, emulate_warnings) = emulate(&args.panel, &framebuffer)?;
+    warnings.extend(emulate_warnings);
+    Ok(EmulatedDisplay {
+        ok: true,
+        warnings,
+        png_b64: BASE64_STANDARD.encode(encode_png(&image)?),
+        width: image.width,
+        height: image.height,
+        framebuffer_b64: BASE64_STANDARD.encode(&framebuffer),
+    })
+}
+
+/// Draws sample text from the baked glyph table the way the firmware walks it: lookup by the
+/// exported key, the fallback glyph on a miss, packed 1-bit bitmaps and the exported metrics.
+/// The export carries no kerning, so neither does the sample.
+fn draw_sample(job: &FontJob, text: &str, options: &SampleOptions) -> Result<(RenderedText, Vec<String>), String> {
+    let loaded = load_font_from_source(&job.source)?;
+    let baked = bake_font(job, &loaded)?;
+    let data = &baked.glyph_data;
+    let ranged_index = |ch: char| {
+        let key = match &baked.encoding {
+            Some(encoding) => encoding.code(ch as u32)?,
+            None => ch as u32,
+        };
+        data.codepoints.binary_search(&key).ok()
+    };
+    let glyph = |ch: char| {
+        let entry = data.glyphs.get(ranged_index(ch).or(data.fallback_index)?)?;
+        Some(SampleGlyph {
+            bits: &data.bitmaps[entry.offset..],
+            width: entry.width,
+            height: entry.height,
+            x_advance: entry.x_advance,
+            x_offset: entry.x_offset,
+            y_offset: entry.y_offset,
+        })
+    };
+    let rendered = render_text(text, options, baked.line_height, baked.baseline, glyph);
+
+    let mut warnings = baked.warnings;
+    let missing: BTreeSet<char> = text
+        .chars()
+        .filter(|ch| !matches!(ch, '\n' | '\r') && ranged_index(*ch).is_none())
+        .collect();
+    if !missing.is_empty() {
+        let listed = missing.iter().map(|ch| format!("U+{:04X}", *ch as u32)).collect::<Vec<_>>().join(", ");
+        warnings.push(match data.fallback_index {
+            Some(_) => format!("Sample text characters not in the font, drawn with the fallback glyph: {}", listed),
+            None => format!("Sample text characters not in the font, skipped: {}", listed),
+        });
+    }
+    if rendered.overflow {
+        warnings.push(format!(
+            "Sample text does not fit {}x{}px and was clipped",
+            rendered.image.width, rendered.image.height
+        ));
+    }
+
+    Ok((rendered, warnings))
+}
+
+/// Runs the shared part of generate/export: charset selection, sizing and rasterization.
+fn bake_font<'a>(job: &'a FontJob, loaded: &'a LoadedFont) -> Result<BakedFont<'a>, String> {
+    if job.range.start > job.range.end {
         return Err("Invalid range: start must be <= end".to_string());
     }
+    let storage = StorageAttrs::from_options(
+        &job.storage_preset,
+        &job.storage_section,
+        job.storage_alignment,
+        &job.storage_qualifier,
+    )?;
+
+    let mut encoding = LegacyEncoding::from_option(&job.output_encoding)?;
+    if !matches!(job.icon_constants.as_str(), "enum" | "define" | "utf8") {
+        return Err(format!("Unknown icon constant style: {}", job.icon_constants));
+    }
+    let (icons, icon_warnings) = match loaded.face() {
+        Some(face) => resolve_icons(&face, &job.icon_names),
+        None if !job.icon_names.is_empty() => (
+            Vec::new(),
+            vec!["Icon names need an OpenType font source, ignored".to_string()],
+        ),
+        None => (Vec::new(), Vec::new()),
+    };
+    let custom = job
+        .custom_glyphs
+        .iter()
+        .map(load_custom_glyph)
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut strings: Vec<NamedString> = Vec::new();
+    if !job.strings_file.trim().is_empty() {
+        strings.extend(load_strings_file(job.strings_file.trim())?);
+    }
+    strings.extend(job.strings.iter().cloned());
+    let mut extra_codepoints: Vec<u32> = icons.iter().map(|icon| icon.codepoint).collect();
+    extra_codepoints.extend(custom.iter().map(|glyph| glyph.codepoint));
+    extra_codepoints.extend(strings.iter().flat_map(|s| s.text.chars().map(|ch| ch as u32)));
+    let conflicts: Vec<String> = custom
+        .iter()
+        .filter(|glyph| {
+            char::from_u32(glyph.codepoint).is_some_and(|ch| loaded.font.lookup_glyph_index(ch) != 0)
+        })
+        .map(|glyph| format!("U+{:04X}", glyph.codepoint))
+        .collect();
+    let font = GlyphSet::new(&loaded.font, custom)?;
+    for glyph_override in &job.glyph_overrides {
+        glyph_override.validate()?;
+    }
+    let missing = missing_glyphs(&strings, |ch| font.lookup_glyph_index(ch) != 0);
+    if !missing.is_empty() {
+        return Err(missing.join("\n"));
+    }
 
-    let (codepoint_map, warnings) = collect_codepoints(&args.job, &font);
-    let fallback_cp = args
-        .job
+    let (mut codepoint_map, mut warnings) = collect_codepoints(job, &font, &extra_codepoints);
+    warnings.extend(icon_warnings);
+    if encoding.is_some() && job.output_kind == "bdf" {
+        warnings.push("BDF output is always keyed by Unicode, output encoding ignored".to_string());
+        encoding = None;
+    }
+    let legacy_chars = match &encoding {
+        Some(encoding) => {
+            let (legacy_chars, legacy_warnings) = legacy_codes(&mut codepoint_map, encoding);
+            warnings.extend(legacy_warnings);
+            legacy_chars
+        }
+        None => BTreeMap::new(),
+    };
+    if !conflicts.is_empty() {
+        warnings.push(format!(
+            "Custom glyphs replace the font's own glyphs at {}",
+            conflicts.join(", ")
+        ));
+    }
+    if let Some(license) = &loaded.license {
+        warnings.extend(license.warnings());
+    }
+    let fallback_char = job
         .fallback_char
         .as_deref()
-        .and_then(|s| s.trim().chars().next())
-        .map(|c| c as u32);
-    let glyph_data = build_glyph_data(
+        .and_then(|s| s.trim().chars().next());
+    let fallback = match fallback_char {
+        Some(ch) if font.lookup_glyph_index(ch) != 0 => Some(FallbackGlyph::Glyph {
+            codepoint: ch as u32,
+            glyph_index: font.lookup_glyph_index(ch),
+        }),
+        Some(ch) if job.synthesize_tofu => {
+            warnings.push(format!(
+                "Fallback U+{:04X} not found in font, using a synthesized box",
+                ch as u32
+            ));
+            Some(FallbackGlyph::Tofu)
+        }
+        Some(ch) => {
+            warnings.push(format!("Fallback U+{:04X} not found in font", ch as u32));
+            None
+        }
+        None if job.synthesize_tofu => Some(FallbackGlyph::Tofu),
+        None => None,
+    };
+
+    let mut settings = RasterSettings::from_job(job);
+    match (job.size_mode.as_str(), &loaded.font) {
+        ("em", _) => {}
+        (mode @ ("cap_height" | "x_height"), SourceFont::Bitmap(_)) => {
+            warnings.push(format!("Size mode {} does not apply to bitmap fonts; using the font's size", mode));
+        }
+        (mode @ ("cap_height" | "x_height"), SourceFont::Outline(outline)) => {
+            let (ch, what) = if mode == "x_height" { ('x', "x-height") } else { ('H', "cap height") };
+            let (size_px, measured) = fit_size_to_height(outline, ch, what, job.size_px)?;
+            if measured != job.size_px {
+                warnings.push(format!(
+                    "No size draws a {}px {}, using {}px ({}px {})",
+                    job.size_px, what, size_px, measured, what
+                ));
+            }
+            settings.size_px = size_px;
+        }
+        (other, _) => return Err(format!("Unknown size mode: {}", other)),
+    }
+    if let Some(bitmap) = font.as_bitmap() {
+        if job.size_px != bitmap.pixel_size {
+            warnings.push(format!(
+                "{} font is {}px, size {}px ignored",
+                bitmap.format, bitmap.pixel_size, job.size_px
+            ));
+        }
+        settings.size_px = bitmap.pixel_size;
+        settings.pixel_exact = true;
+    }
+    let face = loaded.face();
+    let pixel_grid = face
+        .as_ref()
+        .and_then(|f| detect_pixel_grid(f, codepoint_map.values().copied().filter(|g| font.custom(*g).is_none())));
+    if let Some(grid) = &pixel_grid {
+        apply_pixel_grid(grid, job.snap_to_pixel_grid, &mut settings, &mut warnings);
+    }
+    let parameters = provenance_parameters(job, &settings);
+    let mut provenance = Provenance::new(job, loaded.file_sha256.clone(), face.as_ref(), parameters)?;
+    if let Some(bitmap) = font.as_bitmap() {
+        provenance.family = bitmap.family.clone();
+    }
+
+    let unmatched: Vec<String> = job
+        .glyph_overrides
+        .iter()
+        .filter(|o| o.size_px == settings.size_px && !codepoint_map.contains_key(&o.codepoint))
+        .filter(|o| !matches!(fallback, Some(FallbackGlyph::Glyph { codepoint, .. }) if codepoint == o.codepoint))
+        .map(|o| format!("U+{:04X}", o.codepoint))
+        .collect();
+    if !unmatched.is_empty() {
+        warnings.push(format!(
+            "Glyph overrides at {}px for codepoints outside the charset: {}",
+            settings.size_px,
+            unmatched.join(", ")
+        ));
+    }
+
+    let (mut line_height, mut baseline) = line_metrics(&loaded.font, settings.size_px);
+    baseline += job.baseline_shift;
+    if job.line_height > 0 {
+        line_height = job.line_height as i32;
+    } else if job.cell_height > 0 {
+        line_height = job.cell_height as i32;
+    }
+    if job.cell_height > 0 {
+        settings.cell = Some((baseline, job.cell_height));
+    }
+
+    let strike_face = if job.use_bitmap_strikes { face } else { None };
+    let mut glyph_data = build_glyph_data(
         &font,
-        args.job.size_px,
+        strike_face.as_ref(),
         &codepoint_map,
-        fallback_cp,
-        &args.job.binarize_mode,
-        args.job.threshold,
-        args.job.gamma,
-        args.job.oversample,
-    );
-    let (line_height, baseline) = line_metrics(&font, args.job.size_px);
-    let cpp_module = generate_cpp_module(&args.job, &glyph_data, line_height, baseline);
-
-    write_atomic(&file_path, cpp_module.as_bytes())?;
+        fallback.as_ref(),
+        &settings,
+        &mut warnings,
+    )?;
+    if encoding.is_some() {
+        rekey_glyph_data(&mut glyph_data, &legacy_chars)?;
+    }
+    if job.lookup_strategy == "merged_ranges" && job.output_kind == "bdf" {
+        warnings.push("BDF output has no lookup tables, range gaps left empty".to_string());
+    } else if job.lookup_strategy == "merged_ranges" {
+        let filled = fill_range_gaps(&mut glyph_data, job.lookup_merge_gap)?;
+        if filled > 0 {
+            warnings.push(format!(
+                "Filled {} gap codepoints with the fallback glyph to merge ranges",
+                filled
+            ));
+        }
+    }
+    let strings = encode_strings(
+        &strings,
+        |ch| {
+            let key = match &encoding {
+                Some(encoding) => encoding.code(ch as u32).filter(|code| legacy_chars.get(code) == Some(&(ch as u32)))?,
+                None => ch as u32,
+            };
+            glyph_data.codepoints.binary_search(&key).ok()
+        },
+        |id| glyph_data.glyphs[id].x_advance,
+    )?;
+    if !strings.is_empty() && job.output_kind == "bdf" {
+        warnings.push("String tables are not part of BDF output, ignored".to_string());
+    }
+    let lookup = build_lookup(
+        &job.lookup_strategy,
+        &glyph_data.codepoints,
+        glyph_data.ranges.len(),
+        glyph_data.fallback_index,
+    )?;
+    let layout = match job.glyph_layout.as_str() {
+        "standard" => None,
+        "compact" => Some(CompactLayout::plan(&GLYPH_FIELDS, &glyph_rows(&glyph_data), false)?),
+        "bitfield" => Some(CompactLayout::plan(&GLYPH_FIELDS, &glyph_rows(&glyph_data), true)?),
+        other => return Err(format!("Unknown glyph layout: {}", other)),
+    };
+    let module_output = !matches!(job.output_kind.as_str(), "binary_blob" | "bdf");
+    let name = job.export_name.trim();
+    let name = if name.is_empty() { "font" } else { name };
+    if layout.is_some() && module_output {
+        warnings.push(format!(
+            "The {} glyph layout exports no Font object; code taking `const Font&` must switch to {}_glyph() and the {}_* constants",
+            job.glyph_layout, name, name
+        ));
+    }
+    if storage.progmem() && module_output {
+        warnings.push(format!(
+            "PROGMEM tables live in flash, which ui_font's Font reads as RAM; use {}_glyph(), {}_glyph_index() and {}_bitmap_byte() instead",
+            name, name, name
+        ));
+    }
+    if let Some(face) = &strike_face {
+        warnings.extend(strike_warnings(face, settings.size_px, &glyph_data));
+    }
 
-    Ok(ExportResult {
-        ok: true,
+    Ok(BakedFont {
+        glyph_set: font,
+        settings,
+        strike_face,
+        codepoint_map,
+        glyph_data,
         warnings,
-        output_path: Some(file_path.to_string_lossy().to_string()),
+        line_height,
+        baseline,
+        pixel_grid,
+        lookup,
+        layout,
+        storage,
+        provenance,
+        license: loaded.license.clone(),
+        icons,
+        strings,
+        encoding,
+        legacy_chars,
     })
 }
 
-fn load_font_from_source(source: &FontSource) -> Result<Font, String> {
+fn apply_pixel_grid(
+    grid: &PixelGrid,
+    snap: bool,
+    settings: &mut RasterSettings,
+    warnings: &mut Vec<String>,
+) {
+    let requested = settings.size_px;
+    if !grid.is_clean_size(requested) {
+        let suggested: Vec<String> = grid.suggested_sizes.iter().map(|s| s.to_string()).collect();
+        let suggested = if suggested.is_empty() { "none".to_string() } else { suggested.join(", ") };
+        match grid.snap_size(requested).filter(|_| snap) {
+            Some(snapped) => {
+                warnings.push(format!(
+                    "Pixel font grid is {}px, snapped size from {}px to {}px",
+                    grid.design_px, requested, snapped
+                ));
+                settings.size_px = snapped;
+            }
+            None => {
+                warnings.push(format!(
+                    "Pixel font grid is {}px, {}px will distort the design (clean sizes: {})",
+                    grid.design_px, requested, suggested
+                ));
+                return;
+            }
+        }
+    }
+    settings.pixel_exact = true;
+}
+
+pub(crate) fn load_font_from_source(source: &FontSource) -> Result<LoadedFont, String> {
     match source {
-        FontSource::File { path } => {
+        FontSource::File { path, font_index } => {
             let bytes = fs::read(path)
                 .map_err(|e| format!("Failed to read font file {}: {}", path, e))?;
-            Font::from_bytes(bytes, FontSettings::default())
+            let file_sha256 = sha256_hex(&bytes);
+            let bytes = unwrap_web_font(bytes)
+                .map_err(|e| format!("Failed to read font file {}: {}", path, e))?;
+            LoadedFont::from_bytes(bytes, *font_index, file_sha256)
                 .map_err(|e| format!("Failed to parse font file {}: {}", path, e))
         }
-        FontSource::System { family } => {
+        FontSource::Bitmap { path } => {
+            let bytes = fs::read(path)
+                .map_err(|e| format!("Failed to read font file {}: {}", path, e))?;
+            LoadedFont::from_bitmap(bytes)
+                .map_err(|e| format!("Failed to parse bitmap font {}: {}", path, e))
+        }
+        FontSource::SpriteSheet(spec) => {
+            let bytes = fs::read(&spec.path)
+                .map_err(|e| format!("Failed to read sprite sheet {}: {}", spec.path, e))?;
+            LoadedFont::from_sprite_sheet(bytes, spec)
+                .map_err(|e| format!("Failed to import sprite sheet {}: {}", spec.path, e))
+        }
+        FontSource::System { family, postscript_name } => {
             let source = SystemSource::new();
             let family_handle = source
                 .select_family_by_name(family)
                 .map_err(|e| format!("Failed to find system font {}: {}", family, e))?;
-            let handle = family_handle
-                .fonts()
-                .first()
-                .ok_or_else(|| format!("No fonts found for family {}", family))?;
-            match handle {
-                Handle::Path { path, .. } => {
-                    let bytes = fs::read(path)
-                        .map_err(|e| format!("Failed to read font file {}: {}", path.display(), e))?;
-                    Font::from_bytes(bytes, FontSettings::default())
-                        .map_err(|e| format!("Failed to parse font file {}: {}", path.display(), e))
-                }
-                Handle::Memory { bytes, .. } => {
-                    Font::from_bytes((**bytes).clone(), FontSettings::default())
-                        .map_err(|e| format!("Failed to parse in-memory font {}: {}", family, e))
+            if family_handle.fonts().is_empty() {
+                return Err(format!("No fonts found for family {}", family));
+            }
+            for handle in family_handle.fonts() {
+                let loaded = match handle {
+                    Handle::Path { path, font_index } => {
+                        let bytes = fs::read(path)
+                            .map_err(|e| format!("Failed to read font file {}: {}", path.display(), e))?;
+                        let file_sha256 = sha256_hex(&bytes);
+                        LoadedFont::from_bytes(bytes, *font_index, file_sha256)
+                            .map_err(|e| format!("Failed to parse font file {}: {}", path.display(), e))?
+                    }
+                    Handle::Memory { bytes, font_index } => {
+                        LoadedFont::from_bytes((**bytes).clone(), *font_index, sha256_hex(bytes))
+                            .map_err(|e| format!("Failed to parse in-memory font {}: {}", family, e))?
+                    }
+                };
+                let Some(wanted) = postscript_name else {
+                    return Ok(loaded);
+                };
+                if loaded.face().and_then(|face| system_fonts::postscript_name(&face)).as_ref() == Some(wanted) {
+                    return Ok(loaded);
                 }
             }
+            Err(format!(
+                "System font family {} has no face named {}",
+                family,
+                postscript_name.as_deref().unwrap_or_default()
+            ))
         }
     }
 }
 
-fn collect_codepoints(job: &FontJob, font: &Font) -> (BTreeMap<u32, u16>, Vec<String>) {
+/// Removes characters the legacy set cannot key, before they are rasterized, and maps the
+/// remaining ones by code.
+fn legacy_codes(codepoint_map: &mut BTreeMap<u32, u16>, encoding: &LegacyEncoding) -> (BTreeMap<u32, u32>, Vec<String>) {
+    let mut legacy_chars: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut unmapped: BTreeSet<u32> = BTreeSet::new();
+    let mut shadowed: BTreeSet<u32> = BTreeSet::new();
+    for cp in codepoint_map.keys() {
+        let Some(code) = encoding.code(*cp) else {
+            unmapped.insert(*cp);
+            continue;
+        };
+        // Some codes are shared (Shift-JIS 0x5C encodes both the backslash and the yen sign); the
+        // character the code decodes back to keeps it.
+        match legacy_chars.get(&code) {
+            Some(_) if encoding.decode(code) != Some(*cp) => {
+                shadowed.insert(*cp);
+            }
+            Some(other) => {
+                shadowed.insert(*other);
+                legacy_chars.insert(code, *cp);
+            }
+            None => {
+                legacy_chars.insert(code, *cp);
+            }
+        }
+    }
+    codepoint_map.retain(|cp, _| !unmapped.contains(cp) && !shadowed.contains(cp));
+
+    let list = |cps: &BTreeSet<u32>| {
+        let shown = cps.iter().take(16).map(|cp| format!("U+{:04X}", cp)).collect::<Vec<_>>().join(", ");
+        let more = if cps.len() > 16 { ", ..." } else { "" };
+        format!("{}{}", shown, more)
+    };
+    let mut warnings = Vec::new();
+    if !unmapped.is_empty() {
+        warnings.push(format!(
+            "{} characters have no {} mapping and were left out ({})",
+            unmapped.len(),
+            encoding.label,
+            list(&unmapped)
+        ));
+    }
+    if !shadowed.is_empty() {
+        warnings.push(format!(
+            "{} characters share a {} code with another character and were left out ({})",
+            shadowed.len(),
+            encoding.label,
+            list(&shadowed)
+        ));
+    }
+    (legacy_chars, warnings)
+}
+
+fn collect_codepoints(job: &FontJob, font: &GlyphSet, extra: &[u32]) -> (BTreeMap<u32, u16>, Vec<String>) {
     let mut warnings = Vec::new();
     let mut requested: BTreeSet<u32> = BTreeSet::new();
 
@@ -301,6 +1298,7 @@ fn collect_codepoints(job: &FontJob, font: &Font) -> (BTreeMap<u32, u16>, Vec<St
             requested.insert(ch as u32);
         }
     }
+    requested.extend(extra);
 
     let fallback = parse_fallback_char(job, &mut warnings);
     let mut final_map: BTreeMap<u32, u16> = BTreeMap::new();
@@ -458,67 +1456,157 @@ fn downsample_gray(
 }
 
 fn rasterize_gray(
-    font: &Font,
+    font: &GlyphSet,
+    strike_face: Option<&ttf_parser::Face>,
     glyph_index: u16,
-    size_px: u32,
-    mode: &str,
-    gamma: f32,
-    oversample: u32,
-) -> (fontdue::Metrics, Vec<u8>) {
-    let (metrics, bitmap) = font.rasterize_indexed(glyph_index, size_px as f32);
-    match parse_binarize_mode(mode) {
-        BinarizeMode::Mask => (metrics, bitmap),
-        BinarizeMode::Mask1Bit => (metrics, bitmap),
+    settings: &RasterSettings,
+) -> (fontdue::Metrics, Vec<u8>, GlyphSource) {
+    let size_px = settings.size_px;
+    if font.custom(glyph_index).is_some() {
+        let (metrics, mut bitmap) = font.rasterize_indexed(glyph_index, size_px as f32);
+        if settings.pixel_exact {
+            snap_coverage(&mut bitmap);
+        } else if matches!(parse_binarize_mode(settings.binarize_mode), BinarizeMode::GammaOversample) {
+            bitmap = apply_gamma(&bitmap, settings.gamma);
+        }
+        return (metrics, bitmap, GlyphSource::Custom);
+    }
+    if let Some(strike) = strike_face.and_then(|face| strike_glyph(face, glyph_index, size_px)) {
+        let outline = font.metrics_indexed(glyph_index, size_px as f32);
+        let metrics = fontdue::Metrics {
+            xmin: strike.x,
+            ymin: strike.y,
+            width: strike.width as usize,
+            height: strike.height as usize,
+            advance_width: strike.advance as f32,
+            advance_height: outline.advance_height,
+            ..Default::default()
+        };
+        return (metrics, strike.gray, GlyphSource::Strike);
+    }
+
+    let (metrics, mut bitmap) = font.rasterize_indexed(glyph_index, size_px as f32);
+    if font.as_bitmap().is_some() {
+        return (metrics, bitmap, GlyphSource::Bitmap);
+    }
+    if settings.pixel_exact {
+        snap_coverage(&mut bitmap);
+        return (metrics, bitmap, GlyphSource::Outline);
+    }
+    let bitmap = match parse_binarize_mode(settings.binarize_mode) {
+        BinarizeMode::Mask => bitmap,
+        BinarizeMode::Mask1Bit => bitmap,
         BinarizeMode::GammaOversample => {
-            let os = clamp_oversample(oversample);
+            let os = clamp_oversample(settings.oversample);
             if os <= 1 {
-                return (metrics, apply_gamma(&bitmap, gamma));
+                apply_gamma(&bitmap, settings.gamma)
+            } else {
+                let (os_metrics, os_bitmap) =
+                    font.rasterize_indexed(glyph_index, size_px as f32 * os as f32);
+                downsample_gray(
+                    &os_bitmap,
+                    os_metrics.width,
+                    os_metrics.height,
+                    metrics.width,
+                    metrics.height,
+                    settings.gamma,
+                )
             }
-            let (os_metrics, os_bitmap) =
-                font.rasterize_indexed(glyph_index, size_px as f32 * os as f32);
-            let dst_w = metrics.width as usize;
-            let dst_h = metrics.height as usize;
-            let src_w = os_metrics.width as usize;
-            let src_h = os_metrics.height as usize;
-            let downs = downsample_gray(&os_bitmap, src_w, src_h, dst_w, dst_h, gamma);
-            (metrics, downs)
         }
+    };
+    (metrics, bitmap, GlyphSource::Outline)
+}
+
+/// Rasterizes the glyph shown at `codepoint` and applies its override for the current size, if any.
+fn render_glyph(
+    font: &GlyphSet,
+    strike_face: Option<&ttf_parser::Face>,
+    codepoint: u32,
+    glyph_index: u16,
+    settings: &RasterSettings,
+    warnings: &mut Vec<String>,
+) -> (fontdue::Metrics, Vec<u8>, GlyphSource) {
+    let (mut metrics, mut bitmap, source) = rasterize_gray(font, strike_face, glyph_index, settings);
+    match find_override(settings.overrides, codepoint, settings.size_px).map(|o| o.apply(&mut metrics, &mut bitmap)) {
+        Some(Ok(())) => (metrics, bitmap, GlyphSource::Override),
+        Some(Err(warning)) => {
+            warnings.push(warning);
+            (metrics, bitmap, source)
+        }
+        None => (metrics, bitmap, source),
+    }
+}
+
+/// Glyphs to render in codepoint order: one per glyph index, except that overridden codepoints
+/// get their own entry so a patch never leaks onto other characters sharing the glyph.
+fn render_list(codepoint_map: &BTreeMap<u32, u16>, settings: &RasterSettings) -> Vec<(u32, u16)> {
+    let mut seen: HashSet<u16> = HashSet::new();
+    codepoint_map
+        .iter()
+        .filter(|(cp, glyph_index)| {
+            find_override(settings.overrides, **cp, settings.size_px).is_some() || seen.insert(**glyph_index)
+        })
+        .map(|(cp, glyph_index)| (*cp, *glyph_index))
+        .collect()
+}
+
+fn strike_warnings(face: &ttf_parser::Face, size_px: u32, data: &GlyphData) -> Vec<String> {
+    let sizes = strike_sizes(face);
+    if sizes.is_empty() {
+        return Vec::new();
+    }
+    if !sizes.iter().any(|s| *s as u32 == size_px) {
+        let available: Vec<String> = sizes.iter().map(|s| s.to_string()).collect();
+        return vec![format!(
+            "No embedded bitmap strike at {}px (available: {}), rasterizing outlines",
+            size_px,
+            available.join(", ")
+        )];
+    }
+    let outline_cps: Vec<String> = data
+        .packed_glyphs
+        .iter()
+        .filter(|g| g.source == GlyphSource::Outline)
+        .map(|g| format!("U+{:04X}", g.codepoint))
+        .collect();
+    if outline_cps.is_empty() {
+        return Vec::new();
     }
+    let shown = outline_cps.iter().take(16).cloned().collect::<Vec<_>>().join(", ");
+    let more = if outline_cps.len() > 16 { ", ..." } else { "" };
+    vec![format!(
+        "{} glyphs have no {}px bitmap strike and were rasterized from outlines ({}{})",
+        outline_cps.len(),
+        size_px,
+        shown,
+        more
+    )]
 }
 
 fn build_preview(
-    font: &Font,
-    size_px: u32,
+    font: &GlyphSet,
+    strike_face: Option<&ttf_parser::Face>,
     codepoint_map: &BTreeMap<u32, u16>,
-    binarize_mode: &str,
-    threshold: u8,
-    gamma: f32,
-    oversample: u32,
+    settings: &RasterSettings,
 ) -> (Vec<PreviewGlyph>, Option<(usize, usize)>) {
     let mut glyphs = Vec::new();
     let mut total_bytes: usize = 0;
     let mut truncated: Option<(usize, usize)> = None;
 
-    let mut seen: HashSet<u16> = HashSet::new();
-    let mut unique_indices: Vec<u16> = Vec::new();
-    let mut representative_cp: BTreeMap<u16, u32> = BTreeMap::new();
-    for (cp, glyph_index) in codepoint_map.iter() {
-        if seen.insert(*glyph_index) {
-            unique_indices.push(*glyph_index);
-            representative_cp.insert(*glyph_index, *cp);
-        }
-    }
+    let render = render_list(codepoint_map, settings);
 
-    for glyph_index in unique_indices.iter().take(PREVIEW_MAX_GLYPHS) {
-        let (metrics, bitmap) = rasterize_gray(font, *glyph_index, size_px, binarize_mode, gamma, oversample);
-        let (raw_metrics, raw_bitmap) = font.rasterize_indexed(*glyph_index, size_px as f32);
-        let codepoint = representative_cp.get(glyph_index).copied().unwrap_or(0);
+    for (codepoint, glyph_index) in render.iter().take(PREVIEW_MAX_GLYPHS) {
+        // Override warnings are reported once, by `build_glyph_data`.
+        let (mut metrics, mut bitmap, source) =
+            render_glyph(font, strike_face, *codepoint, *glyph_index, settings, &mut Vec::new());
+        clip_to_cell(&mut metrics, &mut bitmap, settings);
+        let (raw_metrics, raw_bitmap) = font.rasterize_indexed(*glyph_index, settings.size_px as f32);
+        let codepoint = *codepoint;
         let w = metrics.width as u32;
         let h = metrics.height as u32;
         let advance = metrics.advance_width as u32;
         let bitmap_b64 = BASE64_STANDARD.encode(&bitmap);
-        let mono_threshold = if binarize_mode == "mask_1bit" { 1 } else { threshold };
-        let (mono, _stride) = pack_bitmap_1b(&bitmap, w, h, mono_threshold);
+        let (mono, _stride) = pack_bitmap_1b(&bitmap, w, h, settings.mono_threshold());
         let mono_b64 = BASE64_STANDARD.encode(&mono);
         let raw_b64 = if raw_metrics.width == metrics.width && raw_metrics.height == metrics.height {
             BASE64_STANDARD.encode(&raw_bitmap)
@@ -538,146 +1626,485 @@ fn build_preview(
             w,
             h,
             advance,
+            source,
             bitmap_b64,
             mono_b64,
             raw_b64,
         });
     }
 
-    if truncated.is_none() && unique_indices.len() > PREVIEW_MAX_GLYPHS {
-        truncated = Some((glyphs.len(), total_bytes));
+    if truncated.is_none() && render.len() > PREVIEW_MAX_GLYPHS {
+        truncated = Some((glyphs.len(), total_bytes));
+    }
+
+    (glyphs, truncated)
+}
+
+/// Packs glyph bitmaps into one blob, sharing bytes between identical bitmaps.
+#[derive(Default)]
+struct BitmapStore {
+    bitmaps: Vec<u8>,
+    packed_glyphs: Vec<PackedGlyph>,
+    stored: HashMap<Vec<u8>, (usize, u32)>,
+    dedup_saved_bytes: usize,
+    max_w: u32,
+    max_h: u32,
+}
+
+impl BitmapStore {
+    fn push(
+        &mut self,
+        codepoint: u32,
+        metrics: &fontdue::Metrics,
+        gray: &[u8],
+        source: GlyphSource,
+        threshold: u8,
+    ) -> GlyphEntry {
+        let w = metrics.width as u32;
+        let h = metrics.height as u32;
+        self.max_w = self.max_w.max(w);
+        self.max_h = self.max_h.max(h);
+        let (packed, _stride) = pack_bitmap_1b(gray, w, h, threshold);
+        let len = packed.len();
+        let (offset, shared_with) = if len == 0 {
+            (0, None)
+        } else if let Some((offset, owner)) = self.stored.get(&packed) {
+            self.dedup_saved_bytes += len;
+            (*offset, Some(*owner))
+        } else {
+            let offset = self.bitmaps.len();
+            self.bitmaps.extend_from_slice(&packed);
+            self.stored.insert(packed, (offset, codepoint));
+            (offset, None)
+        };
+        self.packed_glyphs.push(PackedGlyph {
+            codepoint,
+            offset,
+            len,
+            source,
+            shared_with,
+        });
+
+        GlyphEntry {
+            offset,
+            width: metrics.width as i32,
+            height: metrics.height as i32,
+            x_advance: metrics.advance_width.round() as i32,
+            x_offset: metrics.xmin,
+            y_offset: metrics.ymin + metrics.height as i32,
+        }
+    }
+}
+
+fn build_glyph_data(
+    font: &GlyphSet,
+    strike_face: Option<&ttf_parser::Face>,
+    codepoint_map: &BTreeMap<u32, u16>,
+    fallback: Option<&FallbackGlyph>,
+    settings: &RasterSettings,
+    warnings: &mut Vec<String>,
+) -> Result<GlyphData, String> {
+    let mut store = BitmapStore::default();
+    let mut glyph_info: HashMap<u16, GlyphEntry> = HashMap::new();
+    let mut overridden: HashMap<u32, GlyphEntry> = HashMap::new();
+    let mut clipped: Vec<(u32, usize)> = Vec::new();
+    let mut render = |codepoint: u32, glyph_index: u16| {
+        let (mut metrics, mut bitmap, source) =
+            render_glyph(font, strike_face, codepoint, glyph_index, settings, warnings);
+        let removed = clip_to_cell(&mut metrics, &mut bitmap, settings);
+        if removed > 0 {
+            clipped.push((codepoint, removed));
+        }
+        (metrics, bitmap, source)
+    };
+
+    for (codepoint, glyph_index) in render_list(codepoint_map, settings) {
+        let (metrics, bitmap, source) = render(codepoint, glyph_index);
+        let entry = store.push(codepoint, &metrics, &bitmap, source, settings.mono_threshold());
+        if find_override(settings.overrides, codepoint, settings.size_px).is_some() {
+            overridden.insert(codepoint, entry);
+        } else {
+            glyph_info.insert(glyph_index, entry);
+        }
+    }
+
+    let mut glyphs: Vec<GlyphEntry> = Vec::with_capacity(codepoint_map.len() + 1);
+    let mut codepoints: Vec<u32> = Vec::with_capacity(codepoint_map.len());
+    let mut fallback_index: Option<usize> = None;
+
+    for (cp, glyph_index) in codepoint_map.iter() {
+        if let Some(entry) = overridden.get(cp).or_else(|| glyph_info.get(glyph_index)) {
+            glyphs.push(*entry);
+        }
+        if matches!(fallback, Some(FallbackGlyph::Glyph { codepoint, .. }) if codepoint == cp) {
+            fallback_index = Some(codepoints.len());
+        }
+        codepoints.push(*cp);
+    }
+
+    // A fallback outside the selected charset is stored after the ranged entries, where only
+    // `fallback_glyph` and the lookup miss path can reach it.
+    if fallback_index.is_none() {
+        let extra = match fallback {
+            Some(FallbackGlyph::Glyph { codepoint, glyph_index }) => {
+                let (metrics, bitmap, source) = render(*codepoint, *glyph_index);
+                Some(store.push(*codepoint, &metrics, &bitmap, source, settings.mono_threshold()))
+            }
+            Some(FallbackGlyph::Tofu) => {
+                let (mut metrics, mut bitmap) = synthesize_tofu(settings.size_px);
+                let removed = clip_to_cell(&mut metrics, &mut bitmap, settings);
+                if removed > 0 {
+                    clipped.push((0, removed));
+                }
+                Some(store.push(0, &metrics, &bitmap, GlyphSource::Synthesized, 1))
+            }
+            None => None,
+        };
+        if let Some(entry) = extra {
+            fallback_index = Some(glyphs.len());
+            glyphs.push(entry);
+        }
+    }
+
+    if let Some((_, cell_height)) = settings.cell.filter(|_| !clipped.is_empty()) {
+        let pixels: usize = clipped.iter().map(|(_, removed)| removed).sum();
+        let shown: Vec<String> = clipped
+            .iter()
+            .take(16)
+            .map(|(cp, _)| match cp {
+                0 => "fallback box".to_string(),
+                cp => format!("U+{:04X}", cp),
+            })
+            .collect();
+        let more = if clipped.len() > 16 { ", ..." } else { "" };
+        warnings.push(format!(
+            "Clipped {} pixels from {} glyphs to the {}px cell ({}{})",
+            pixels,
+            clipped.len(),
+            cell_height,
+            shown.join(", "),
+            more
+        ));
+    }
+
+    let ranges = build_ranges(&codepoints)?;
+
+    Ok(GlyphData {
+        bitmaps: store.bitmaps,
+        packed_glyphs: store.packed_glyphs,
+        dedup_saved_bytes: store.dedup_saved_bytes,
+        glyphs,
+        codepoints,
+        ranges,
+        fallback_index,
+        max_w: store.max_w,
+        max_h: store.max_h,
+    })
+}
+
+/// Cuts the rows of a glyph outside the cell, which starts at the line top with the baseline
+/// `baseline` rows down. Returns the number of inked pixels removed.
+fn clip_to_cell(metrics: &mut fontdue::Metrics, bitmap: &mut Vec<u8>, settings: &RasterSettings) -> usize {
+    let Some((baseline, cell_height)) = settings.cell else {
+        return 0;
+    };
+    let (w, h) = (metrics.width, metrics.height as i32);
+    let top = baseline - (metrics.ymin + h);
+    let first = (-top).clamp(0, h);
+    let last = (cell_height as i32 - top).clamp(first, h);
+    if first == 0 && last == h {
+        return 0;
+    }
+    let threshold = settings.mono_threshold();
+    let removed = bitmap
+        .chunks(w.max(1))
+        .enumerate()
+        .filter(|(row, _)| (*row as i32) < first || *row as i32 >= last)
+        .flat_map(|(_, pixels)| pixels)
+        .filter(|v| **v >= threshold)
+        .count();
+    *bitmap = bitmap[first as usize * w..last as usize * w].to_vec();
+    metrics.ymin += h - last;
+    metrics.height = (last - first) as usize;
+    removed
+}
+
+/// Draws a hollow box roughly the size of a capital letter, used when the font has no fallback.
+fn synthesize_tofu(size_px: u32) -> (fontdue::Metrics, Vec<u8>) {
+    let width = (size_px as usize / 2).max(3);
+    let height = (size_px as usize * 7 / 10).max(4);
+    let mut bitmap = vec![0u8; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                bitmap[y * width + x] = 255;
+            }
+        }
+    }
+    let metrics = fontdue::Metrics {
+        xmin: 1,
+        ymin: 0,
+        width,
+        height,
+        advance_width: (width + 2) as f32,
+        ..Default::default()
+    };
+    (metrics, bitmap)
+}
+
+/// Fills gaps of up to `max_gap` codepoints between ranges with copies of the fallback entry so
+/// neighbouring ranges merge. Returns the number of filled codepoints.
+fn fill_range_gaps(data: &mut GlyphData, max_gap: u32) -> Result<usize, String> {
+    let ranged = data.codepoints.len();
+    let fallback_cp = data.fallback_index.filter(|i| *i < ranged).map(|i| data.codepoints[i]);
+    // Any other filler would turn missing codepoints into lookup hits.
+    let filler = data
+        .fallback_index
+        .and_then(|i| data.glyphs.get(i).copied())
+        .ok_or("Merged ranges fill gaps with the fallback glyph; set a fallback character or synthesize a box")?;
+    let mut glyphs: Vec<GlyphEntry> = Vec::with_capacity(data.glyphs.len());
+    let mut codepoints: Vec<u32> = Vec::with_capacity(data.codepoints.len());
+    let mut filled = 0;
+    for (entry, cp) in data.glyphs.iter().zip(&data.codepoints) {
+        if let Some(prev) = codepoints.last().copied() {
+            if cp - prev - 1 <= max_gap {
+                for missing in prev + 1..*cp {
+                    glyphs.push(filler);
+                    codepoints.push(missing);
+                    filled += 1;
+                }
+            }
+        }
+        glyphs.push(*entry);
+        codepoints.push(*cp);
     }
+    data.fallback_index = match fallback_cp {
+        Some(cp) => codepoints.binary_search(&cp).ok(),
+        None => Some(glyphs.len()),
+    };
+    glyphs.extend_from_slice(&data.glyphs[ranged..]);
+    data.ranges = build_ranges(&codepoints)?;
+    data.glyphs = glyphs;
+    data.codepoints = codepoints;
+    Ok(filled)
+}
 
-    (glyphs, truncated)
+/// Reorders the ranged entries by legacy code and rebuilds the ranges over the codes. Entries
+/// behind the ranges, like a fallback outside the charset, stay where they are.
+fn rekey_glyph_data(data: &mut GlyphData, legacy_chars: &BTreeMap<u32, u32>) -> Result<(), String> {
+    let ranged = data.codepoints.len();
+    let position = |cp: u32| {
+        data.codepoints
+            .binary_search(&cp)
+            .map_err(|_| format!("U+{:04X} is missing from the glyph table", cp))
+    };
+    let mut glyphs: Vec<GlyphEntry> = Vec::with_capacity(data.glyphs.len());
+    for cp in legacy_chars.values() {
+        glyphs.push(data.glyphs[position(*cp)?]);
+    }
+    let codes: Vec<u32> = legacy_chars.keys().copied().collect();
+    data.fallback_index = match data.fallback_index {
+        Some(i) if i < ranged => {
+            let cp = data.codepoints[i];
+            legacy_chars.values().position(|c| *c == cp)
+        }
+        other => other,
+    };
+    glyphs.extend_from_slice(&data.glyphs[ranged..]);
+    data.ranges = build_ranges(&codes)?;
+    data.glyphs = glyphs;
+    data.codepoints = codes;
+    Ok(())
 }
 
-fn build_glyph_data(
-    font: &Font,
-    size_px: u32,
-    codepoint_map: &BTreeMap<u32, u16>,
-    fallback_cp: Option<u32>,
-    binarize_mode: &str,
-    threshold: u8,
-    gamma: f32,
-    oversample: u32,
-) -> GlyphData {
-    let mut unique_indices: Vec<u16> = Vec::new();
-    let mut seen: HashSet<u16> = HashSet::new();
-    let mut rep_cp: BTreeMap<u16, u32> = BTreeMap::new();
-    for (cp, glyph_index) in codepoint_map.iter() {
-        if seen.insert(*glyph_index) {
-            unique_indices.push(*glyph_index);
-            rep_cp.insert(*glyph_index, *cp);
+/// Splits the sorted codepoint list into runs of consecutive codepoints.
+fn build_ranges(codepoints: &[u32]) -> Result<Vec<GlyphRangeEntry>, String> {
+    let mut ranges: Vec<GlyphRangeEntry> = Vec::new();
+    for (index, cp) in codepoints.iter().enumerate() {
+        let index = u32::try_from(index)
+            .map_err(|_| format!("Glyph table too large: {} entries", codepoints.len()))?;
+        match ranges.last_mut() {
+            Some(range) if range.start.checked_add(range.length) == Some(*cp) => range.length += 1,
+            _ => ranges.push(GlyphRangeEntry {
+                start: *cp,
+                length: 1,
+                glyph_id_start: index,
+            }),
         }
     }
+    Ok(ranges)
+}
 
-    let mut bitmaps: Vec<u8> = Vec::new();
-    let mut packed_glyphs: Vec<PackedGlyph> = Vec::new();
-    let mut glyph_info: HashMap<u16, GlyphEntry> = HashMap::new();
-    let mut max_w: u32 = 0;
-    let mut max_h: u32 = 0;
+/// Generation parameters recorded in the provenance block, after pixel-grid size snapping.
+fn provenance_parameters(job: &FontJob, settings: &RasterSettings) -> Vec<(String, String)> {
+    let fallback = job.fallback_char.as_deref().unwrap_or("");
+    [
+        ("size_px", settings.size_px.to_string()),
+        ("range", format!("U+{:04X}-U+{:04X}", job.range.start, job.range.end)),
+        ("custom_chars", job.custom_chars.as_deref().unwrap_or("").chars().count().to_string()),
+        ("fallback", fallback.chars().next().map(|c| format!("U+{:04X}", c as u32)).unwrap_or_default()),
+        ("binarize_mode", settings.binarize_mode.to_string()),
+        ("threshold", settings.threshold.to_string()),
+        ("gamma", settings.gamma.to_string()),
+        ("oversample", settings.oversample.to_string()),
+        ("glyph_layout", job.glyph_layout.clone()),
+        ("lookup_strategy", job.lookup_strategy.clone()),
+        ("storage_preset", job.storage_preset.clone()),
+        ("output_encoding", job.output_encoding.clone()),
+        ("size_mode", job.size_mode.clone()),
+        ("line_height", job.line_height.to_string()),
+        ("baseline_shift", job.baseline_shift.to_string()),
+        ("cell_height", job.cell_height.to_string()),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
 
-    for glyph_index in unique_indices {
-        let (metrics, bitmap) = rasterize_gray(font, glyph_index, size_px, binarize_mode, gamma, oversample);
-        let w = metrics.width as u32;
-        let h = metrics.height as u32;
-        if w > max_w {
-            max_w = w;
-        }
-        if h > max_h {
-            max_h = h;
-        }
-        let mono_threshold = if binarize_mode == "mask_1bit" { 1 } else { threshold };
-        let (packed, _stride) = pack_bitmap_1b(&bitmap, w, h, mono_threshold);
-        let offset = bitmaps.len();
-        let len = packed.len();
-        bitmaps.extend_from_slice(&packed);
-        packed_glyphs.push(PackedGlyph {
-            codepoint: *rep_cp.get(&glyph_index).unwrap_or(&0),
-            offset,
-            len,
-        });
+fn blob_layout(job: &FontJob) -> Result<BlobLayout, String> {
+    let big_endian = match job.blob_endianness.as_str() {
+        "little" => false,
+        "big" => true,
+        other => return Err(format!("Unknown blob endianness: {}", other)),
+    };
+    Ok(BlobLayout {
+        big_endian,
+        alignment: job.blob_alignment as usize,
+    })
+}
 
-        let x_advance = metrics.advance_width.round() as i32;
-        let x_offset = metrics.xmin;
-        let y_offset = metrics.ymin + metrics.height as i32;
+fn notice_lines(baked: &BakedFont) -> Vec<String> {
+    baked.license.as_ref().map(FontLicense::notice_lines).unwrap_or_default()
+}
 
-        glyph_info.insert(
-            glyph_index,
-            GlyphEntry {
-                offset,
-                width: metrics.width as i32,
-                height: metrics.height as i32,
-                x_advance,
-                x_offset,
-                y_offset,
-            },
-        );
+/// Icon and string constants placed in the blob's C header.
+fn blob_constants(job: &FontJob, baked: &BakedFont) -> String {
+    let mut out = icon_names::c_constants(&baked.icons, &job.icon_constants);
+    if let Some(encoding) = &baked.encoding {
+        out.push_str(&format!(
+            "/* Glyph ranges are keyed by {} codes, not Unicode codepoints. */\n\n",
+            encoding.label
+        ));
     }
+    out.push_str(&string_tables::c_strings(&baked.strings, baked.glyph_data.glyphs.len(), &baked.storage));
+    out
+}
 
-    let mut glyphs: Vec<GlyphEntry> = Vec::with_capacity(codepoint_map.len());
-    let mut codepoints: Vec<u32> = Vec::with_capacity(codepoint_map.len());
-    let mut ranges: Vec<GlyphRangeEntry> = Vec::new();
-    let mut last_cp: Option<u32> = None;
-    let mut range_start: u32 = 0;
-    let mut range_start_index: u16 = 0;
-    let mut index: u16 = 0;
-    let mut fallback_index: Option<usize> = None;
+fn blob_for(baked: &BakedFont) -> Result<BlobFont, String> {
+    let mut metadata = baked.provenance.lines().join("\n");
+    metadata.push('\n');
+    blob_font(&baked.glyph_data, baked.line_height, baked.baseline, metadata)
+}
 
-    for (cp, glyph_index) in codepoint_map.iter() {
-        if let Some(prev) = last_cp {
-            if *cp != prev + 1 {
-                let length = (index - range_start_index) as u16;
-                ranges.push(GlyphRangeEntry {
-                    start: range_start,
-                    length,
-                    glyph_id_start: range_start_index,
-                });
-                range_start = *cp;
-                range_start_index = index;
-            }
-        } else {
-            range_start = *cp;
-            range_start_index = index;
-        }
+/// Converts the baked tables to the fixed-width records of the binary blob.
+fn blob_font(data: &GlyphData, line_height: i32, baseline: i32, metadata: String) -> Result<BlobFont, String> {
+    let glyphs = data
+        .glyphs
+        .iter()
+        .map(|g| {
+            Ok(BlobGlyph {
+                bitmap_offset: blob_field(g.offset as i64, "bitmap offset")?,
+                width: blob_field(g.width as i64, "glyph width")?,
+                height: blob_field(g.height as i64, "glyph height")?,
+                x_advance: blob_field(g.x_advance as i64, "x advance")?,
+                x_offset: blob_field(g.x_offset as i64, "x offset")?,
+                y_offset: blob_field(g.y_offset as i64, "y offset")?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(BlobFont {
+        line_height: blob_field(line_height as i64, "line height")?,
+        baseline: blob_field(baseline as i64, "baseline")?,
+        max_w: blob_field(data.max_w as i64, "max width")?,
+        max_h: blob_field(data.max_h as i64, "max height")?,
+        fallback_index: data.fallback_index.map(|i| i as u32),
+        glyphs,
+        ranges: data
+            .ranges
+            .iter()
+            .map(|r| BlobRange {
+                start: r.start,
+                length: r.length,
+                glyph_id_start: r.glyph_id_start,
+            })
+            .collect(),
+        bitmaps: data.bitmaps.clone(),
+        metadata,
+    })
+}
 
-        if let Some(entry) = glyph_info.get(glyph_index) {
-            glyphs.push(GlyphEntry { ..*entry });
-        }
-        codepoints.push(*cp);
+fn bdf_for(job: &FontJob, baked: &BakedFont) -> BdfFont {
+    let family = match baked.provenance.family.trim() {
+        "" => job.export_name.clone(),
+        family => family.to_string(),
+    };
+    let mut font = bdf_font(&baked.glyph_data, baked.settings.size_px, baked.line_height, baked.baseline, family);
+    font.copyright = baked.license.as_ref().and_then(|l| l.copyright.clone());
+    font.comments = notice_lines(baked);
+    font.comments.extend(baked.provenance.lines());
+    font
+}
 
-        if let Some(fallback) = fallback_cp {
-            if *cp == fallback {
-                fallback_index = Some(index as usize);
+/// Converts the baked tables to BDF glyphs; `glyph_data.bitmaps` rows are already BDF-packed.
+fn bdf_font(data: &GlyphData, size_px: u32, line_height: i32, baseline: i32, family: String) -> BdfFont {
+    let glyphs = data
+        .glyphs
+        .iter()
+        .enumerate()
+        .map(|(index, g)| {
+            let len = (g.width.max(0) as usize).div_ceil(8) * g.height.max(0) as usize;
+            BdfGlyph {
+                encoding: data.codepoints.get(index).copied(),
+                width: g.width.max(0) as u32,
+                height: g.height.max(0) as u32,
+                x_offset: g.x_offset,
+                y_offset: g.y_offset - g.height,
+                advance: g.x_advance,
+                rows: data.bitmaps[g.offset..g.offset + len].to_vec(),
             }
-        }
-
-        last_cp = Some(*cp);
-        index = index.saturating_add(1);
+        })
+        .collect();
+    BdfFont {
+        family,
+        pixel_size: size_px,
+        ascent: baseline,
+        descent: line_height - baseline,
+        default_char: data.fallback_index.and_then(|i| data.codepoints.get(i).copied()),
+        copyright: None,
+        comments: Vec::new(),
+        glyphs,
     }
+}
 
-    if last_cp.is_some() {
-        let length = (index - range_start_index) as u16;
-        ranges.push(GlyphRangeEntry {
-            start: range_start,
-            length,
-            glyph_id_start: range_start_index,
-        });
-    }
+fn blob_field<T: TryFrom<i64>>(value: i64, what: &str) -> Result<T, String> {
+    T::try_from(value).map_err(|_| format!("{} {} does not fit the blob format", what, value))
+}
 
-    GlyphData {
-        bitmaps,
-        packed_glyphs,
-        glyphs,
-        codepoints,
-        ranges,
-        fallback_index,
-        max_w,
-        max_h,
+/// Integer em size at which `ch` is closest to `target` pixels tall above the baseline, and the
+/// height it gets there.
+fn fit_size_to_height(font: &Font, ch: char, what: &str, target: u32) -> Result<(u32, u32), String> {
+    let glyph = font.lookup_glyph_index(ch);
+    if glyph == 0 {
+        return Err(format!("Font has no '{}' to measure the {}", ch, what));
     }
+    let height = |size: u32| {
+        let m = font.metrics_indexed(glyph, size as f32);
+        (m.ymin + m.height as i32).max(0) as u32
+    };
+    let reference = height(100).max(1);
+    let estimate = ((target * 100 + reference / 2) / reference).max(1);
+    let best = (estimate.saturating_sub(3).max(1)..=estimate + 3)
+        .map(|size| (size, height(size)))
+        .min_by_key(|(size, measured)| (measured.abs_diff(target), size.abs_diff(estimate)))
+        .unwrap_or((estimate, height(estimate)));
+    Ok(best)
 }
 
-fn line_metrics(font: &Font, size_px: u32) -> (i32, i32) {
+fn line_metrics(font: &SourceFont, size_px: u32) -> (i32, i32) {
+    let font = match font {
+        SourceFont::Outline(font) => font,
+        SourceFont::Bitmap(font) => return (font.ascent + font.descent, font.ascent),
+    };
     if let Some(m) = font.horizontal_line_metrics(size_px as f32) {
         let line_height = m.new_line_size.round() as i32;
         let baseline = m.ascent.round() as i32;
@@ -699,6 +2126,23 @@ fn format_byte(value: u8, number_format: &str) -> String {
     }
 }
 
+const GLYPH_FIELDS: [&str; 6] = ["offset", "width", "height", "x_advance", "x_offset", "y_offset"];
+
+fn glyph_row(entry: &GlyphEntry) -> Vec<i64> {
+    vec![
+        entry.offset as i64,
+        entry.width as i64,
+        entry.height as i64,
+        entry.x_advance as i64,
+        entry.x_offset as i64,
+        entry.y_offset as i64,
+    ]
+}
+
+fn glyph_rows(data: &GlyphData) -> Vec<Vec<i64>> {
+    data.glyphs.iter().map(glyph_row).collect()
+}
+
 fn display_char(codepoint: u32) -> char {
     if (32..=126).contains(&codepoint) {
         char::from_u32(codepoint).unwrap_or('?')
@@ -707,7 +2151,10 @@ fn display_char(codepoint: u32) -> char {
     }
 }
 
-fn generate_cpp_module(job: &FontJob, data: &GlyphData, line_height: i32, baseline: i32) -> String {
+fn generate_cpp_module(job: &FontJob, baked: &BakedFont) -> String {
+    let data = &baked.glyph_data;
+    let line_height = baked.line_height;
+    let baseline = baked.baseline;
     let module_name = if !job.module_name.trim().is_empty() {
         job.module_name.trim()
     } else if !job.export_name.trim().is_empty() {
@@ -722,18 +2169,34 @@ fn generate_cpp_module(job: &FontJob, data: &GlyphData, line_height: i32, baseli
     };
 
     let mut out = String::new();
-    out.push_str("module;
+    let notice = notice_lines(baked);
+    if !notice.is_empty() {
+        out.push_str("// Font license:
+");
+        for line in &notice {
+            out.push_str(format!("//   {}", line).trim_end());
+            out.push('\n');
+        }
+    }
+    out.push_str(&baked.provenance.comment_block());
+    out.push_str("
+module;
 ");
     out.push_str("#include <cstdint>
 ");
     out.push_str("#include <span>
 ");
+    if let Some(include) = baked.storage.include() {
+        out.push_str(&format!("#include {}
+", include));
+    }
     out.push_str(&format!("export module {};
 
 ", module_name));
     out.push_str("import ui_font;
 
 ");
+    out.push_str(&icon_names::cpp_constants(&baked.icons, &job.icon_constants, export_name));
     out.push_str("// Bitmap format: 1-bit packed, row-major, MSB-first.
 ");
     out.push_str("// stride = (width + 7) / 8
@@ -744,13 +2207,25 @@ fn generate_cpp_module(job: &FontJob, data: &GlyphData, line_height: i32, baseli
 
 ");
 
-    out.push_str("static constexpr uint8_t glyph_bitmaps[] = {
-");
+    out.push_str(&baked.storage.array_decl("uint8_t", "glyph_bitmaps", ""));
     for packed in &data.packed_glyphs {
+        if let Some(owner) = packed.shared_with {
+            if job.with_comments {
+                let ch = display_char(packed.codepoint);
+                out.push_str(&format!("    // code {} ('{}') shares bitmap of code {}
+", packed.codepoint, ch, owner));
+            }
+            continue;
+        }
         if job.with_comments {
-            let ch = display_char(packed.codepoint);
-            out.push_str(&format!("    // code {} ('{}')
+            if packed.source == GlyphSource::Synthesized {
+                out.push_str("    // fallback box
+");
+            } else {
+                let ch = display_char(packed.codepoint);
+                out.push_str(&format!("    // code {} ('{}')
 ", packed.codepoint, ch));
+            }
         }
         let end = packed.offset + packed.len;
         for b in &data.bitmaps[packed.offset..end] {
@@ -762,27 +2237,130 @@ fn generate_cpp_module(job: &FontJob, data: &GlyphData, line_height: i32, baseli
 
 ");
 
-    out.push_str("static constexpr Glyph glyph_table[] = {
-");
+    let layout = &baked.layout;
+    match &layout {
+        Some(layout) => {
+            out.push_str(&format!(
+                "// Glyph layout: {}, {} bytes per entry
+",
+                job.glyph_layout,
+                layout.entry_size()
+            ));
+            out.push_str(&layout.struct_definition("CompactGlyph"));
+            out.push('\n');
+            out.push_str(&baked.storage.array_decl("CompactGlyph", "glyph_table", ""));
+        }
+        None => out.push_str(&baked.storage.array_decl("Glyph", "glyph_table", "")),
+    }
     for (idx, entry) in data.glyphs.iter().enumerate() {
         if job.with_comments {
-            let cp = data.codepoints.get(idx).copied().unwrap_or(0);
-            let ch = display_char(cp);
-            out.push_str(&format!("    // {} (code {})
-", ch, cp));
+            match data.codepoints.get(idx) {
+                Some(cp) => match &baked.encoding {
+                    Some(encoding) => {
+                        let ch = baked.legacy_chars.get(cp).copied().or_else(|| encoding.decode(*cp));
+                        out.push_str(&format!(
+                            "    // {} ({} 0x{:X})
+",
+                            display_char(ch.unwrap_or(0)),
+                            encoding.label,
+                            cp
+                        ))
+                    }
+                    None => out.push_str(&format!("    // {} (code {})
+", display_char(*cp), cp)),
+                },
+                None => out.push_str("    // fallback
+"),
+            }
         }
-        out.push_str(&format!(
-            "    {{ glyph_bitmaps + {}, {}, {}, {}, {}, {} }},
+        match &layout {
+            Some(layout) => out.push_str(&format!(
+                "    {},
 ",
-            entry.offset, entry.width, entry.height, entry.x_advance, entry.x_offset, entry.y_offset
-        ));
+                layout.initializer(&GLYPH_FIELDS, &glyph_row(entry))
+            )),
+            None => out.push_str(&format!(
+                "    {{ glyph_bitmaps + {}, {}, {}, {}, {}, {} }},
+",
+                entry.offset, entry.width, entry.height, entry.x_advance, entry.x_offset, entry.y_offset
+            )),
+        }
     }
     out.push_str("};
 
 ");
 
-    out.push_str("static constexpr GlyphRange glyph_ranges[] = {
+    let storage = &baked.storage;
+    out.push_str(&format!(
+        "export {} Glyph {}_glyph(uint32_t index) {{
+",
+        storage.function_specifier(),
+        export_name
+    ));
+    match &layout {
+        Some(layout) => {
+            out.push_str(&format!("    {}
+", storage.load_struct("CompactGlyph", "g", "glyph_table[index]")));
+            out.push_str(&format!(
+                "    return Glyph(glyph_bitmaps + {}, {}, {}, {}, {}, {});
+",
+                layout.accessor("g", "offset"),
+                layout.accessor("g", "width"),
+                layout.accessor("g", "height"),
+                layout.accessor("g", "x_advance"),
+                layout.accessor("g", "x_offset"),
+                layout.accessor("g", "y_offset")
+            ));
+        }
+        None if storage.progmem() => {
+            out.push_str(&format!("    {}
+", storage.load_struct("Glyph", "g", "glyph_table[index]")));
+            out.push_str("    return g;
+");
+        }
+        None => out.push_str("    return glyph_table[index];
+"),
+    }
+    out.push_str("}
+
+");
+    if storage.progmem() {
+        out.push_str("// Glyph bitmaps are in program memory; read them through this instead of the pointer.
+");
+        out.push_str(&format!(
+            "export inline uint8_t {}_bitmap_byte(const uint8_t* bitmap, uint32_t index) {{
+",
+            export_name
+        ));
+        out.push_str("    return pgm_read_byte(bitmap + index);
+");
+        out.push_str("}
+
 ");
+    }
+
+    if let Some(encoding) = &baked.encoding {
+        out.push_str(&format!(
+            "// Ranges and lookups are keyed by {} codes, not Unicode codepoints.
+",
+            encoding.label
+        ));
+    }
+    // ui_font's GlyphRange decides the field widths; fail the firmware build instead of narrowing.
+    let widest = |field: fn(&GlyphRangeEntry) -> u32| data.ranges.iter().map(field).max().unwrap_or(0);
+    let range_fields: [(&str, u32); 3] = [
+        ("start", widest(|r| r.start)),
+        ("length", widest(|r| r.length)),
+        ("glyph_id_start", widest(|r| r.glyph_id_start)),
+    ];
+    for (field, max) in range_fields.iter().filter(|(_, max)| *max > u16::MAX as u32) {
+        out.push_str(&format!(
+            "static_assert(sizeof(GlyphRange::{}) >= 4, \"GlyphRange::{} must be 32-bit to hold {}\");
+",
+            field, field, max
+        ));
+    }
+    out.push_str(&baked.storage.array_decl("GlyphRange", "glyph_ranges", ""));
     for range in &data.ranges {
         out.push_str(&format!(
             "    {{ {}, {}, {} }},
@@ -794,6 +2372,110 @@ fn generate_cpp_module(job: &FontJob, data: &GlyphData, line_height: i32, baseli
 
 ");
 
+    out.push_str(&baked.lookup.emit(export_name, &baked.storage));
+
+    let glyph_words = data.glyphs.iter().flat_map(|entry| glyph_row(entry).into_iter().map(|v| v as u32));
+    let range_words = data.ranges.iter().flat_map(|r| [r.start, r.length, r.glyph_id_start]);
+    let crc = module_crc32(&data.bitmaps, glyph_words.chain(range_words).chain(baked.lookup.crc_words()));
+    out.push_str("// CRC-32 over glyph_bitmaps, then as little-endian uint32 the offset, width, height,
+");
+    out.push_str("// x_advance, x_offset and y_offset of every glyph, start, length and glyph_id_start of
+");
+    out.push_str("// every glyph_ranges entry and every value of the lookup arrays.
+");
+    out.push_str(&format!("export constexpr uint32_t {}_crc32 = 0x{:08X}u;
+
+", export_name, crc));
+    out.push_str("constexpr uint32_t crc32_step(uint32_t crc, uint32_t byte) {
+");
+    out.push_str("    crc ^= byte & 0xFFu;
+");
+    out.push_str("    for (int k = 0; k < 8; ++k) crc = (crc >> 1) ^ (0xEDB88320u & (0u - (crc & 1u)));
+");
+    out.push_str("    return crc;
+");
+    out.push_str("}
+
+");
+    out.push_str("constexpr uint32_t crc32_word(uint32_t crc, uint32_t value) {
+");
+    out.push_str("    for (int k = 0; k < 32; k += 8) crc = crc32_step(crc, value >> k);
+");
+    out.push_str("    return crc;
+");
+    out.push_str("}
+
+");
+    out.push_str(&format!("export inline bool {}_verify() {{
+", export_name));
+    out.push_str("    uint32_t crc = 0xFFFFFFFFu;
+");
+    out.push_str(&format!(
+        "    for (uint32_t i = 0; i < sizeof(glyph_bitmaps); ++i) crc = crc32_step(crc, {});
+",
+        storage.load("uint8_t", "glyph_bitmaps[i]")
+    ));
+    // Unpacking by position keeps the loop independent of ui_font's Glyph member names.
+    out.push_str(&format!("    for (uint32_t i = 0; i < {}; ++i) {{
+", data.glyphs.len()));
+    out.push_str(&format!(
+        "        const auto [bitmap, width, height, x_advance, x_offset, y_offset] = {}_glyph(i);
+",
+        export_name
+    ));
+    out.push_str("        const uint32_t fields[] = { (uint32_t)(bitmap - glyph_bitmaps), (uint32_t)width, (uint32_t)height,
+");
+    out.push_str("                                    (uint32_t)x_advance, (uint32_t)x_offset, (uint32_t)y_offset };
+");
+    out.push_str("        for (uint32_t v : fields) crc = crc32_word(crc, v);
+");
+    out.push_str("    }
+");
+    out.push_str("    for (const GlyphRange& entry : glyph_ranges) {
+");
+    out.push_str(&format!("        {}
+", storage.load_struct("GlyphRange", "r", "entry")));
+    out.push_str("        const uint32_t fields[] = { (uint32_t)r.start, (uint32_t)r.length, (uint32_t)r.glyph_id_start };
+");
+    out.push_str("        for (uint32_t v : fields) crc = crc32_word(crc, v);
+");
+    out.push_str("    }
+");
+    out.push_str(&baked.lookup.emit_crc(storage));
+    out.push_str(&format!("    return (crc ^ 0xFFFFFFFFu) == {}_crc32;
+", export_name));
+    out.push_str("}
+
+");
+
+    out.push_str(&string_tables::cpp_strings(&baked.strings, data.glyphs.len(), &baked.storage));
+
+    if layout.is_some() {
+        // Compact entries are not `Glyph`s, so the runtime reads them through the accessor.
+        out.push_str(&format!(
+            "export constexpr uint32_t {}_glyph_count = {};
+",
+            export_name,
+            data.glyphs.len()
+        ));
+        out.push_str(&format!(
+            "export constexpr std::span<const GlyphRange> {}_ranges = glyph_ranges;
+",
+            export_name
+        ));
+        out.push_str(&format!(
+            "export constexpr int32_t {}_fallback_index = {};
+",
+            export_name,
+            data.fallback_index.map(|i| i as i64).unwrap_or(-1)
+        ));
+        out.push_str(&format!("export constexpr int32_t {}_line_height = {};
+", export_name, line_height));
+        out.push_str(&format!("export constexpr int32_t {}_baseline = {};
+", export_name, baseline));
+        return out;
+    }
+
     out.push_str(&format!("export constexpr Font {} = {{
 ", export_name));
     out.push_str("    .table = glyph_table,
@@ -838,18 +2520,686 @@ fn pack_bitmap_1b(gray: &[u8], w: u32, h: u32, threshold: u8) -> (Vec<u8>, usize
     (packed, stride)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(ranges: &[GlyphRangeEntry], cp: u32) -> Option<u32> {
+        let idx = ranges.partition_point(|r| r.start + r.length <= cp);
+        let range = ranges.get(idx)?;
+        (cp >= range.start).then(|| range.glyph_id_start + (cp - range.start))
+    }
+
+    #[test]
+    fn ranges_cover_more_than_u16_entries() {
+        // Every 7th codepoint is missing so the table splits into many ranges.
+        let codepoints: Vec<u32> = (0x3400..0x3400 + 82_000)
+            .filter(|cp| cp % 7 != 0)
+            .collect();
+        assert!(codepoints.len() > 70_000);
+
+        let ranges = build_ranges(&codepoints).unwrap();
+        let last = ranges.last().unwrap();
+        assert!(last.glyph_id_start > u16::MAX as u32);
+        assert_eq!(last.glyph_id_start + last.length, codepoints.len() as u32);
+
+        for (index, cp) in codepoints.iter().enumerate() {
+            assert_eq!(lookup(&ranges, *cp), Some(index as u32), "U+{:04X}", cp);
+        }
+        let gap = (0x3400u32..).find(|cp| cp % 7 == 0).unwrap();
+        assert_eq!(lookup(&ranges, gap), None);
+        assert_eq!(lookup(&ranges, 0x33FF), None);
+    }
+
+    #[test]
+    fn blob_round_trips_glyph_data() {
+        let codepoints: Vec<u32> = vec![0x20, 0x41, 0x42, 0x43, 0x4E2D];
+        let mut glyphs: Vec<GlyphEntry> = codepoints
+            .iter()
+            .enumerate()
+            .map(|(i, _)| GlyphEntry {
+                offset: i * 3,
+                width: i as i32 + 1,
+                height: 7,
+                x_advance: 8,
+                x_offset: -(i as i32),
+                y_offset: -6,
+            })
+            .collect();
+        glyphs.push(GlyphEntry {
+            offset: 15,
+            width: 5,
+            height: 7,
+            x_advance: 6,
+            ..Default::default()
+        });
+        let data = GlyphData {
+            bitmaps: (0..20u8).collect(),
+            packed_glyphs: Vec::new(),
+            dedup_saved_bytes: 0,
+            ranges: build_ranges(&codepoints).unwrap(),
+            fallback_index: Some(codepoints.len()),
+            codepoints,
+            glyphs,
+            max_w: 5,
+            max_h: 7,
+        };
+        let font = blob_font(&data, 12, 9, "generator=test\n".to_string()).unwrap();
+
+        for (big_endian, alignment) in [(false, 1), (false, 4), (true, 8), (true, 64)] {
+            let layout = BlobLayout { big_endian, alignment };
+            let (mut bytes, crc) = font_blob::write_blob(&font, &layout).unwrap();
+            let sections = layout.sections(&font);
+            assert_eq!(bytes.len(), sections.total);
+            let covered_end = sections.bitmaps + font.bitmaps.len();
+            assert_eq!(crc, crc32fast::hash(&bytes[sections.glyph_table..covered_end]));
+            assert_eq!(sections.glyph_table % alignment, 0);
+            assert_eq!(sections.range_table % alignment, 0);
+            assert_eq!(sections.bitmaps % alignment, 0);
+
+            let parsed = font_blob::parse_blob(&bytes).unwrap();
+            assert_eq!(parsed, font);
+            assert_eq!(parsed.line_height, 12);
+            assert_eq!(parsed.baseline, 9);
+            assert_eq!(parsed.bitmaps, data.bitmaps);
+            assert_eq!(parsed.fallback_index, Some(data.codepoints.len() as u32));
+            for (glyph, entry) in parsed.glyphs.iter().zip(&data.glyphs) {
+                assert_eq!(glyph.bitmap_offset as usize, entry.offset);
+                assert_eq!(glyph.width as i32, entry.width);
+                assert_eq!(glyph.height as i32, entry.height);
+                assert_eq!(glyph.x_advance as i32, entry.x_advance);
+                assert_eq!(glyph.x_offset as i32, entry.x_offset);
+                assert_eq!(glyph.y_offset as i32, entry.y_offset);
+            }
+            for (range, entry) in parsed.ranges.iter().zip(&data.ranges) {
+                assert_eq!(
+                    (range.start, range.length, range.glyph_id_start),
+                    (entry.start, entry.length, entry.glyph_id_start)
+                );
+            }
+
+            bytes[sections.bitmaps + 3] ^= 0x10;
+            assert!(font_blob::parse_blob(&bytes).is_err());
+        }
+        assert!(font_blob::write_blob(&font, &BlobLayout { big_endian: false, alignment: 3 }).is_err());
+    }
+
+    /// Lit pixels as (x, y) relative to the glyph origin, y counting up from the baseline.
+    fn ink(x_offset: i32, top: i32, width: usize, gray: &[u8]) -> BTreeSet<(i32, i32)> {
+        gray.iter()
+            .enumerate()
+            .filter(|(_, v)| **v != 0)
+            .map(|(i, _)| (x_offset + (i % width) as i32, top - (i / width) as i32))
+            .collect()
+    }
+
+    #[test]
+    fn bdf_round_trips_glyph_data() {
+        let shapes: [(u32, i32, i32, &[&str]); 4] = [
+            (0x20, 0, 0, &[]),
+            (0x41, 1, 7, &["0110", "1001", "1111", "1001", "1001", "1001", "1001"]),
+            (0x67, 0, 5, &["011110", "100010", "011110", "000010", "100010", "011100", "000000"]),
+            (0x4E2D, -1, 9, &["000010000", "111111111", "100010001", "111111111", "000010000", "000010000"]),
+        ];
+        let mut store = BitmapStore::default();
+        let mut glyphs = Vec::new();
+        let mut codepoints = Vec::new();
+        let mut expected = Vec::new();
+        for (cp, x_offset, top, rows) in shapes {
+            let width = rows.first().map_or(0, |r| r.len());
+            let gray: Vec<u8> = rows.concat().bytes().map(|b| if b == b'1' { 255 } else { 0 }).collect();
+            let metrics = fontdue::Metrics {
+                xmin: x_offset,
+                ymin: top - rows.len() as i32,
+                width,
+                height: rows.len(),
+                advance_width: width as f32 + 1.0,
+                ..Default::default()
+            };
+            glyphs.push(store.push(cp, &metrics, &gray, GlyphSource::Outline, 1));
+            codepoints.push(cp);
+            expected.push((cp, width as i32 + 1, ink(x_offset, top - 1, width.max(1), &gray)));
+        }
+        let data = GlyphData {
+            bitmaps: store.bitmaps,
+            packed_glyphs: store.packed_glyphs,
+            dedup_saved_bytes: 0,
+            glyphs,
+            ranges: build_ranges(&codepoints).unwrap(),
+            fallback_index: Some(1),
+            codepoints,
+            max_w: store.max_w,
+            max_h: store.max_h,
+        };
+
+        let text = bdf_export::write_bdf(&bdf_font(&data, 10, 12, 9, "Round Trip".to_string()));
+        assert!(text.contains("DEFAULT_CHAR 65\n"));
+        assert!(text.contains("FONTBOUNDINGBOX 9 11 -1 -2\n"));
+        let parsed = BitmapFont::parse(text.as_bytes()).unwrap();
+        assert_eq!((parsed.pixel_size, parsed.ascent, parsed.descent), (10, 9, 3));
+        assert_eq!(parsed.family, "Round Trip");
+
+        for (cp, advance, pixels) in expected {
+            let glyph = parsed.glyph(parsed.glyph_index(cp)).unwrap();
+            assert_eq!(glyph.advance, advance, "U+{:04X}", cp);
+            let top = glyph.y_min + glyph.height as i32 - 1;
+            let width = (glyph.width as usize).max(1);
+            assert_eq!(ink(glyph.x_min, top, width, &glyph.gray), pixels, "U+{:04X}", cp);
+        }
+    }
+
+    /// A BDF font with one glyph per entry; rows are drawn with `#` and at most 8 pixels wide.
+    fn test_bdf(glyphs: &[(u32, &[&str])]) -> Vec<u8> {
+        let mut bdf = String::from("STARTFONT 2.1\nFONTBOUNDINGBOX 8 8 0 -1\n");
+        bdf.push_str("STARTPROPERTIES 3\nFAMILY_NAME \"Test\"\nFONT_ASCENT 7\nFONT_DESCENT 1\nENDPROPERTIES\n");
+        bdf.push_str(&format!("CHARS {}\n", glyphs.len()));
+        for (cp, rows) in glyphs {
+            let width = rows.first().map_or(0, |r| r.len());
+            bdf.push_str(&format!("STARTCHAR U{:04X}\nENCODING {}\nDWIDTH {} 0\n", cp, cp, width + 1));
+            bdf.push_str(&format!("BBX {} {} 0 0\nBITMAP\n", width, rows.len()));
+            for row in rows.iter() {
+                let bits = row.bytes().enumerate().filter(|(_, b)| *b == b'#').fold(0u8, |acc, (x, _)| acc | 0x80 >> x);
+                bdf.push_str(&format!("{:02X}\n", bits));
+            }
+            bdf.push_str("ENDCHAR\n");
+        }
+        bdf.push_str("ENDFONT\n");
+        bdf.into_bytes()
+    }
+
+    const GLYPH_A: &[&str] = &[".##.", "#..#", "####", "#..#", "#..#"];
+    const GLYPH_B: &[&str] = &["###.", "#..#", "###.", "#..#", "###."];
+    const GLYPH_QUESTION: &[&str] = &["###", "..#", ".#.", "...", ".#."];
+
+    fn test_job(extra: serde_json::Value) -> FontJob {
+        let mut job = serde_json::json!({
+            "source": {"mode": "bitmap", "path": "test.bdf"},
+            "size_px": 8,
+            "range": {"start": 65, "end": 66},
+            "custom_chars": null,
+            "fallback_char": "?",
+            "output_kind": "cpp_module",
+            "export_name": "font",
+            "with_comments": false,
+            "number_format": "hex"
+        });
+        for (key, value) in extra.as_object().unwrap() {
+            job[key] = value.clone();
+        }
+        serde_json::from_value(job).unwrap()
+    }
+
+    /// Bakes `glyphs` with the job overrides in `extra` and returns the C++ module and warnings.
+    fn test_module(glyphs: &[(u32, &[&str])], extra: serde_json::Value) -> (String, Vec<String>) {
+        let job = test_job(extra);
+        let loaded = LoadedFont::from_bitmap(test_bdf(glyphs)).unwrap();
+        let baked = bake_font(&job, &loaded).unwrap();
+        (generate_cpp_module(&job, &baked), baked.warnings.clone())
+    }
+
+    #[test]
+    fn module_asserts_runtime_range_width_for_wide_values() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        let (module, _) = test_module(glyphs, serde_json::json!({}));
+        assert!(!module.contains("static_assert"));
+
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x3F, GLYPH_QUESTION), (0x20000, GLYPH_B)];
+        let (module, warnings) = test_module(glyphs, serde_json::json!({"custom_chars": "\u{20000}"}));
+        assert!(module.contains(
+            "static_assert(sizeof(GlyphRange::start) >= 4, \"GlyphRange::start must be 32-bit to hold 131072\");\n"
+        ));
+        assert!(!module.contains("GlyphRange::length"));
+        assert!(!module.contains("GlyphRange::glyph_id_start"));
+        assert!(warnings.iter().all(|w| !w.contains("32-bit")));
+    }
+
+    #[test]
+    fn compact_layouts_warn_that_the_font_object_is_gone() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        let (module, warnings) = test_module(glyphs, serde_json::json!({}));
+        assert!(module.contains("export constexpr Font font = {"));
+        assert!(warnings.iter().all(|w| !w.contains("Font object")));
+
+        for layout in ["compact", "bitfield"] {
+            let (module, warnings) = test_module(glyphs, serde_json::json!({"glyph_layout": layout}));
+            assert!(!module.contains("export constexpr Font"));
+            assert!(module.contains("export constexpr int32_t font_line_height = "));
+            let expected = format!(
+                "The {} glyph layout exports no Font object; code taking `const Font&` must switch to font_glyph() and the font_* constants",
+                layout
+            );
+            assert!(warnings.contains(&expected), "{:?}", warnings);
+        }
+
+        let job = test_job(serde_json::json!({"glyph_layout": "packed"}));
+        let loaded = LoadedFont::from_bitmap(test_bdf(glyphs)).unwrap();
+        assert_eq!(bake_font(&job, &loaded).err().as_deref(), Some("Unknown glyph layout: packed"));
+    }
+
+    #[test]
+    fn merged_ranges_need_a_fallback_and_stay_out_of_bdf() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x43, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        let loaded = LoadedFont::from_bitmap(test_bdf(glyphs)).unwrap();
+        let merged = serde_json::json!({
+            "range": {"start": 65, "end": 65},
+            "custom_chars": "C",
+            "lookup_strategy": "merged_ranges"
+        });
+
+        let job = test_job(merged.clone());
+        let baked = bake_font(&job, &loaded).unwrap();
+        let data = &baked.glyph_data;
+        assert_eq!(data.codepoints, vec![0x41, 0x42, 0x43]);
+        assert_eq!(data.ranges.len(), 1);
+        let fallback = data.glyphs[data.fallback_index.unwrap()];
+        assert_eq!(data.glyphs[1].offset, fallback.offset);
+        assert!(baked.warnings.iter().any(|w| w.starts_with("Filled 1 gap codepoints")));
+
+        let mut no_fallback = merged.clone();
+        no_fallback["fallback_char"] = serde_json::Value::Null;
+        let job = test_job(no_fallback);
+        let err = bake_font(&job, &loaded).err().unwrap();
+        assert!(err.starts_with("Merged ranges fill gaps with the fallback glyph"), "{}", err);
+
+        let mut bdf = merged;
+        bdf["output_kind"] = "bdf".into();
+        let job = test_job(bdf);
+        let baked = bake_font(&job, &loaded).unwrap();
+        let text = bdf_export::write_bdf(&bdf_for(&job, &baked));
+        assert!(text.contains("ENCODING 65\n") && text.contains("ENCODING 67\n"));
+        assert!(!text.contains("ENCODING 66\n"));
+        assert!(baked.warnings.contains(&"BDF output has no lookup tables, range gaps left empty".to_string()));
+    }
+
+    /// Every element access of the generated arrays in `module` takes the element's address,
+    /// as `pgm_read_*` and `memcpy_P` need, instead of loading it directly.
+    fn assert_no_plain_loads(module: &str, context: &str) {
+        let arrays = [
+            "glyph_bitmaps",
+            "glyph_table",
+            "glyph_ranges",
+            "lookup_codepoints",
+            "lookup_pages",
+            "lookup_page_glyphs",
+            "lookup_hash_disp",
+            "lookup_hash_keys",
+            "lookup_hash_glyphs",
+        ];
+        for array in arrays {
+            let access = format!("{}[", array);
+            for (at, _) in module.match_indices(&access).filter(|(at, _)| !module[at + access.len()..].starts_with(']')) {
+                let line = module[..at].rsplit('\n').next().unwrap_or("");
+                assert!(line.ends_with('&'), "{}: plain load in `{}...`", context, line);
+            }
+        }
+    }
+
+    #[test]
+    fn avr_preset_reads_tables_through_progmem_accessors() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION), (0x4E2D, GLYPH_B)];
+        for layout in ["standard", "compact", "bitfield"] {
+            for strategy in ["ranges", "sorted_array", "page_table", "perfect_hash"] {
+                let context = format!("{} {}", layout, strategy);
+                let (module, warnings) = test_module(
+                    glyphs,
+                    serde_json::json!({
+                        "storage_preset": "avr",
+                        "glyph_layout": layout,
+                        "lookup_strategy": strategy,
+                        "custom_chars": "\u{4E2D}"
+                    }),
+                );
+                assert!(module.contains("#include <avr/pgmspace.h>\n"), "{}", context);
+                assert!(module.contains("inline constexpr uint8_t glyph_bitmaps[] PROGMEM = {\n"), "{}", context);
+                assert!(module.contains("export inline Glyph font_glyph(uint32_t index) {\n"), "{}", context);
+                assert!(module.contains("export inline int32_t font_glyph_index(uint32_t codepoint) {\n"), "{}", context);
+                assert!(module.contains("    return pgm_read_byte(bitmap + index);\n"), "{}", context);
+                assert!(
+                    module.contains("crc = crc32_step(crc, (uint8_t)pgm_read_byte(&glyph_bitmaps[i]));\n"),
+                    "{}",
+                    context
+                );
+                assert!(module.contains("        GlyphRange r; memcpy_P(&r, &entry, sizeof r);\n"), "{}", context);
+                assert!(!module.contains("export constexpr Glyph"), "{}", context);
+                assert!(!module.contains("export constexpr int32_t font_glyph_index"), "{}", context);
+                assert_no_plain_loads(&module, &context);
+                assert!(warnings.iter().any(|w| w.starts_with("PROGMEM tables live in flash")), "{}", context);
+            }
+        }
+
+        let (module, _) = test_module(glyphs, serde_json::json!({"storage_preset": "avr"}));
+        assert!(module.contains("    Glyph g; memcpy_P(&g, &glyph_table[index], sizeof g);\n    return g;\n"));
+        let (module, _) = test_module(glyphs, serde_json::json!({"storage_preset": "avr", "lookup_strategy": "sorted_array", "custom_chars": "\u{4E2D}"}));
+        assert!(module.contains("        uint16_t key = (uint16_t)pgm_read_word(&lookup_codepoints[mid]);\n"));
+
+        let (module, warnings) = test_module(glyphs, serde_json::json!({"storage_preset": "arm_gcc"}));
+        assert!(module.contains("export constexpr Glyph font_glyph(uint32_t index) {\n    return glyph_table[index];\n"));
+        assert!(!module.contains("pgm_read") && !module.contains("memcpy_P"));
+        assert!(warnings.iter().all(|w| !w.starts_with("PROGMEM")));
+    }
+
+    #[test]
+    fn module_crc_covers_glyph_table_and_lookup_arrays() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        let module_for = |bdf: Vec<u8>, strategy: &str| {
+            let job = test_job(serde_json::json!({"lookup_strategy": strategy}));
+            let loaded = LoadedFont::from_bitmap(bdf).unwrap();
+            let baked = bake_font(&job, &loaded).unwrap();
+            let module = generate_cpp_module(&job, &baked);
+            let crc = module.lines().find(|l| l.starts_with("export constexpr uint32_t font_crc32 = ")).unwrap().to_string();
+            (crc, module)
+        };
+        let (ranges_crc, module) = module_for(test_bdf(glyphs), "ranges");
+        assert!(module.contains("        const auto [bitmap, width, height, x_advance, x_offset, y_offset] = font_glyph(i);\n"));
+        assert!(module.contains("        for (uint32_t v : fields) crc = crc32_word(crc, v);\n"));
+
+        // Same bitmaps and ranges, different advance.
+        let wider = String::from_utf8(test_bdf(glyphs)).unwrap().replacen("DWIDTH 5 0", "DWIDTH 6 0", 1);
+        assert_ne!(module_for(wider.into_bytes(), "ranges").0, ranges_crc);
+
+        // Same glyph table and ranges, different lookup arrays.
+        let (sorted_crc, module) = module_for(test_bdf(glyphs), "sorted_array");
+        assert_ne!(sorted_crc, ranges_crc);
+        assert!(module.contains("crc = crc32_word(crc, lookup_codepoints[i]);\n"));
+        let (page_crc, module) = module_for(test_bdf(glyphs), "page_table");
+        assert_ne!(page_crc, sorted_crc);
+        assert!(module.contains("crc = crc32_word(crc, lookup_page_glyphs[i / 256][i % 256]);\n"));
+        let verify = module.find("export inline bool font_verify()").unwrap();
+        assert!(module.find("inline constexpr uint8_t lookup_page_glyphs[][256] = {").unwrap() < verify);
+    }
+
+    fn test_glyph_data(codepoints: &[u32], extra_entries: usize, fallback_index: Option<usize>) -> GlyphData {
+        let glyphs = (0..codepoints.len() + extra_entries)
+            .map(|offset| GlyphEntry {
+                offset,
+                ..Default::default()
+            })
+            .collect();
+        GlyphData {
+            bitmaps: Vec::new(),
+            packed_glyphs: Vec::new(),
+            dedup_saved_bytes: 0,
+            glyphs,
+            codepoints: codepoints.to_vec(),
+            ranges: build_ranges(codepoints).unwrap(),
+            fallback_index,
+            max_w: 0,
+            max_h: 0,
+        }
+    }
+
+    #[test]
+    fn legacy_codes_drop_unmapped_and_shadowed_characters() {
+        let shift_jis = LegacyEncoding::from_option("shift_jis").unwrap().unwrap();
+        // U+005C and U+00A5 both encode to 0x5C, U+2212 and U+FF0D both to 0x817C; the character
+        // each code decodes back to keeps it, whichever comes first.
+        let mut codepoint_map: BTreeMap<u32, u16> =
+            [0x41, 0x5C, 0xA5, 0x2212, 0x4E2D, 0xFF0D, 0x20AC].iter().map(|cp| (*cp, 1)).collect();
+        let (legacy_chars, warnings) = legacy_codes(&mut codepoint_map, &shift_jis);
+        assert_eq!(
+            legacy_chars.into_iter().collect::<Vec<_>>(),
+            [(0x41, 0x41), (0x5C, 0x5C), (0x817C, 0xFF0D), (0x9286, 0x4E2D)]
+        );
+        assert_eq!(codepoint_map.keys().copied().collect::<Vec<_>>(), [0x41, 0x5C, 0x4E2D, 0xFF0D]);
+        assert_eq!(
+            warnings,
+            [
+                "1 characters have no Shift-JIS mapping and were left out (U+20AC)",
+                "2 characters share a Shift-JIS code with another character and were left out (U+00A5, U+2212)",
+            ]
+        );
+    }
+
+    #[test]
+    fn rekey_orders_glyphs_by_legacy_code() {
+        let shift_jis = LegacyEncoding::from_option("shift_jis").unwrap().unwrap();
+        // Codepoint order A, あ, 中, ｡ becomes code order 0x41, 0xA1, 0x82A0, 0x9286.
+        let codepoints = [0x41, 0x3042, 0x4E2D, 0xFF61];
+        let mut codepoint_map: BTreeMap<u32, u16> = codepoints.iter().map(|cp| (*cp, 1)).collect();
+        let (legacy_chars, warnings) = legacy_codes(&mut codepoint_map, &shift_jis);
+        assert!(warnings.is_empty());
+
+        // The fallback sits behind the ranged entries and keeps its slot.
+        let mut data = test_glyph_data(&codepoints, 1, Some(4));
+        rekey_glyph_data(&mut data, &legacy_chars).unwrap();
+        assert_eq!(data.codepoints, [0x41, 0xA1, 0x82A0, 0x9286]);
+        assert_eq!(data.glyphs.iter().map(|g| g.offset).collect::<Vec<_>>(), [0, 3, 1, 2, 4]);
+        let ranges: Vec<[u32; 3]> = data.ranges.iter().map(|r| [r.start, r.length, r.glyph_id_start]).collect();
+        assert_eq!(ranges, [[0x41, 1, 0], [0xA1, 1, 1], [0x82A0, 1, 2], [0x9286, 1, 3]]);
+        assert_eq!(data.fallback_index, Some(4));
+
+        // A fallback inside the charset follows its character.
+        let mut data = test_glyph_data(&codepoints, 0, Some(1));
+        rekey_glyph_data(&mut data, &legacy_chars).unwrap();
+        assert_eq!(data.fallback_index, Some(2));
+        assert_eq!(data.glyphs[2].offset, 1);
+
+        let mut data = test_glyph_data(&[0x41], 0, None);
+        let err = rekey_glyph_data(&mut data, &legacy_chars).unwrap_err();
+        assert_eq!(err, "U+FF61 is missing from the glyph table");
+    }
 
+    #[test]
+    fn line_height_overrides_cell_height_and_the_font() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        for (extra, line_height) in [
+            (serde_json::json!({}), 8),
+            (serde_json::json!({"cell_height": 6}), 6),
+            (serde_json::json!({"line_height": 10}), 10),
+            (serde_json::json!({"line_height": 10, "cell_height": 6}), 10),
+        ] {
+            let (module, _) = test_module(glyphs, extra.clone());
+            assert!(module.contains(&format!("    .line_height = {},\n", line_height)), "{}", extra);
+            assert!(module.contains("    .baseline = 7\n"), "{}", extra);
+        }
+    }
 
+    #[test]
+    fn baseline_shift_moves_the_baseline_and_the_cell() {
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        let (module, warnings) = test_module(glyphs, serde_json::json!({"baseline_shift": 2}));
+        assert!(module.contains("    .line_height = 8,\n    .baseline = 9\n"));
+        // Without a cell the glyphs keep their offsets above the baseline.
+        assert!(module.contains("    { glyph_bitmaps + 0, 4, 5, 5, 0, 5 },\n"));
+        assert!(warnings.iter().all(|w| !w.starts_with("Clipped")), "{:?}", warnings);
+
+        // Two rows down, the bottom row of every 5-row glyph falls out of the 8px cell.
+        let (module, warnings) = test_module(glyphs, serde_json::json!({"baseline_shift": 2, "cell_height": 8}));
+        assert!(module.contains("    .line_height = 8,\n    .baseline = 9\n"));
+        assert!(module.contains("    { glyph_bitmaps + 0, 4, 4, 5, 0, 5 },\n"));
+        assert!(
+            warnings.contains(&"Clipped 6 pixels from 3 glyphs to the 8px cell (U+0041, U+0042, U+003F)".to_string()),
+            "{:?}",
+            warnings
+        );
 
+        // Three rows up, the top row of each glyph is above the cell.
+        let (module, warnings) = test_module(glyphs, serde_json::json!({"baseline_shift": -3, "cell_height": 8}));
+        assert!(module.contains("    .baseline = 4\n"));
+        assert!(module.contains("    { glyph_bitmaps + 0, 4, 4, 5, 0, 4 },\n"));
+        assert!(
+            warnings.contains(&"Clipped 8 pixels from 3 glyphs to the 8px cell (U+0041, U+0042, U+003F)".to_string()),
+            "{:?}",
+            warnings
+        );
+    }
 
+    /// A TrueType font with 1000 units per em: for each character a 500 unit wide rectangle from
+    /// the baseline up to the given height, advancing 600 units.
+    fn test_ttf(glyphs: &[(char, i16)]) -> Vec<u8> {
+        crate::woff::build_sfnt(0x0001_0000, test_ttf_tables(glyphs))
+    }
 
+    fn test_ttf_tables(glyphs: &[(char, i16)]) -> Vec<([u8; 4], Vec<u8>)> {
+        let words = |values: &[i16]| values.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+        let longs = |values: &[u32]| values.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+        let count = glyphs.len() as i16 + 1;
+        let top = glyphs.iter().map(|(_, top)| *top).max().unwrap_or(0);
+
+        let mut head = longs(&[0x0001_0000, 0x0001_0000, 0, 0x5F0F_3CF5]);
+        head.extend(words(&[0, 1000]));
+        head.extend([0; 16]);
+        // Bounding box, macStyle, lowestRecPPEM, fontDirectionHint, long loca, glyphDataFormat.
+        head.extend(words(&[0, 0, 500, top, 0, 8, 2, 1, 0]));
+        let mut hhea = longs(&[0x0001_0000]);
+        hhea.extend(words(&[800, -200, 0, 600, 0, 0, 500, 1, 0, 0, 0, 0, 0, 0, 0, count]));
+        let mut maxp = longs(&[0x0000_5000]);
+        maxp.extend(words(&[count]));
+        let hmtx = words(&[600, 0].repeat(count as usize));
+
+        let mut groups: Vec<(u32, u32)> =
+            glyphs.iter().enumerate().map(|(i, (ch, _))| (*ch as u32, i as u32 + 1)).collect();
+        groups.sort_unstable();
+        let mut cmap = words(&[0, 1, 3, 10]);
+        cmap.extend(longs(&[12]));
+        cmap.extend(words(&[12, 0]));
+        cmap.extend(longs(&[16 + 12 * groups.len() as u32, 0, groups.len() as u32]));
+        for (cp, glyph) in groups {
+            cmap.extend(longs(&[cp, cp, glyph]));
+        }
 
+        let mut glyf = Vec::new();
+        let mut loca = vec![0u32, 0];
+        for (_, top) in glyphs {
+            glyf.extend(words(&[1, 0, 0, 500, *top, 3, 0]));
+            glyf.extend([1; 4]);
+            glyf.extend(words(&[0, 0, 500, 0, 0, *top, 0, -*top]));
+            glyf.resize(glyf.len().next_multiple_of(4), 0);
+            loca.push(glyf.len() as u32);
+        }
+        let loca = longs(&loca);
+
+        vec![
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"maxp", maxp),
+            (*b"hmtx", hmtx),
+            (*b"cmap", cmap),
+            (*b"loca", loca),
+            (*b"glyf", glyf),
+        ]
+    }
 
+    /// Bakes an outline font from `test_ttf` like `test_module` does a bitmap font.
+    fn test_outline_module(glyphs: &[(char, i16)], extra: serde_json::Value) -> Result<(String, Vec<String>), String> {
+        let job = test_job(extra);
+        let loaded = LoadedFont::from_bytes(test_ttf(glyphs), 0, String::new()).unwrap();
+        let baked = bake_font(&job, &loaded)?;
+        Ok((generate_cpp_module(&job, &baked), baked.warnings.clone()))
+    }
 
+    #[test]
+    fn cap_height_sizing_searches_around_the_estimate() {
+        // "H" is 0.73em tall: the estimate of 11px draws it 9px tall, 10px draws the 8px asked for.
+        let glyphs = [('A', 700), ('B', 700), ('?', 700), ('H', 730), ('x', 500)];
+        let (module, warnings) = test_outline_module(&glyphs, serde_json::json!({"size_mode": "cap_height"})).unwrap();
+        assert!(module.contains("//   size_px=10\n"), "{}", module);
+        assert!(warnings.iter().all(|w| !w.starts_with("No size")), "{:?}", warnings);
+
+        let extra = serde_json::json!({"size_mode": "x_height", "size_px": 5});
+        let (module, _) = test_outline_module(&glyphs, extra).unwrap();
+        assert!(module.contains("//   size_px=10\n"));
+
+        let extra = serde_json::json!({"size_mode": "x_height"});
+        let err = test_outline_module(&glyphs[..4], extra).unwrap_err();
+        assert_eq!(err, "Font has no 'x' to measure the x-height");
+    }
 
+    #[test]
+    fn cap_height_sizing_warns_when_no_size_fits() {
+        // At two pixels of "H" per em pixel, an odd cap height is out of reach; of 3px and 4px,
+        // both one pixel off, the estimate wins.
+        let glyphs = [('A', 700), ('B', 700), ('?', 700), ('H', 2000)];
+        let extra = serde_json::json!({"size_mode": "cap_height", "size_px": 7});
+        let (module, warnings) = test_outline_module(&glyphs, extra).unwrap();
+        assert!(module.contains("//   size_px=4\n"));
+        assert!(
+            warnings.contains(&"No size draws a 7px cap height, using 4px (8px cap height)".to_string()),
+            "{:?}",
+            warnings
+        );
 
+        let glyphs: &[(u32, &[&str])] = &[(0x41, GLYPH_A), (0x42, GLYPH_B), (0x3F, GLYPH_QUESTION)];
+        let (_, warnings) = test_module(glyphs, serde_json::json!({"size_mode": "cap_height"}));
+        let expected = "Size mode cap_height does not apply to bitmap fonts; using the font's size";
+        assert!(warnings.contains(&expected.to_string()), "{:?}", warnings);
+    }
 
+    fn test_grid(units_per_em: u16, grid_units: u32, suggested_sizes: &[u32]) -> PixelGrid {
+        PixelGrid {
+            units_per_em,
+            grid_units,
+            design_px: units_per_em as f32 / grid_units as f32,
+            suggested_sizes: suggested_sizes.to_vec(),
+        }
+    }
 
+    #[test]
+    fn pixel_grid_snaps_or_warns_about_unclean_sizes() {
+        let grid = test_grid(1024, 64, &[16, 32, 48, 64]);
+        let apply = |size_px: u32, snap: bool| {
+            let job = test_job(serde_json::json!({"size_px": size_px}));
+            let mut settings = RasterSettings::from_job(&job);
+            let mut warnings = Vec::new();
+            apply_pixel_grid(&grid, snap, &mut settings, &mut warnings);
+            (settings.size_px, settings.pixel_exact, warnings)
+        };
 
+        assert_eq!(apply(32, false), (32, true, vec![]));
+        assert_eq!(
+            apply(24, true),
+            (16, true, vec!["Pixel font grid is 16px, snapped size from 24px to 16px".to_string()])
+        );
+        let distorted = "Pixel font grid is 16px, 20px will distort the design (clean sizes: 16, 32, 48, 64)";
+        assert_eq!(apply(20, false), (20, false, vec![distorted.to_string()]));
+
+        // A 20.48px grid has no clean size among its first multiples, so there is nothing to snap to.
+        let grid = test_grid(2048, 100, &[]);
+        let job = test_job(serde_json::json!({"size_px": 20}));
+        let mut settings = RasterSettings::from_job(&job);
+        let mut warnings = Vec::new();
+        apply_pixel_grid(&grid, true, &mut settings, &mut warnings);
+        assert_eq!((settings.size_px, settings.pixel_exact), (20, false));
+        assert_eq!(warnings, ["Pixel font grid is 20.48px, 20px will distort the design (clean sizes: none)"]);
+    }
 
+    #[test]
+    fn provenance_hashes_the_font_file_not_the_decoded_woff() {
+        // A WOFF file storing the tables uncompressed, which decodes to a different SFNT.
+        let tables = test_ttf_tables(&[('A', 700), ('B', 700), ('?', 700)]);
+        let mut woff = b"wOFF".to_vec();
+        woff.extend(0x0001_0000u32.to_be_bytes());
+        woff.extend([0; 4]);
+        woff.extend((tables.len() as u16).to_be_bytes());
+        woff.resize(44, 0);
+        let mut offset = woff.len() + tables.len() * 20;
+        for (tag, table) in &tables {
+            woff.extend(tag);
+            for value in [offset, table.len(), table.len(), 0] {
+                woff.extend((value as u32).to_be_bytes());
+            }
+            offset += table.len().next_multiple_of(4);
+        }
+        for (_, table) in &tables {
+            woff.extend(table);
+            woff.resize(woff.len().next_multiple_of(4), 0);
+        }
 
+        let path = std::env::temp_dir().join(format!("provenance-test-{}.woff", std::process::id()));
+        fs::write(&path, &woff).unwrap();
+        let source = FontSource::File {
+            path: path.to_string_lossy().to_string(),
+            font_index: 0,
+        };
+        let loaded = load_font_from_source(&source);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.file_sha256, sha256_hex(&woff));
+        assert_ne!(loaded.file_sha256, sha256_hex(&loaded.data));
+
+        let job = test_job(serde_json::json!({}));
+        let baked = bake_font(&job, &loaded).unwrap();
+        let module = generate_cpp_module(&job, &baked);
+        assert!(module.contains(&format!("//   font_sha256={}\n", sha256_hex(&woff))), "{}", module);
+    }
+}
@@ -0,0 +1,508 @@
+// Display controller emulation for previews. Framebuffer bytes are packed the way firmware
+// writes them (with the selected bit layout and byte order), then decoded with the controller's
+// native memory format and scan settings, so a layout or orientation mistake shows up as a
+// scrambled or mirrored picture instead of on the bench.
+
+use serde::{Deserialize, Serialize};
+
+use crate::png_io::RgbaImage;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PanelSpec {
+    /// "ssd1306", "sh1106", "st7735", "ili9341", "epaper" or "epaper_tricolor".
+    pub panel: String,
+    /// Glass resolution; 0 uses the panel's usual size.
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    /// Layout the firmware packs 1bpp memory in: "page_lsb", "page_msb", "row_msb" or "row_lsb".
+    /// Empty uses the controller's own layout.
+    #[serde(default)]
+    pub bit_layout: String,
+    /// Byte order of RGB565 pixels as sent: "big" (what the controller expects) or "little".
+    #[serde(default = "default_byte_order")]
+    pub byte_order: String,
+    /// SH1106: RAM column the framebuffer is written at; the glass starts at column 2.
+    pub column_offset: Option<u32>,
+    /// Controller scan setup: segment remap and COM direction on OLEDs, MADCTL MX/MY/MV on TFTs,
+    /// data entry mode on e-paper.
+    #[serde(default)]
+    pub mirror_x: bool,
+    #[serde(default)]
+    pub mirror_y: bool,
+    #[serde(default)]
+    pub swap_xy: bool,
+    /// MADCTL BGR bit: the controller reads the first color field of a pixel as blue.
+    #[serde(default)]
+    pub bgr: bool,
+    /// Display inversion on monochrome panels.
+    #[serde(default)]
+    pub invert: bool,
+    /// Clockwise rotation of the rendered picture, matching how the panel is mounted.
+    #[serde(default)]
+    pub rotate: u32,
+}
+
+fn default_byte_order() -> String {
+    "big".to_string()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Memory {
+    /// OLED page memory: 8-row pages, one byte per column, `ram_width` columns per page.
+    Pages { ram_width: u32, column_offset: u32 },
+    Rgb565,
+    Mono,
+    TriColor,
+}
+
+/// RAM column where the SH1106 glass starts.
+const SH1106_GLASS_OFFSET: u32 = 2;
+
+const OLED_LIT: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+const OLED_OFF: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+const PAPER_WHITE: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+const PAPER_BLACK: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+const PAPER_RED: [u8; 4] = [0xD0, 0x00, 0x00, 0xFF];
+
+struct Panel {
+    memory: Memory,
+    width: u32,
+    height: u32,
+    native_layout: &'static str,
+}
+
+impl Panel {
+    fn resolve(spec: &PanelSpec) -> Result<Self, String> {
+        let (memory, default_size, native_layout) = match spec.panel.as_str() {
+            "ssd1306" => (
+                Memory::Pages {
+                    ram_width: 0,
+                    column_offset: 0,
+                },
+                Some((128, 64)),
+                "page_lsb",
+            ),
+            "sh1106" => (
+                Memory::Pages {
+                    ram_width: 132,
+                    column_offset: spec.column_offset.unwrap_or(SH1106_GLASS_OFFSET),
+                },
+                Some((128, 64)),
+                "page_lsb",
+            ),
+            "st7735" => (Memory::Rgb565, Some((128, 160)), ""),
+            "ili9341" => (Memory::Rgb565, Some((240, 320)), ""),
+            "epaper" => (Memory::Mono, None, "row_msb"),
+            "epaper_tricolor" => (Memory::TriColor, None, "row_msb"),
+            other => return Err(format!("Unknown panel: {}", other)),
+        };
+        let (width, height) = match (spec.width, spec.height, default_size) {
+            (0, 0, Some(size)) => size,
+            (0, _, _) | (_, 0, _) => return Err(format!("Panel {} needs a width and height", spec.panel)),
+            size => (size.0, size.1),
+        };
+        let memory = match memory {
+            Memory::Pages { ram_width: 0, .. } => Memory::Pages {
+                ram_width: width,
+                column_offset: 0,
+            },
+            Memory::Pages { column_offset, .. } => Memory::Pages {
+                ram_width: width + 2 * SH1106_GLASS_OFFSET,
+                column_offset,
+            },
+            other => other,
+        };
+        if spec.swap_xy && matches!(memory, Memory::Pages { .. }) {
+            return Err(format!("Panel {} cannot exchange rows and columns", spec.panel));
+        }
+        if !matches!(spec.rotate, 0 | 90 | 180 | 270) {
+            return Err(format!("Invalid rotation: {}", spec.rotate));
+        }
+        Ok(Self {
+            memory,
+            width,
+            height,
+            native_layout,
+        })
+    }
+
+    /// Size of the picture the firmware draws, before the controller maps it onto the glass.
+    fn logical_size(&self, spec: &PanelSpec) -> (u32, u32) {
+        if spec.swap_xy {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    fn layout<'a>(&self, spec: &'a PanelSpec) -> Result<&'a str, String> {
+        match spec.bit_layout.as_str() {
+            "" => Ok(self.native_layout),
+            "page_lsb" | "page_msb" | "row_msb" | "row_lsb" => Ok(&spec.bit_layout),
+            other => Err(format!("Unknown bit layout: {}", other)),
+        }
+    }
+}
+
+/// Size of the picture to draw for `spec`, in firmware coordinates.
+pub fn drawing_size(spec: &PanelSpec) -> Result<(u32, u32), String> {
+    let panel = Panel::resolve(spec)?;
+    Ok(panel.logical_size(spec))
+}
+
+/// Byte index and bit mask of pixel (x, y) in a 1bpp buffer `width` pixels wide.
+fn bit_position(layout: &str, width: u32, x: u32, y: u32) -> (usize, u8) {
+    match layout {
+        "page_lsb" => (((y / 8) * width + x) as usize, 1 << (y % 8)),
+        "page_msb" => (((y / 8) * width + x) as usize, 0x80 >> (y % 8)),
+        "row_lsb" => ((y * width.div_ceil(8) + x / 8) as usize, 1 << (x % 8)),
+        _ => ((y * width.div_ceil(8) + x / 8) as usize, 0x80 >> (x % 8)),
+    }
+}
+
+fn plane_size(layout: &str, width: u32, height: u32) -> usize {
+    if layout.starts_with("page") {
+        (height.div_ceil(8) * width) as usize
+    } else {
+        (width.div_ceil(8) * height) as usize
+    }
+}
+
+fn luma(p: [u8; 4]) -> u32 {
+    (p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000
+}
+
+fn is_red(p: [u8; 4]) -> bool {
+    p[0] >= 128 && p[1] < 128 && p[2] < 128
+}
+
+/// Packs a picture of `drawing_size` into framebuffer bytes the way firmware would: bright
+/// pixels light OLED pixels, dark pixels become e-paper black and pure reds the red plane.
+pub fn pack_framebuffer(spec: &PanelSpec, image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let panel = Panel::resolve(spec)?;
+    let (w, h) = panel.logical_size(spec);
+    if (image.width, image.height) != (w, h) {
+        return Err(format!(
+            "Picture is {}x{}, panel {} draws {}x{}",
+            image.width, image.height, spec.panel, w, h
+        ));
+    }
+    if panel.memory == Memory::Rgb565 {
+        let little = match spec.byte_order.as_str() {
+            "big" => false,
+            "little" => true,
+            other => return Err(format!("Unknown byte order: {}", other)),
+        };
+        let mut out = Vec::with_capacity(w as usize * h as usize * 2);
+        for p in image.pixels.chunks_exact(4) {
+            let value = (p[0] as u16 >> 3) << 11 | (p[1] as u16 >> 2) << 5 | p[2] as u16 >> 3;
+            out.extend_from_slice(&if little { value.to_le_bytes() } else { value.to_be_bytes() });
+        }
+        return Ok(out);
+    }
+
+    let layout = panel.layout(spec)?;
+    let size = plane_size(layout, w, h);
+    let mut ink = vec![0u8; size];
+    let mut red = vec![0u8; size];
+    for y in 0..h {
+        for x in 0..w {
+            let p = image.pixel(x, y);
+            let (byte, mask) = bit_position(layout, w, x, y);
+            let bright = luma(p) >= 128;
+            match panel.memory {
+                // E-paper planes store 1 for white.
+                Memory::Mono | Memory::TriColor if bright || is_red(p) => ink[byte] |= mask,
+                Memory::Pages { .. } if bright => ink[byte] |= mask,
+                _ => {}
+            }
+            if panel.memory == Memory::TriColor && is_red(p) {
+                red[byte] |= mask;
+            }
+        }
+    }
+    if panel.memory == Memory::TriColor {
+        ink.extend(red);
+    }
+    Ok(ink)
+}
+
+/// Shows framebuffer bytes as the panel would, in the orientation set by `rotate`. Short
+/// buffers read as blank and extra bytes are ignored, both with a warning.
+pub fn emulate(spec: &PanelSpec, bytes: &[u8]) -> Result<(RgbaImage, Vec<String>), String> {
+    let panel = Panel::resolve(spec)?;
+    let (w, h) = panel.logical_size(spec);
+    let expected = match panel.memory {
+        Memory::Pages { .. } => plane_size("page_lsb", w, h),
+        Memory::Rgb565 => w as usize * h as usize * 2,
+        Memory::Mono => plane_size("row_msb", w, h),
+        Memory::TriColor => plane_size("row_msb", w, h) * 2,
+    };
+    let mut warnings = Vec::new();
+    if bytes.len() != expected {
+        warnings.push(format!(
+            "Panel {} expects {} framebuffer bytes, got {}",
+            spec.panel,
+            expected,
+            bytes.len()
+        ));
+    }
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+    let bit = |layout: &str, base: usize, width: u32, x: u32, y: u32| {
+        let (i, mask) = bit_position(layout, width, x, y);
+        byte(base + i) & mask != 0
+    };
+
+    // Decode the controller's memory into the picture it holds, in firmware coordinates.
+    let mut logical = RgbaImage {
+        width: w,
+        height: h,
+        pixels: vec![0; w as usize * h as usize * 4],
+    };
+    if let Memory::Pages {
+        ram_width,
+        column_offset,
+    } = panel.memory
+    {
+        // The framebuffer is `w` columns per page written from `column_offset`; the glass shows
+        // RAM from column `(ram_width - w) / 2`.
+        let glass_start = (ram_width - w) / 2;
+        let mut ram = vec![0u8; plane_size("page_lsb", ram_width, h)];
+        for page in 0..h.div_ceil(8) {
+            for x in 0..w {
+                let column = column_offset + x;
+                if column < ram_width {
+                    ram[(page * ram_width + column) as usize] = byte((page * w + x) as usize);
+                }
+            }
+        }
+        for y in 0..h {
+            for x in 0..w {
+                let (i, mask) = bit_position("page_lsb", ram_width, glass_start + x, y);
+                let lit = (ram[i] & mask != 0) != spec.invert;
+                set_pixel(&mut logical, x, y, if lit { OLED_LIT } else { OLED_OFF });
+            }
+        }
+    }
+    for y in 0..h {
+        for x in 0..w {
+            let color = match panel.memory {
+                Memory::Pages { .. } => continue,
+                Memory::Rgb565 => {
+                    let i = (y as usize * w as usize + x as usize) * 2;
+                    let value = u16::from_be_bytes([byte(i), byte(i + 1)]);
+                    let first = ((value >> 11) & 0x1F) as u32 * 255 / 31;
+                    let green = ((value >> 5) & 0x3F) as u32 * 255 / 63;
+                    let last = (value & 0x1F) as u32 * 255 / 31;
+                    let (r, b) = if spec.bgr { (last, first) } else { (first, last) };
+                    [r as u8, green as u8, b as u8, 0xFF]
+                }
+                Memory::Mono | Memory::TriColor => {
+                    let white = bit("row_msb", 0, w, x, y) != spec.invert;
+                    let red = panel.memory == Memory::TriColor && bit("row_msb", expected / 2, w, x, y);
+                    match (red, white) {
+                        (true, _) => PAPER_RED,
+                        (false, true) => PAPER_WHITE,
+                        (false, false) => PAPER_BLACK,
+                    }
+                }
+            };
+            set_pixel(&mut logical, x, y, color);
+        }
+    }
+
+    // Map firmware coordinates onto the glass, then turn the glass the way it is mounted.
+    let mut glass = RgbaImage {
+        width: panel.width,
+        height: panel.height,
+        pixels: vec![0; panel.width as usize * panel.height as usize * 4],
+    };
+    for y in 0..h {
+        for x in 0..w {
+            let (mut gx, mut gy) = if spec.swap_xy { (y, x) } else { (x, y) };
+            if spec.mirror_x {
+                gx = panel.width - 1 - gx;
+            }
+            if spec.mirror_y {
+                gy = panel.height - 1 - gy;
+            }
+            set_pixel(&mut glass, gx, gy, logical.pixel(x, y));
+        }
+    }
+    Ok((rotate(&glass, spec.rotate), warnings))
+}
+
+fn set_pixel(image: &mut RgbaImage, x: u32, y: u32, color: [u8; 4]) {
+    let i = (y as usize * image.width as usize + x as usize) * 4;
+    image.pixels[i..i + 4].copy_from_slice(&color);
+}
+
+fn rotate(image: &RgbaImage, degrees: u32) -> RgbaImage {
+    let (w, h) = (image.width, image.height);
+    let (rw, rh) = if degrees % 180 == 90 { (h, w) } else { (w, h) };
+    let mut out = RgbaImage {
+        width: rw,
+        height: rh,
+        pixels: vec![0; image.pixels.len()],
+    };
+    for y in 0..h {
+        for x in 0..w {
+            let (rx, ry) = match degrees {
+                90 => (h - 1 - y, x),
+                180 => (w - 1 - x, h - 1 - y),
+                270 => (y, w - 1 - x),
+                _ => (x, y),
+            };
+            set_pixel(&mut out, rx, ry, image.pixel(x, y));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(fields: serde_json::Value) -> PanelSpec {
+        serde_json::from_value(fields).unwrap()
+    }
+
+    fn picture(width: u32, height: u32, color: impl Fn(u32, u32) -> [u8; 4]) -> RgbaImage {
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| color(x, y)).collect();
+        RgbaImage { width, height, pixels }
+    }
+
+    /// An irregular pattern whose rows and columns all differ, so any reordering shows.
+    fn pattern(width: u32, height: u32, on: [u8; 4], off: [u8; 4]) -> RgbaImage {
+        picture(width, height, |x, y| if (x * 7 + y * 3) % 5 == 0 || (x == 1 && y < 5) { on } else { off })
+    }
+
+    fn round_trip(spec: &PanelSpec, image: &RgbaImage) -> RgbaImage {
+        let bytes = pack_framebuffer(spec, image).unwrap();
+        let (emulated, warnings) = emulate(spec, &bytes).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        emulated
+    }
+
+    fn lit(image: &RgbaImage, on: [u8; 4]) -> Vec<(u32, u32)> {
+        (0..image.height)
+            .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+            .filter(|(x, y)| image.pixel(*x, *y) == on)
+            .collect()
+    }
+
+    #[test]
+    fn oled_pages_round_trip_in_the_native_layout() {
+        let image = pattern(16, 16, OLED_LIT, OLED_OFF);
+        for panel in ["ssd1306", "sh1106"] {
+            let native = spec(serde_json::json!({"panel": panel, "width": 16, "height": 16}));
+            assert_eq!(round_trip(&native, &image).pixels, image.pixels, "{}", panel);
+            for layout in ["page_msb", "row_msb"] {
+                let wrong = spec(serde_json::json!({"panel": panel, "width": 16, "height": 16, "bit_layout": layout}));
+                assert_ne!(round_trip(&wrong, &image).pixels, image.pixels, "{} {}", panel, layout);
+            }
+        }
+        let ssd1306 = spec(serde_json::json!({"panel": "ssd1306"}));
+        assert_eq!(pack_framebuffer(&ssd1306, &pattern(128, 64, OLED_LIT, OLED_OFF)).unwrap().len(), 1024);
+    }
+
+    #[test]
+    fn sh1106_column_offset_shifts_the_picture() {
+        let image = pattern(16, 8, OLED_LIT, OLED_OFF);
+        // Written at RAM column 0, the glass (from column 2) shows the picture two columns left.
+        let unshifted = spec(serde_json::json!({"panel": "sh1106", "width": 16, "height": 8, "column_offset": 0}));
+        let shown = round_trip(&unshifted, &image);
+        for y in 0..8 {
+            for x in 0..16 {
+                let expected = if x < 14 { image.pixel(x + 2, y) } else { OLED_OFF };
+                assert_eq!(shown.pixel(x, y), expected, "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn mirrors_swaps_and_rotations_move_the_corner() {
+        // Two lit pixels at the top-left corner, the second to its right.
+        let image = picture(8, 4, |x, y| if y == 0 && x < 2 { PAPER_WHITE } else { PAPER_BLACK });
+        let cases = [
+            (serde_json::json!({}), vec![(0, 0), (1, 0)]),
+            (serde_json::json!({"mirror_x": true}), vec![(6, 0), (7, 0)]),
+            (serde_json::json!({"mirror_y": true}), vec![(0, 3), (1, 3)]),
+            (serde_json::json!({"rotate": 90}), vec![(3, 0), (3, 1)]),
+            (serde_json::json!({"rotate": 180}), vec![(6, 3), (7, 3)]),
+            (serde_json::json!({"rotate": 270}), vec![(0, 6), (0, 7)]),
+        ];
+        for (fields, expected) in cases {
+            let mut json = serde_json::json!({"panel": "epaper", "width": 8, "height": 4});
+            json.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+            let shown = round_trip(&spec(json), &image);
+            assert_eq!(lit(&shown, PAPER_WHITE), expected, "{}", fields);
+        }
+
+        // Exchanging rows and columns draws a 4x8 picture onto the 8x4 glass.
+        let swapped = spec(serde_json::json!({"panel": "st7735", "width": 8, "height": 4, "swap_xy": true}));
+        assert_eq!(drawing_size(&swapped).unwrap(), (4, 8));
+        let image = picture(4, 8, |x, y| if x == 0 && y < 2 { PAPER_WHITE } else { PAPER_BLACK });
+        let shown = round_trip(&swapped, &image);
+        assert_eq!((shown.width, shown.height), (8, 4));
+        assert_eq!(lit(&shown, PAPER_WHITE), [(0, 0), (1, 0)]);
+
+        let oled = spec(serde_json::json!({"panel": "ssd1306", "swap_xy": true}));
+        assert!(pack_framebuffer(&oled, &image).is_err());
+        let tilted = spec(serde_json::json!({"panel": "epaper", "width": 8, "height": 4, "rotate": 45}));
+        assert!(drawing_size(&tilted).is_err());
+    }
+
+    #[test]
+    fn rgb565_byte_order_and_bgr() {
+        const RED: [u8; 4] = [0xFF, 0x00, 0x00, 0xFF];
+        const BLUE: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+        let image = picture(4, 3, |x, y| match (x + y) % 4 {
+            0 => RED,
+            1 => [0x00, 0xFF, 0x00, 0xFF],
+            2 => BLUE,
+            _ => [0xFF, 0xFF, 0xFF, 0xFF],
+        });
+        let big = spec(serde_json::json!({"panel": "st7735", "width": 4, "height": 3}));
+        assert_eq!(&pack_framebuffer(&big, &image).unwrap()[..2], &[0xF8, 0x00]);
+        assert_eq!(round_trip(&big, &image).pixels, image.pixels);
+
+        let little = spec(serde_json::json!({"panel": "st7735", "width": 4, "height": 3, "byte_order": "little"}));
+        assert_eq!(&pack_framebuffer(&little, &image).unwrap()[..2], &[0x00, 0xF8]);
+        assert_ne!(round_trip(&little, &image).pixels, image.pixels);
+
+        // A controller in BGR mode shows the red and blue fields swapped.
+        let bgr = spec(serde_json::json!({"panel": "st7735", "width": 4, "height": 3, "bgr": true}));
+        let shown = round_trip(&bgr, &image);
+        assert_eq!((shown.pixel(0, 0), shown.pixel(2, 0)), (BLUE, RED));
+    }
+
+    #[test]
+    fn tricolor_epaper_packs_two_planes() {
+        let image = picture(8, 2, |x, _| match x % 3 {
+            0 => PAPER_WHITE,
+            1 => PAPER_BLACK,
+            _ => PAPER_RED,
+        });
+        let tricolor = spec(serde_json::json!({"panel": "epaper_tricolor", "width": 8, "height": 2}));
+        let bytes = pack_framebuffer(&tricolor, &image).unwrap();
+        // White and red are 1 in the black/white plane; only red is set in the red plane.
+        assert_eq!(bytes, [0b1011_0110, 0b1011_0110, 0b0010_0100, 0b0010_0100]);
+        assert_eq!(round_trip(&tricolor, &image).pixels, image.pixels);
+
+        let mono = spec(serde_json::json!({"panel": "epaper", "width": 8, "height": 2}));
+        assert_eq!(pack_framebuffer(&mono, &image).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn wrong_buffer_sizes_warn() {
+        let ssd1306 = spec(serde_json::json!({"panel": "ssd1306", "width": 16, "height": 16}));
+        let (shown, warnings) = emulate(&ssd1306, &[0xFF; 4]).unwrap();
+        assert_eq!(warnings, ["Panel ssd1306 expects 32 framebuffer bytes, got 4"]);
+        assert_eq!(lit(&shown, OLED_LIT).len(), 32);
+        let err = pack_framebuffer(&ssd1306, &pattern(8, 8, OLED_LIT, OLED_OFF)).unwrap_err();
+        assert_eq!(err, "Picture is 8x8, panel ssd1306 draws 16x16");
+    }
+}
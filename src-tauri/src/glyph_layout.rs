@@ -0,0 +1,175 @@
+/// Value range of one glyph table column, used to pick the narrowest C type for it.
+pub struct FieldRange {
+    pub name: &'static str,
+    pub min: i64,
+    pub max: i64,
+}
+
+impl FieldRange {
+    /// Columns holding the same value for every glyph are dropped from the struct.
+    pub fn constant(&self) -> Option<i64> {
+        (self.min == self.max).then_some(self.min)
+    }
+
+    pub fn signed(&self) -> bool {
+        self.min < 0
+    }
+
+    pub fn bits(&self) -> u32 {
+        if self.signed() {
+            let magnitude = self.max.max(-self.min - 1).max(0) as u64;
+            (u64::BITS - magnitude.leading_zeros()) + 1
+        } else {
+            (u64::BITS - (self.max as u64).leading_zeros()).max(1)
+        }
+    }
+
+    fn byte_width(&self) -> usize {
+        match self.bits() {
+            0..=8 => 1,
+            9..=16 => 2,
+            17..=32 => 4,
+            _ => 8,
+        }
+    }
+
+    fn c_type(&self) -> String {
+        let prefix = if self.signed() { "int" } else { "uint" };
+        format!("{}{}_t", prefix, self.byte_width() * 8)
+    }
+}
+
+pub struct CompactLayout {
+    pub fields: Vec<FieldRange>,
+    pub bitfields: bool,
+}
+
+impl CompactLayout {
+    /// Plans a layout from the table rows; each row holds one value per column in `names` order.
+    /// Bitfield entries are packed into one 32- or 64-bit word and fail when they need more.
+    pub fn plan(names: &[&'static str], rows: &[Vec<i64>], bitfields: bool) -> Result<Self, String> {
+        let mut fields: Vec<FieldRange> = names
+            .iter()
+            .enumerate()
+            .map(|(col, name)| FieldRange {
+                name,
+                min: rows.iter().map(|r| r[col]).min().unwrap_or(0),
+                max: rows.iter().map(|r| r[col]).max().unwrap_or(0),
+            })
+            .collect();
+        // Widest first so the struct needs no padding between members.
+        if !bitfields {
+            fields.sort_by_key(|f| std::cmp::Reverse(f.byte_width()));
+        }
+        let layout = Self { fields, bitfields };
+        if bitfields && layout.total_bits() > 64 {
+            return Err(format!(
+                "Bitfield glyph entries need {} bits, more than fit in 64; use the compact layout",
+                layout.total_bits()
+            ));
+        }
+        Ok(layout)
+    }
+
+    pub fn stored_fields(&self) -> impl Iterator<Item = &FieldRange> {
+        self.fields.iter().filter(|f| f.constant().is_none())
+    }
+
+    fn total_bits(&self) -> u32 {
+        self.stored_fields().map(|f| f.bits()).sum()
+    }
+
+    /// Size of one table entry in bytes, including tail padding.
+    pub fn entry_size(&self) -> usize {
+        if self.bitfields {
+            return if self.total_bits() <= 32 { 4 } else { 8 };
+        }
+        let size: usize = self.stored_fields().map(|f| f.byte_width()).sum();
+        let align = self.stored_fields().map(|f| f.byte_width()).max().unwrap_or(1);
+        size.div_ceil(align).max(1) * align
+    }
+
+    pub fn struct_definition(&self, name: &str) -> String {
+        let mut out = format!("struct {} {{\n", name);
+        let storage_bits = if self.total_bits() <= 32 { 32 } else { 64 };
+        for field in self.stored_fields() {
+            if self.bitfields {
+                let prefix = if field.signed() { "int" } else { "uint" };
+                out.push_str(&format!(
+                    "    {}{}_t {} : {};\n",
+                    prefix,
+                    storage_bits,
+                    field.name,
+                    field.bits()
+                ));
+            } else {
+                out.push_str(&format!("    {} {};\n", field.c_type(), field.name));
+            }
+        }
+        if self.stored_fields().next().is_none() {
+            out.push_str("    uint8_t unused;\n");
+        }
+        out.push_str("};\n");
+        out
+    }
+
+    /// Formats one row as a brace initializer in struct member order.
+    pub fn initializer(&self, names: &[&'static str], row: &[i64]) -> String {
+        let values: Vec<String> = self
+            .stored_fields()
+            .map(|f| {
+                let col = names.iter().position(|n| *n == f.name).unwrap_or(0);
+                row[col].to_string()
+            })
+            .collect();
+        if values.is_empty() {
+            "{ 0 }".to_string()
+        } else {
+            format!("{{ {} }}", values.join(", "))
+        }
+    }
+
+    /// Expression reading column `name` from entry `var`, folding constant columns to literals.
+    pub fn accessor(&self, var: &str, name: &str) -> String {
+        match self.fields.iter().find(|f| f.name == name) {
+            Some(field) => match field.constant() {
+                Some(value) => value.to_string(),
+                None => format!("{}.{}", var, name),
+            },
+            None => "0".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 3] = ["offset", "width", "x_offset"];
+
+    #[test]
+    fn bitfield_entries_use_the_smallest_word() {
+        let rows = vec![vec![0, 1, -2], vec![4000, 12, 3]];
+        let layout = CompactLayout::plan(&NAMES, &rows, true).unwrap();
+        // 12 + 4 + 3 bits.
+        assert_eq!(layout.entry_size(), 4);
+        assert!(layout.struct_definition("G").contains("    uint32_t offset : 12;\n"));
+        assert!(layout.struct_definition("G").contains("    int32_t x_offset : 3;\n"));
+
+        let rows = vec![vec![0, 1, -2], vec![1 << 40, 12, 3]];
+        let layout = CompactLayout::plan(&NAMES, &rows, true).unwrap();
+        assert_eq!(layout.entry_size(), 8);
+        assert!(layout.struct_definition("G").contains("    uint64_t offset : 41;\n"));
+    }
+
+    #[test]
+    fn bitfield_entries_wider_than_64_bits_are_rejected() {
+        let rows = vec![vec![0, 0, -(1 << 30)], vec![1 << 40, 1 << 20, 0]];
+        let err = CompactLayout::plan(&NAMES, &rows, true).err().unwrap();
+        assert!(err.contains("need 93 bits"), "{}", err);
+
+        let layout = CompactLayout::plan(&NAMES, &rows, false).unwrap();
+        assert_eq!(layout.entry_size(), 16);
+        assert_eq!(layout.initializer(&NAMES, &rows[1]), "{ 1099511627776, 1048576, 0 }");
+    }
+}
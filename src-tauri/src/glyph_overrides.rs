@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+/// Hand edit of one glyph at one pixel size, applied on top of the rasterized bitmap.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GlyphOverride {
+    pub codepoint: u32,
+    pub size_px: u32,
+    /// "replace" swaps in `rows`; "patch" sets and clears single pixels of the rasterized glyph.
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    /// Size of the replacement bitmap, or of the rasterized glyph a patch was drawn against.
+    pub width: u32,
+    pub height: u32,
+    /// Replacement bitmap, one string per row; `#` is ink, anything else is blank.
+    #[serde(default)]
+    pub rows: Vec<String>,
+    /// Patch pixels as `[x, y]` from the top-left corner of the glyph bitmap.
+    #[serde(default)]
+    pub set: Vec<[u32; 2]>,
+    #[serde(default)]
+    pub clear: Vec<[u32; 2]>,
+    /// Replacement placement; each defaults to the rasterized glyph's value.
+    pub x_offset: Option<i32>,
+    /// Bottom edge of the replacement relative to the baseline.
+    pub y_offset: Option<i32>,
+    pub advance: Option<i32>,
+}
+
+fn default_kind() -> String {
+    "patch".to_string()
+}
+
+impl GlyphOverride {
+    pub fn validate(&self) -> Result<(), String> {
+        let at = format!("Glyph override for U+{:04X} at {}px", self.codepoint, self.size_px);
+        match self.kind.as_str() {
+            "replace" => {
+                if self.rows.len() != self.height as usize {
+                    return Err(format!("{} has {} rows, expected {}", at, self.rows.len(), self.height));
+                }
+                if let Some(row) = self.rows.iter().find(|row| row.chars().count() != self.width as usize) {
+                    return Err(format!("{} has a row of {} pixels, expected {}", at, row.chars().count(), self.width));
+                }
+            }
+            "patch" => {
+                let outside = self.set.iter().chain(&self.clear).find(|[x, y]| *x >= self.width || *y >= self.height);
+                if let Some([x, y]) = outside {
+                    return Err(format!("{} pixel ({}, {}) is outside {}x{}", at, x, y, self.width, self.height));
+                }
+            }
+            other => return Err(format!("{} has unknown kind: {}", at, other)),
+        }
+        Ok(())
+    }
+
+    /// Applies the override to a rasterized glyph. Invalid overrides and patches drawn against a
+    /// glyph of a different size are rejected with a warning and the glyph is left as rasterized.
+    pub fn apply(&self, metrics: &mut fontdue::Metrics, gray: &mut Vec<u8>) -> Result<(), String> {
+        self.validate()?;
+        if self.kind == "replace" {
+            *gray = self
+                .rows
+                .iter()
+                .flat_map(|row| row.chars().map(|c| if c == '#' { 255 } else { 0 }))
+                .collect();
+            metrics.width = self.width as usize;
+            metrics.height = self.height as usize;
+            metrics.xmin = self.x_offset.unwrap_or(metrics.xmin);
+            metrics.ymin = self.y_offset.unwrap_or(metrics.ymin);
+            if let Some(advance) = self.advance {
+                metrics.advance_width = advance as f32;
+            }
+            return Ok(());
+        }
+        if metrics.width != self.width as usize || metrics.height != self.height as usize {
+            return Err(format!(
+                "Glyph override for U+{:04X} at {}px was drawn on a {}x{} glyph, now {}x{}; skipped",
+                self.codepoint, self.size_px, self.width, self.height, metrics.width, metrics.height
+            ));
+        }
+        let stride = self.width as usize;
+        for [x, y] in &self.set {
+            gray[*y as usize * stride + *x as usize] = 255;
+        }
+        for [x, y] in &self.clear {
+            gray[*y as usize * stride + *x as usize] = 0;
+        }
+        Ok(())
+    }
+}
+
+pub fn find_override(overrides: &[GlyphOverride], codepoint: u32, size_px: u32) -> Option<&GlyphOverride> {
+    overrides
+        .iter()
+        .rev()
+        .find(|o| o.codepoint == codepoint && o.size_px == size_px)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph_override(fields: serde_json::Value) -> GlyphOverride {
+        let mut json = serde_json::json!({"codepoint": 0x41, "size_px": 8});
+        json.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(json).unwrap()
+    }
+
+    /// A rasterized 2x2 glyph with the top-left pixel inked.
+    fn rasterized() -> (fontdue::Metrics, Vec<u8>) {
+        let metrics = fontdue::Metrics {
+            xmin: 1,
+            ymin: -1,
+            width: 2,
+            height: 2,
+            advance_width: 3.0,
+            ..Default::default()
+        };
+        (metrics, vec![255, 0, 0, 0])
+    }
+
+    #[test]
+    fn replace_swaps_bitmap_and_placement() {
+        let (mut metrics, mut gray) = rasterized();
+        let o = glyph_override(serde_json::json!({
+            "kind": "replace",
+            "width": 3,
+            "height": 1,
+            "rows": ["#.#"],
+            "y_offset": 2,
+            "advance": 5
+        }));
+        o.apply(&mut metrics, &mut gray).unwrap();
+        assert_eq!(gray, [255, 0, 255]);
+        assert_eq!((metrics.width, metrics.height, metrics.xmin, metrics.ymin), (3, 1, 1, 2));
+        assert_eq!(metrics.advance_width, 5.0);
+    }
+
+    #[test]
+    fn patch_sets_and_clears_pixels() {
+        let (mut metrics, mut gray) = rasterized();
+        let o = glyph_override(serde_json::json!({"width": 2, "height": 2, "set": [[1, 1]], "clear": [[0, 0]]}));
+        o.apply(&mut metrics, &mut gray).unwrap();
+        assert_eq!(gray, [0, 0, 0, 255]);
+        assert_eq!((metrics.width, metrics.height, metrics.xmin), (2, 2, 1));
+    }
+
+    #[test]
+    fn patch_for_another_size_is_skipped_with_a_warning() {
+        let (mut metrics, mut gray) = rasterized();
+        let o = glyph_override(serde_json::json!({"width": 3, "height": 2, "set": [[2, 1]]}));
+        let warning = o.apply(&mut metrics, &mut gray).unwrap_err();
+        assert_eq!(warning, "Glyph override for U+0041 at 8px was drawn on a 3x2 glyph, now 2x2; skipped");
+        assert_eq!(gray, [255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected_before_touching_the_glyph() {
+        let (mut metrics, mut gray) = rasterized();
+        let outside = glyph_override(serde_json::json!({"width": 2, "height": 2, "set": [[0, 5]]}));
+        let err = outside.apply(&mut metrics, &mut gray).unwrap_err();
+        assert!(err.contains("pixel (0, 5) is outside 2x2"), "{}", err);
+        let short = glyph_override(serde_json::json!({"kind": "replace", "width": 2, "height": 2, "rows": ["##"]}));
+        let err = short.apply(&mut metrics, &mut gray).unwrap_err();
+        assert!(err.contains("has 1 rows, expected 2"), "{}", err);
+        assert_eq!((metrics, gray), rasterized());
+    }
+}
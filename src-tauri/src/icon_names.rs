@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, HashMap};
+use ttf_parser::gsub::SubstitutionSubtable;
+use ttf_parser::{Face, GlyphId};
+
+/// An icon picked by name, with the codepoint the generated constant refers to.
+#[derive(Debug, Clone)]
+pub struct IconGlyph {
+    pub name: String,
+    pub constant: String,
+    pub codepoint: u32,
+}
+
+/// Maps icon names to codepoints via `post`/CFF glyph names, then GSUB ligatures that spell the
+/// name (Material Icons style). Names match case-insensitively with `-` and `_` interchangeable.
+pub fn resolve_icons(face: &Face, names: &[String]) -> (Vec<IconGlyph>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut by_name: HashMap<String, GlyphId> = HashMap::new();
+    for id in 0..face.number_of_glyphs() {
+        if let Some(name) = face.glyph_name(GlyphId(id)) {
+            by_name.entry(normalize(name)).or_insert(GlyphId(id));
+        }
+    }
+    let codepoints = glyph_codepoints(face);
+
+    let mut icons: Vec<IconGlyph> = Vec::new();
+    for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+        let glyph = by_name.get(&normalize(name)).copied().or_else(|| ligature_glyph(face, name));
+        let Some(glyph) = glyph else {
+            warnings.push(format!("Icon \"{}\" not found in font", name));
+            continue;
+        };
+        let Some(&codepoint) = codepoints.get(&glyph.0) else {
+            warnings.push(format!("Icon \"{}\" (glyph {}) has no Unicode codepoint", name, glyph.0));
+            continue;
+        };
+        let constant = constant_name(name);
+        if let Some(existing) = icons.iter().find(|icon| icon.constant == constant) {
+            warnings.push(format!(
+                "Icon \"{}\" duplicates constant {} of \"{}\", skipped",
+                name, constant, existing.name
+            ));
+            continue;
+        }
+        icons.push(IconGlyph {
+            name: name.to_string(),
+            constant,
+            codepoint,
+        });
+    }
+    (icons, warnings)
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace('-', "_")
+}
+
+/// `ICON_` plus the name in upper snake case, e.g. "battery-full" -> `ICON_BATTERY_FULL`.
+fn constant_name(name: &str) -> String {
+    let body: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("ICON_{}", body)
+}
+
+/// Lowest codepoint of every glyph reachable through a Unicode cmap subtable.
+fn glyph_codepoints(face: &Face) -> BTreeMap<u16, u32> {
+    let mut map: BTreeMap<u16, u32> = BTreeMap::new();
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|cp| {
+                if let Some(glyph) = subtable.glyph_index(cp).filter(|g| g.0 != 0) {
+                    let entry = map.entry(glyph.0).or_insert(cp);
+                    *entry = (*entry).min(cp);
+                }
+            });
+        }
+    }
+    map
+}
+
+/// Finds a ligature whose components spell `name` character by character.
+fn ligature_glyph(face: &Face, name: &str) -> Option<GlyphId> {
+    let spelled: Vec<GlyphId> = name.chars().map(|c| face.glyph_index(c)).collect::<Option<_>>()?;
+    let (first, rest) = spelled.split_first()?;
+    let gsub = face.tables().gsub?;
+    for lookup in gsub.lookups {
+        for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+            let SubstitutionSubtable::Ligature(ligatures) = subtable else {
+                continue;
+            };
+            let Some(set) = ligatures.coverage.get(*first).and_then(|i| ligatures.ligature_sets.get(i)) else {
+                continue;
+            };
+            let found = set
+                .into_iter()
+                .find(|lig| lig.components.len() as usize == rest.len() && lig.components.into_iter().eq(rest.iter().copied()));
+            if let Some(ligature) = found {
+                return Some(ligature.glyph);
+            }
+        }
+    }
+    None
+}
+
+/// Constants for a C++ module; macros don't cross module boundaries, so "define" becomes an
+/// exported `constexpr`.
+pub fn cpp_constants(icons: &[IconGlyph], style: &str, export_name: &str) -> String {
+    if icons.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("// Icon constants\n");
+    match style {
+        "utf8" => {
+            for icon in icons {
+                out.push_str(&format!(
+                    "export inline constexpr char {}[] = \"{}\";\n",
+                    icon.constant,
+                    utf8_escape(icon.codepoint)
+                ));
+            }
+        }
+        "define" => {
+            for icon in icons {
+                out.push_str(&format!(
+                    "export inline constexpr uint32_t {} = 0x{:04X};\n",
+                    icon.constant, icon.codepoint
+                ));
+            }
+        }
+        _ => {
+            out.push_str(&format!("export enum {}_icon : uint32_t {{\n", export_name));
+            for icon in icons {
+                out.push_str(&format!("    {} = 0x{:04X},\n", icon.constant, icon.codepoint));
+            }
+            out.push_str("};\n");
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// Constants for the plain C blob header.
+pub fn c_constants(icons: &[IconGlyph], style: &str) -> String {
+    if icons.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("/* Icon constants */\n");
+    match style {
+        "utf8" => {
+            for icon in icons {
+                out.push_str(&format!("#define {} \"{}\"\n", icon.constant, utf8_escape(icon.codepoint)));
+            }
+        }
+        "define" => {
+            for icon in icons {
+                out.push_str(&format!("#define {} 0x{:04X}u\n", icon.constant, icon.codepoint));
+            }
+        }
+        _ => {
+            out.push_str("enum {\n");
+            for icon in icons {
+                out.push_str(&format!("    {} = 0x{:04X},\n", icon.constant, icon.codepoint));
+            }
+            out.push_str("};\n");
+        }
+    }
+    out.push('\n');
+    out
+}
+
+fn utf8_escape(codepoint: u32) -> String {
+    let mut buf = [0u8; 4];
+    char::from_u32(codepoint)
+        .map(|c| c.encode_utf8(&mut buf).bytes().map(|b| format!("\\x{:02X}", b)).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    /// Glyphs "home" (U+E88A), "battery-full" (U+E1A4 and U+E1A5), "orphan" (unmapped), "a",
+    /// "b", and "uniE000" (U+E000), which GSUB ligates from "a" "b".
+    fn icon_font() -> Vec<u8> {
+        let names = ["home", "battery-full", "orphan", "a", "b", "uniE000"];
+        let count = names.len() as u16 + 1;
+
+        let mut head = words(&[1, 0, 1, 0, 0, 0, 0x5F0F, 0x3CF5, 0, 1000]);
+        head.extend([0; 16]);
+        head.extend(words(&[0, 0, 1000, 1000, 0, 8, 2, 0, 0]));
+        let mut hhea = words(&[1, 0, 800, (-200i16) as u16, 0, 1000, 0, 0, 1000, 1, 0, 0, 0, 0, 0, 0, 0]);
+        hhea.extend(words(&[count]));
+        let maxp = words(&[0, 0x5000, count]);
+
+        let mut post = words(&[2, 0]);
+        post.extend([0; 28]);
+        post.extend(words(&[count, 0]));
+        post.extend(words(&(258..258 + names.len() as u16).collect::<Vec<u16>>()));
+        for name in names {
+            post.push(name.len() as u8);
+            post.extend(name.bytes());
+        }
+
+        let groups = [(0x61, 4), (0x62, 5), (0xE000, 6), (0xE1A4, 2), (0xE1A5, 2), (0xE88A, 1)];
+        let mut cmap = words(&[0, 1, 3, 10, 0, 12, 12, 0]);
+        for value in [16 + 12 * groups.len() as u32, 0, groups.len() as u32] {
+            cmap.extend(value.to_be_bytes());
+        }
+        for (codepoint, glyph) in groups {
+            cmap.extend([codepoint, codepoint, glyph].iter().flat_map(|v: &u32| v.to_be_bytes()));
+        }
+
+        // Empty script and feature lists, then one lookup with one ligature subtable: "a" "b" -> 6.
+        let gsub = words(&[
+            1, 0, 10, 12, 14, // header
+            0, // script list
+            0, // feature list
+            1, 4, // lookup list
+            4, 0, 1, 8, // lookup: ligature substitution, one subtable
+            1, 8, 1, 14, // subtable: coverage, one ligature set
+            1, 1, 4, // coverage: glyph 4
+            1, 4, // ligature set
+            6, 2, 5, // ligature: glyph 6 from 4, 5
+        ]);
+
+        crate::woff::build_sfnt(
+            0x0001_0000,
+            vec![
+                (*b"head", head),
+                (*b"hhea", hhea),
+                (*b"maxp", maxp),
+                (*b"post", post),
+                (*b"cmap", cmap),
+                (*b"GSUB", gsub),
+            ],
+        )
+    }
+
+    fn resolve(names: &[&str]) -> (Vec<(String, String, u32)>, Vec<String>) {
+        let data = icon_font();
+        let face = Face::parse(&data, 0).unwrap();
+        let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+        let (icons, warnings) = resolve_icons(&face, &names);
+        let icons = icons.into_iter().map(|i| (i.name, i.constant, i.codepoint)).collect();
+        (icons, warnings)
+    }
+
+    #[test]
+    fn resolves_glyph_names_and_ligatures() {
+        let (icons, warnings) = resolve(&["home", " Battery_Full ", "", "ab"]);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(
+            icons,
+            [
+                ("home".to_string(), "ICON_HOME".to_string(), 0xE88A),
+                // The lowest of the glyph's codepoints wins.
+                ("Battery_Full".to_string(), "ICON_BATTERY_FULL".to_string(), 0xE1A4),
+                ("ab".to_string(), "ICON_AB".to_string(), 0xE000),
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_missing_unmapped_and_duplicate_icons() {
+        let (icons, warnings) = resolve(&["missing", "orphan", "battery-full", "battery_full", "ba"]);
+        assert_eq!(icons.len(), 1);
+        assert_eq!(
+            warnings,
+            [
+                "Icon \"missing\" not found in font",
+                "Icon \"orphan\" (glyph 3) has no Unicode codepoint",
+                "Icon \"battery_full\" duplicates constant ICON_BATTERY_FULL of \"battery-full\", skipped",
+                "Icon \"ba\" not found in font",
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_names_are_upper_snake_case() {
+        assert_eq!(constant_name("battery-full"), "ICON_BATTERY_FULL");
+        assert_eq!(constant_name("3d.rotation"), "ICON_3D_ROTATION");
+        assert_eq!(constant_name("Wi-Fi"), "ICON_WI_FI");
+        assert_eq!(normalize(" Wi-Fi "), "wi_fi");
+    }
+
+    fn icons() -> Vec<IconGlyph> {
+        [("home", 0xE88A), ("gear", 0x2699)]
+            .into_iter()
+            .map(|(name, codepoint)| IconGlyph {
+                name: name.to_string(),
+                constant: constant_name(name),
+                codepoint,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cpp_constants_in_each_style() {
+        assert_eq!(
+            cpp_constants(&icons(), "enum", "font"),
+            "// Icon constants\nexport enum font_icon : uint32_t {\n    ICON_HOME = 0xE88A,\n    ICON_GEAR = 0x2699,\n\
+             };\n\n"
+        );
+        assert_eq!(
+            cpp_constants(&icons(), "define", "font"),
+            "// Icon constants\nexport inline constexpr uint32_t ICON_HOME = 0xE88A;\n\
+             export inline constexpr uint32_t ICON_GEAR = 0x2699;\n\n"
+        );
+        assert_eq!(
+            cpp_constants(&icons(), "utf8", "font"),
+            "// Icon constants\nexport inline constexpr char ICON_HOME[] = \"\\xEE\\xA2\\x8A\";\n\
+             export inline constexpr char ICON_GEAR[] = \"\\xE2\\x9A\\x99\";\n\n"
+        );
+        assert_eq!(cpp_constants(&[], "enum", "font"), "");
+    }
+
+    #[test]
+    fn c_constants_in_each_style() {
+        assert_eq!(
+            c_constants(&icons(), "enum"),
+            "/* Icon constants */\nenum {\n    ICON_HOME = 0xE88A,\n    ICON_GEAR = 0x2699,\n};\n\n"
+        );
+        assert_eq!(
+            c_constants(&icons(), "define"),
+            "/* Icon constants */\n#define ICON_HOME 0xE88Au\n#define ICON_GEAR 0x2699u\n\n"
+        );
+        assert_eq!(
+            c_constants(&icons(), "utf8"),
+            "/* Icon constants */\n#define ICON_HOME \"\\xEE\\xA2\\x8A\"\n#define ICON_GEAR \"\\xE2\\x9A\\x99\"\n\n"
+        );
+        assert_eq!(c_constants(&[], "utf8"), "");
+    }
+
+    #[test]
+    fn utf8_escape_encodes_every_byte() {
+        assert_eq!(utf8_escape(0x41), "\\x41");
+        assert_eq!(utf8_escape(0xE9), "\\xC3\\xA9");
+        assert_eq!(utf8_escape(0x1F600), "\\xF0\\x9F\\x98\\x80");
+        assert_eq!(utf8_escape(0xD800), "");
+    }
+}
@@ -0,0 +1,93 @@
+use encoding_rs::{Encoding, BIG5, GBK, SHIFT_JIS, WINDOWS_1251};
+
+/// A pre-Unicode character set the exported ranges and lookups can be keyed by. Codes are the
+/// encoded bytes read big-endian: one byte for ASCII and single-byte sets, lead byte in the
+/// high half for double-byte characters (e.g. GBK "中" is 0xD6D0).
+pub struct LegacyEncoding {
+    pub label: &'static str,
+    encoding: &'static Encoding,
+}
+
+impl LegacyEncoding {
+    /// Resolves the `output_encoding` option; "unicode" keeps codepoint keys and yields `None`.
+    pub fn from_option(name: &str) -> Result<Option<Self>, String> {
+        let (label, encoding) = match name {
+            "unicode" => return Ok(None),
+            "gbk" => ("GBK", GBK),
+            "shift_jis" => ("Shift-JIS", SHIFT_JIS),
+            "big5" => ("Big5", BIG5),
+            "cp1251" => ("CP1251", WINDOWS_1251),
+            other => return Err(format!("Unknown output encoding: {}", other)),
+        };
+        Ok(Some(Self { label, encoding }))
+    }
+
+    /// Legacy code of a Unicode codepoint, or `None` when the set has no mapping for it.
+    pub fn code(&self, codepoint: u32) -> Option<u32> {
+        let ch = char::from_u32(codepoint)?;
+        let mut buf = [0u8; 4];
+        let (bytes, _, unmappable) = self.encoding.encode(ch.encode_utf8(&mut buf));
+        match bytes.as_ref() {
+            _ if unmappable => None,
+            [byte] => Some(*byte as u32),
+            [lead, trail] => Some((*lead as u32) << 8 | *trail as u32),
+            _ => None,
+        }
+    }
+
+    /// Unicode codepoint a legacy code decodes to.
+    pub fn decode(&self, code: u32) -> Option<u32> {
+        let bytes = if code > 0xFF {
+            vec![(code >> 8) as u8, code as u8]
+        } else {
+            vec![code as u8]
+        };
+        let text = self.encoding.decode_without_bom_handling_and_without_replacement(&bytes)?;
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Some(ch as u32),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip_through_decode() {
+        let cases: [(&str, &[(char, u32)]); 4] = [
+            ("gbk", &[('A', 0x41), ('中', 0xD6D0), ('文', 0xCEC4)]),
+            ("shift_jis", &[('A', 0x41), ('あ', 0x82A0), ('｡', 0xA1), ('中', 0x9286)]),
+            ("big5", &[('A', 0x41), ('中', 0xA4A4)]),
+            ("cp1251", &[('A', 0x41), ('Ж', 0xC6), ('я', 0xFF)]),
+        ];
+        for (name, chars) in cases {
+            let encoding = LegacyEncoding::from_option(name).unwrap().unwrap();
+            for (ch, code) in chars {
+                assert_eq!(encoding.code(*ch as u32), Some(*code), "{} {:?}", name, ch);
+                assert_eq!(encoding.decode(*code), Some(*ch as u32), "{} 0x{:X}", name, code);
+            }
+        }
+    }
+
+    #[test]
+    fn unmappable_characters_and_codes_have_no_counterpart() {
+        let cp1251 = LegacyEncoding::from_option("cp1251").unwrap().unwrap();
+        assert_eq!(cp1251.code('中' as u32), None);
+        assert_eq!(cp1251.code(0xD800), None);
+        let shift_jis = LegacyEncoding::from_option("shift_jis").unwrap().unwrap();
+        // A lone lead byte is not a character.
+        assert_eq!(shift_jis.decode(0x82), None);
+        // The yen sign shares 0x5C with the backslash, which is what the code decodes to.
+        assert_eq!(shift_jis.code(0xA5), Some(0x5C));
+        assert_eq!(shift_jis.decode(0x5C), Some(0x5C));
+    }
+
+    #[test]
+    fn unicode_and_unknown_options() {
+        assert!(LegacyEncoding::from_option("unicode").unwrap().is_none());
+        assert_eq!(LegacyEncoding::from_option("koi8").err().unwrap(), "Unknown output encoding: koi8");
+    }
+}
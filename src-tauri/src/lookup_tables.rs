@@ -0,0 +1,541 @@
+use serde::Serialize;
+
+use crate::storage_attrs::StorageAttrs;
+
+// Assumed runtime size of a `GlyphRange { uint32_t start, length, glyph_id_start; }`.
+const GLYPH_RANGE_BYTES: usize = 12;
+const PAGE_BITS: u32 = 8;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+const AUTO_MAX_PROBES: u32 = 4;
+const HASH_MAX_DISPLACEMENT: u32 = 1 << 20;
+const VALUES_PER_LINE: usize = 16;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LookupStats {
+    strategy: String,
+    table_bytes: u32,
+    worst_case_probes: u32,
+}
+
+enum Tables {
+    Ranges { count: usize },
+    Sorted { codepoints: Vec<u32> },
+    PageTable { pages: Vec<Option<usize>>, page_glyphs: Vec<Vec<Option<u32>>> },
+    PerfectHash { displacements: Vec<u32>, keys: Vec<Option<u32>>, glyphs: Vec<u32> },
+}
+
+pub struct LookupTable {
+    pub stats: LookupStats,
+    tables: Tables,
+    fallback: Option<usize>,
+}
+
+/// Builds the codepoint -> glyph index structure for `strategy`.
+///
+/// `codepoints` is the sorted glyph table order (glyph id = position) and `range_count` the number
+/// of entries in `glyph_ranges`. `merged_ranges` expects gaps to be filled in before this call.
+pub fn build_lookup(
+    strategy: &str,
+    codepoints: &[u32],
+    range_count: usize,
+    fallback: Option<usize>,
+) -> Result<LookupTable, String> {
+    let tables = match strategy {
+        "ranges" | "merged_ranges" => Tables::Ranges { count: range_count },
+        "sorted_array" => Tables::Sorted { codepoints: codepoints.to_vec() },
+        "page_table" => build_page_table(codepoints),
+        "perfect_hash" => build_perfect_hash(codepoints)?,
+        "auto" => {
+            let mut candidates = vec![
+                ("ranges", Tables::Ranges { count: range_count }),
+                ("sorted_array", Tables::Sorted { codepoints: codepoints.to_vec() }),
+                ("page_table", build_page_table(codepoints)),
+            ];
+            if let Ok(hash) = build_perfect_hash(codepoints) {
+                candidates.push(("perfect_hash", hash));
+            }
+            let (name, tables) = candidates
+                .into_iter()
+                .min_by_key(|(_, t)| {
+                    // Smallest table within the probe budget, otherwise the fewest probes.
+                    let probes = t.worst_case_probes();
+                    if probes <= AUTO_MAX_PROBES {
+                        (0, t.table_bytes(codepoints.len()))
+                    } else {
+                        (1, probes as usize)
+                    }
+                })
+                .expect("auto lookup has candidates");
+            return Ok(LookupTable::new(name, tables, codepoints.len(), fallback));
+        }
+        other => return Err(format!("Unknown lookup strategy: {}", other)),
+    };
+    Ok(LookupTable::new(strategy, tables, codepoints.len(), fallback))
+}
+
+impl Tables {
+    fn worst_case_probes(&self) -> u32 {
+        match self {
+            Tables::Ranges { count } => binary_search_probes(*count),
+            Tables::Sorted { codepoints } => binary_search_probes(codepoints.len()),
+            Tables::PageTable { .. } => 2,
+            Tables::PerfectHash { .. } => 1,
+        }
+    }
+
+    fn table_bytes(&self, glyph_count: usize) -> usize {
+        let id_width = index_width(glyph_count as u64 + 1);
+        match self {
+            Tables::Ranges { count } => count * GLYPH_RANGE_BYTES,
+            Tables::Sorted { codepoints } => {
+                codepoints.len() * index_width(codepoints.last().copied().unwrap_or(0) as u64)
+            }
+            Tables::PageTable { pages, page_glyphs } => {
+                pages.len() * index_width(page_glyphs.len() as u64 + 1)
+                    + page_glyphs.len() * PAGE_SIZE * id_width
+            }
+            Tables::PerfectHash { displacements, keys, .. } => {
+                let max_disp = displacements.iter().copied().max().unwrap_or(0);
+                displacements.len() * index_width(max_disp as u64) + keys.len() * (4 + id_width)
+            }
+        }
+    }
+}
+
+impl LookupTable {
+    fn new(strategy: &str, tables: Tables, glyph_count: usize, fallback: Option<usize>) -> Self {
+        Self {
+            stats: LookupStats {
+                strategy: strategy.to_string(),
+                table_bytes: tables.table_bytes(glyph_count) as u32,
+                worst_case_probes: tables.worst_case_probes(),
+            },
+            tables,
+            fallback,
+        }
+    }
+
+    /// Emits the lookup tables and `<export_name>_glyph_index(codepoint)`, which returns the glyph
+    /// table index, the fallback index, or -1.
+    pub fn emit(&self, export_name: &str, storage: &StorageAttrs) -> String {
+        let miss = self.fallback.map(|i| i as i64).unwrap_or(-1);
+        let arrays = self.arrays();
+        let ty = |name: &str| arrays.iter().find(|a| a.name == name).map(|a| a.ty).unwrap_or("uint32_t");
+        let mut out = format!(
+            "// Codepoint lookup: {}, {} bytes, worst case {} probes\n",
+            self.stats.strategy, self.stats.table_bytes, self.stats.worst_case_probes
+        );
+        if let Tables::PerfectHash { .. } = &self.tables {
+            out.push_str("constexpr uint32_t lookup_hash_mix(uint32_t x, uint32_t seed) {\n");
+            out.push_str("    x ^= seed;\n");
+            out.push_str("    x ^= x >> 16;\n");
+            out.push_str("    x *= 0x7FEB352Du;\n");
+            out.push_str("    x ^= x >> 15;\n");
+            out.push_str("    x *= 0x846CA68Bu;\n");
+            out.push_str("    x ^= x >> 16;\n");
+            out.push_str("    return x;\n");
+            out.push_str("}\n\n");
+        }
+        for array in &arrays {
+            array.declare(&mut out, storage);
+        }
+        out.push_str(&format!(
+            "export {} int32_t {}_glyph_index(uint32_t codepoint) {{\n",
+            storage.function_specifier(),
+            export_name
+        ));
+        match &self.tables {
+            Tables::Ranges { count } => {
+                out.push_str(&format!("    uint32_t lo = 0, hi = {};\n", count));
+                out.push_str("    while (lo < hi) {\n");
+                out.push_str("        uint32_t mid = (lo + hi) / 2;\n");
+                out.push_str(&format!("        {}\n", storage.load_struct("GlyphRange", "r", "glyph_ranges[mid]")));
+                out.push_str("        if (codepoint < r.start) hi = mid;\n");
+                out.push_str("        else if (codepoint - r.start >= r.length) lo = mid + 1;\n");
+                out.push_str("        else return (int32_t)(r.glyph_id_start + (codepoint - r.start));\n");
+                out.push_str("    }\n");
+            }
+            Tables::Sorted { codepoints } => {
+                let ty = ty("lookup_codepoints");
+                out.push_str(&format!("    uint32_t lo = 0, hi = {};\n", codepoints.len()));
+                out.push_str("    while (lo < hi) {\n");
+                out.push_str("        uint32_t mid = (lo + hi) / 2;\n");
+                out.push_str(&format!("        {} key = {};\n", ty, storage.load(ty, "lookup_codepoints[mid]")));
+                out.push_str("        if (codepoint < key) hi = mid;\n");
+                out.push_str("        else if (codepoint > key) lo = mid + 1;\n");
+                out.push_str("        else return (int32_t)mid;\n");
+                out.push_str("    }\n");
+            }
+            Tables::PageTable { pages, page_glyphs } => {
+                let (slot_empty, id_empty) = page_markers(page_glyphs);
+                let (slot_ty, id_ty) = (ty("lookup_pages"), ty("lookup_page_glyphs"));
+                out.push_str(&format!("    uint32_t page = codepoint >> {};\n", PAGE_BITS));
+                out.push_str(&format!("    if (page < {}) {{\n", pages.len()));
+                out.push_str(&format!("        {} slot = {};\n", slot_ty, storage.load(slot_ty, "lookup_pages[page]")));
+                out.push_str(&format!("        if (slot != {}) {{\n", slot_empty));
+                let id = format!("lookup_page_glyphs[slot][codepoint & 0x{:X}]", PAGE_SIZE - 1);
+                out.push_str(&format!("            {} id = {};\n", id_ty, storage.load(id_ty, &id)));
+                out.push_str(&format!("            if (id != {}) return (int32_t)id;\n", id_empty));
+                out.push_str("        }\n");
+                out.push_str("    }\n");
+            }
+            Tables::PerfectHash { displacements, keys, .. } => {
+                out.push_str(&format!(
+                    "    uint32_t bucket = lookup_hash_mix(codepoint, 0) % {};\n",
+                    displacements.len()
+                ));
+                out.push_str(&format!(
+                    "    uint32_t slot = lookup_hash_mix(codepoint, {}) % {};\n",
+                    storage.load(ty("lookup_hash_disp"), "lookup_hash_disp[bucket]"),
+                    keys.len()
+                ));
+                out.push_str(&format!(
+                    "    if ({} == codepoint) return (int32_t){};\n",
+                    storage.load("uint32_t", "lookup_hash_keys[slot]"),
+                    storage.load(ty("lookup_hash_glyphs"), "lookup_hash_glyphs[slot]")
+                ));
+            }
+        }
+        out.push_str(&format!("    return {};\n", miss));
+        out.push_str("}\n\n");
+        out
+    }
+
+    /// Every value of the emitted lookup arrays in declaration order, for the module CRC.
+    pub fn crc_words(&self) -> Vec<u32> {
+        self.arrays().iter().flat_map(|a| a.values.iter().map(|v| *v as u32)).collect()
+    }
+
+    /// `_verify()` statements that feed the lookup arrays to `crc32_word` in `crc_words` order.
+    pub fn emit_crc(&self, storage: &StorageAttrs) -> String {
+        let mut out = String::new();
+        for array in self.arrays() {
+            let place = match array.row {
+                Some(row) => format!("{}[i / {}][i % {}]", array.name, row, row),
+                None => format!("{}[i]", array.name),
+            };
+            out.push_str(&format!(
+                "    for (uint32_t i = 0; i < {}; ++i) crc = crc32_word(crc, {});\n",
+                array.values.len(),
+                storage.load(array.ty, &place)
+            ));
+        }
+        out
+    }
+
+    fn arrays(&self) -> Vec<LookupArray> {
+        match &self.tables {
+            Tables::Ranges { .. } => Vec::new(),
+            Tables::Sorted { codepoints } => vec![LookupArray {
+                name: "lookup_codepoints",
+                ty: c_uint(codepoints.last().copied().unwrap_or(0) as u64),
+                values: codepoints.iter().map(|v| *v as u64).collect(),
+                row: None,
+            }],
+            Tables::PageTable { pages, page_glyphs } => {
+                let (slot_empty, id_empty) = page_markers(page_glyphs);
+                vec![
+                    LookupArray {
+                        name: "lookup_pages",
+                        ty: c_uint(slot_empty),
+                        values: pages.iter().map(|p| p.map(|s| s as u64).unwrap_or(slot_empty)).collect(),
+                        row: None,
+                    },
+                    LookupArray {
+                        name: "lookup_page_glyphs",
+                        ty: c_uint(id_empty),
+                        values: page_glyphs.iter().flatten().map(|g| g.map(|v| v as u64).unwrap_or(id_empty)).collect(),
+                        row: Some(PAGE_SIZE),
+                    },
+                ]
+            }
+            Tables::PerfectHash { displacements, keys, glyphs } => vec![
+                LookupArray {
+                    name: "lookup_hash_disp",
+                    ty: c_uint(displacements.iter().copied().max().unwrap_or(0) as u64),
+                    values: displacements.iter().map(|v| *v as u64).collect(),
+                    row: None,
+                },
+                LookupArray {
+                    name: "lookup_hash_keys",
+                    ty: "uint32_t",
+                    values: keys.iter().map(|k| k.map(|v| v as u64).unwrap_or(u32::MAX as u64)).collect(),
+                    row: None,
+                },
+                LookupArray {
+                    name: "lookup_hash_glyphs",
+                    ty: c_uint(glyphs.iter().copied().max().unwrap_or(0) as u64),
+                    values: glyphs.iter().map(|v| *v as u64).collect(),
+                    row: None,
+                },
+            ],
+        }
+    }
+}
+
+/// One emitted lookup array; `row` splits it into rows of that many values.
+struct LookupArray {
+    name: &'static str,
+    ty: &'static str,
+    values: Vec<u64>,
+    row: Option<usize>,
+}
+
+impl LookupArray {
+    fn declare(&self, out: &mut String, storage: &StorageAttrs) {
+        match self.row {
+            Some(row) => {
+                out.push_str(&storage.array_decl(self.ty, self.name, &format!("[{}]", row)));
+                for chunk in self.values.chunks(row) {
+                    out.push_str("    {\n");
+                    push_values(out, "        ", chunk.iter().copied());
+                    out.push_str("    },\n");
+                }
+            }
+            None => {
+                out.push_str(&storage.array_decl(self.ty, self.name, ""));
+                push_values(out, "    ", self.values.iter().copied());
+            }
+        }
+        out.push_str("};\n\n");
+    }
+}
+
+/// Empty markers of the page slot and page glyph arrays.
+fn page_markers(page_glyphs: &[Vec<Option<u32>>]) -> (u64, u64) {
+    let slot_empty = empty_marker(page_glyphs.len() as u64 + 1);
+    let id_empty = empty_marker(page_glyphs.iter().flatten().flatten().max().copied().unwrap_or(0) as u64 + 1);
+    (slot_empty, id_empty)
+}
+
+fn build_page_table(codepoints: &[u32]) -> Tables {
+    let page_count = codepoints.last().map(|cp| (cp >> PAGE_BITS) as usize + 1).unwrap_or(0);
+    let mut pages: Vec<Option<usize>> = vec![None; page_count];
+    let mut page_glyphs: Vec<Vec<Option<u32>>> = Vec::new();
+    for (id, cp) in codepoints.iter().enumerate() {
+        let page = (cp >> PAGE_BITS) as usize;
+        let slot = *pages[page].get_or_insert_with(|| {
+            page_glyphs.push(vec![None; PAGE_SIZE]);
+            page_glyphs.len() - 1
+        });
+        page_glyphs[slot][*cp as usize & (PAGE_SIZE - 1)] = Some(id as u32);
+    }
+    Tables::PageTable { pages, page_glyphs }
+}
+
+/// Hash-and-displace perfect hash: every bucket gets a seed that drops its keys into free slots.
+fn build_perfect_hash(codepoints: &[u32]) -> Result<Tables, String> {
+    let n = codepoints.len();
+    let bucket_count = (n / 4).max(1);
+    let slot_count = (n + n / 4).max(1);
+    let mut buckets: Vec<Vec<(u32, u32)>> = vec![Vec::new(); bucket_count];
+    for (id, cp) in codepoints.iter().enumerate() {
+        buckets[hash_mix(*cp, 0) as usize % bucket_count].push((*cp, id as u32));
+    }
+    let mut order: Vec<usize> = (0..bucket_count).collect();
+    order.sort_by_key(|b| std::cmp::Reverse(buckets[*b].len()));
+
+    let mut displacements = vec![0u32; bucket_count];
+    let mut keys: Vec<Option<u32>> = vec![None; slot_count];
+    let mut glyphs = vec![0u32; slot_count];
+    for bucket in order {
+        let entries = &buckets[bucket];
+        if entries.is_empty() {
+            continue;
+        }
+        let mut placed = false;
+        for seed in 1..HASH_MAX_DISPLACEMENT {
+            let slots: Vec<usize> = entries
+                .iter()
+                .map(|(cp, _)| hash_mix(*cp, seed) as usize % slot_count)
+                .collect();
+            let free = slots.iter().enumerate().all(|(i, s)| keys[*s].is_none() && !slots[..i].contains(s));
+            if free {
+                for ((cp, id), slot) in entries.iter().zip(slots) {
+                    keys[slot] = Some(*cp);
+                    glyphs[slot] = *id;
+                }
+                displacements[bucket] = seed;
+                placed = true;
+                break;
+            }
+        }
+        if !placed {
+            return Err(format!("Failed to build perfect hash for {} codepoints", n));
+        }
+    }
+    Ok(Tables::PerfectHash { displacements, keys, glyphs })
+}
+
+fn hash_mix(mut x: u32, seed: u32) -> u32 {
+    x ^= seed;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    x
+}
+
+fn binary_search_probes(count: usize) -> u32 {
+    usize::BITS - count.leading_zeros()
+}
+
+fn index_width(max_value: u64) -> usize {
+    if max_value <= u8::MAX as u64 {
+        1
+    } else if max_value <= u16::MAX as u64 {
+        2
+    } else {
+        4
+    }
+}
+
+fn c_uint(max_value: u64) -> &'static str {
+    match index_width(max_value) {
+        1 => "uint8_t",
+        2 => "uint16_t",
+        _ => "uint32_t",
+    }
+}
+
+/// Largest value of the narrowest type able to hold `count` distinct values plus the marker.
+fn empty_marker(count: u64) -> u64 {
+    match index_width(count) {
+        1 => u8::MAX as u64,
+        2 => u16::MAX as u64,
+        _ => u32::MAX as u64,
+    }
+}
+
+fn push_values(out: &mut String, indent: &str, values: impl Iterator<Item = u64>) {
+    let values: Vec<String> = values.map(|v| v.to_string()).collect();
+    for chunk in values.chunks(VALUES_PER_LINE) {
+        out.push_str(&format!("{}{},\n", indent, chunk.join(", ")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates the lookup the way the emitted `_glyph_index` does; `ranges` stands in for
+    /// `glyph_ranges` as `[start, length, glyph_id_start]`.
+    fn glyph_index(table: &LookupTable, ranges: &[[u32; 3]], codepoint: u32) -> i64 {
+        let found = match &table.tables {
+            Tables::Ranges { count } => {
+                let (mut lo, mut hi) = (0, *count);
+                let mut found = None;
+                while lo < hi {
+                    let mid = (lo + hi) / 2;
+                    let [start, length, glyph_id_start] = ranges[mid];
+                    if codepoint < start {
+                        hi = mid;
+                    } else if codepoint - start >= length {
+                        lo = mid + 1;
+                    } else {
+                        found = Some(glyph_id_start + (codepoint - start));
+                        break;
+                    }
+                }
+                found
+            }
+            Tables::Sorted { codepoints } => codepoints.binary_search(&codepoint).ok().map(|i| i as u32),
+            Tables::PageTable { pages, page_glyphs } => pages
+                .get((codepoint >> PAGE_BITS) as usize)
+                .copied()
+                .flatten()
+                .and_then(|slot| page_glyphs[slot][codepoint as usize & (PAGE_SIZE - 1)]),
+            Tables::PerfectHash { displacements, keys, glyphs } => {
+                let bucket = hash_mix(codepoint, 0) as usize % displacements.len();
+                let slot = hash_mix(codepoint, displacements[bucket]) as usize % keys.len();
+                (keys[slot] == Some(codepoint)).then_some(glyphs[slot])
+            }
+        };
+        found.map(|id| id as i64).unwrap_or(table.fallback.map(|i| i as i64).unwrap_or(-1))
+    }
+
+    fn ranges(codepoints: &[u32]) -> Vec<[u32; 3]> {
+        let mut ranges: Vec<[u32; 3]> = Vec::new();
+        for (id, cp) in codepoints.iter().enumerate() {
+            match ranges.last_mut() {
+                Some(r) if r[0] + r[1] == *cp => r[1] += 1,
+                _ => ranges.push([*cp, 1, id as u32]),
+            }
+        }
+        ranges
+    }
+
+    /// ASCII, a few Latin-1 letters, scattered CJK and one astral character.
+    fn sparse_codepoints() -> Vec<u32> {
+        let mut codepoints: Vec<u32> = (0x20..0x7F).collect();
+        codepoints.extend([0xA9, 0xE9, 0x4E00, 0x4E2D, 0x56FD, 0x6587, 0x8A9E, 0x9F8D, 0x1F600]);
+        codepoints
+    }
+
+    const STRATEGIES: [&str; 6] = ["ranges", "merged_ranges", "sorted_array", "page_table", "perfect_hash", "auto"];
+
+    #[test]
+    fn every_strategy_round_trips() {
+        let codepoints = sparse_codepoints();
+        let ranges = ranges(&codepoints);
+        for strategy in STRATEGIES {
+            let table = build_lookup(strategy, &codepoints, ranges.len(), None).unwrap();
+            for (id, cp) in codepoints.iter().enumerate() {
+                assert_eq!(glyph_index(&table, &ranges, *cp), id as i64, "{} U+{:04X}", strategy, cp);
+            }
+            for missing in [0x00, 0x1F, 0x7F, 0xAA, 0x4E01, 0x1F5FF, 0x1F601, 0x10FFFF] {
+                assert_eq!(glyph_index(&table, &ranges, missing), -1, "{} U+{:04X}", strategy, missing);
+            }
+
+            let fallback = codepoints.len();
+            let table = build_lookup(strategy, &codepoints, ranges.len(), Some(fallback)).unwrap();
+            assert_eq!(glyph_index(&table, &ranges, 0x4E01), fallback as i64, "{}", strategy);
+            assert!(table.emit("font", &StorageAttrs::from_options("none", "", 0, "").unwrap())
+                .contains(&format!("    return {};\n}}\n", fallback)));
+        }
+    }
+
+    #[test]
+    fn perfect_hash_resolves_bucket_collisions() {
+        let codepoints: Vec<u32> = (0..64).map(|i| 0x4E00 + i * 37).collect();
+        let table = build_lookup("perfect_hash", &codepoints, 0, None).unwrap();
+        let Tables::PerfectHash { displacements, keys, .. } = &table.tables else {
+            panic!("expected a perfect hash");
+        };
+        let bucket = |cp: u32| hash_mix(cp, 0) as usize % displacements.len();
+        let colliding = codepoints
+            .iter()
+            .filter(|a| codepoints.iter().any(|b| *a != b && bucket(**a) == bucket(*b)))
+            .count();
+        assert!(colliding > 1, "64 keys in {} buckets should share buckets", displacements.len());
+        for (id, cp) in codepoints.iter().enumerate() {
+            assert_eq!(glyph_index(&table, &[], *cp), id as i64, "U+{:04X}", cp);
+        }
+
+        // A codepoint outside the set that lands on an occupied slot is told apart by its key.
+        let slot = |cp: u32| hash_mix(cp, displacements[bucket(cp)]) as usize % keys.len();
+        let impostor = (0x4E00..0x5000)
+            .filter(|cp| !codepoints.contains(cp))
+            .find(|cp| keys[slot(*cp)].is_some())
+            .unwrap();
+        assert_eq!(glyph_index(&table, &[], impostor), -1);
+    }
+
+    #[test]
+    fn page_table_marker_never_equals_a_glyph_id() {
+        // 256 glyphs use ids 0..=255, so the empty marker must move to 16 bits.
+        let codepoints: Vec<u32> = (0..256).map(|i| i * 2).collect();
+        let table = build_lookup("page_table", &codepoints, 0, None).unwrap();
+        let emitted = table.emit("font", &StorageAttrs::from_options("none", "", 0, "").unwrap());
+        assert!(emitted.contains("inline constexpr uint16_t lookup_page_glyphs[][256] = {\n"));
+        assert!(emitted.contains("            if (id != 65535) return (int32_t)id;\n"));
+        assert_eq!(glyph_index(&table, &[], 510), 255);
+        assert_eq!(glyph_index(&table, &[], 511), -1);
+    }
+
+    #[test]
+    fn unknown_strategy_is_rejected() {
+        assert!(build_lookup("trie", &[0x41], 1, None).is_err());
+    }
+}
@@ -1,10 +1,32 @@
 // src-tauri/src/main.rs
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod bdf_export;
+mod bitmap_fonts;
+mod bitmap_strikes;
+mod custom_glyphs;
+mod font_blob;
+mod font_inspect;
+mod font_license;
 mod font_pipeline;
+mod framebuffer;
 mod fs_utils;
+mod glyph_layout;
+mod glyph_overrides;
+mod icon_names;
+mod legacy_encodings;
+mod lookup_tables;
+mod pixel_grid;
+mod png_io;
+mod provenance;
 mod settings;
+mod sprite_sheet;
+mod storage_attrs;
+mod string_tables;
 mod system_fonts;
+mod text_render;
+mod unicode_blocks;
+mod woff;
 
 fn main() {
     tauri::Builder::default()
@@ -15,7 +37,11 @@ fn main() {
             settings::load_settings,
             font_pipeline::generate_font,
             font_pipeline::export_font,
-            system_fonts::list_system_fonts
+            font_pipeline::render_sample,
+            font_pipeline::emulate_display,
+            font_inspect::inspect_font,
+            system_fonts::list_system_fonts,
+            system_fonts::find_fonts_covering
         ])
         .run(tauri::generate_context!())
         .expect("Failed to run Tauri application");
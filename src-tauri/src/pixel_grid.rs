@@ -0,0 +1,221 @@
+use serde::Serialize;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+const MAX_SAMPLE_GLYPHS: usize = 256;
+const MIN_SAMPLE_POINTS: usize = 16;
+const MAX_DESIGN_PX: f32 = 128.0;
+const SUGGESTED_MULTIPLES: u32 = 8;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PixelGrid {
+    pub units_per_em: u16,
+    pub grid_units: u32,
+    pub design_px: f32,
+    pub suggested_sizes: Vec<u32>,
+}
+
+impl PixelGrid {
+    /// True when `size_px` maps every design pixel onto a whole number of output pixels.
+    pub fn is_clean_size(&self, size_px: u32) -> bool {
+        let scale = size_px as f64 * self.grid_units as f64 / self.units_per_em as f64;
+        scale >= 1.0 && (scale - scale.round()).abs() < 1e-6
+    }
+
+    /// Picks the clean size closest to `size_px`, preferring the smaller one on ties.
+    pub fn snap_size(&self, size_px: u32) -> Option<u32> {
+        self.suggested_sizes
+            .iter()
+            .copied()
+            .min_by_key(|s| (s.abs_diff(size_px), *s))
+    }
+}
+
+#[derive(Default)]
+struct PointCollector {
+    coords: Vec<f32>,
+    has_curves: bool,
+}
+
+impl OutlineBuilder for PointCollector {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.coords.extend([x, y]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.coords.extend([x, y]);
+    }
+
+    fn quad_to(&mut self, _x1: f32, _y1: f32, x: f32, y: f32) {
+        self.has_curves = true;
+        self.coords.extend([x, y]);
+    }
+
+    fn curve_to(&mut self, _x1: f32, _y1: f32, _x2: f32, _y2: f32, x: f32, y: f32) {
+        self.has_curves = true;
+        self.coords.extend([x, y]);
+    }
+
+    fn close(&mut self) {}
+}
+
+impl PointCollector {
+    /// The design pixel the collected points share, if they are straight, plentiful and on a grid
+    /// coarser than one font unit.
+    fn grid(&self, units_per_em: u16) -> Option<PixelGrid> {
+        if self.has_curves || self.coords.len() < MIN_SAMPLE_POINTS * 2 {
+            return None;
+        }
+
+        let mut grid_units: u32 = 0;
+        for c in &self.coords {
+            if c.fract() != 0.0 {
+                return None;
+            }
+            grid_units = gcd(grid_units, c.abs() as u32);
+        }
+        if grid_units <= 1 {
+            return None;
+        }
+        let design_px = units_per_em as f32 / grid_units as f32;
+        if design_px > MAX_DESIGN_PX {
+            return None;
+        }
+
+        let mut grid = PixelGrid {
+            units_per_em,
+            grid_units,
+            design_px,
+            suggested_sizes: Vec::new(),
+        };
+        grid.suggested_sizes = (1..=SUGGESTED_MULTIPLES)
+            .filter_map(|n| {
+                let size = (n as f32 * design_px).round() as u32;
+                grid.is_clean_size(size).then_some(size)
+            })
+            .collect();
+        Some(grid)
+    }
+}
+
+/// Detects fonts drawn on a unit grid (pixel-art TTFs) from their outline coordinates.
+///
+/// Every sampled outline must consist of straight segments whose points share a common divisor
+/// larger than one font unit; that divisor is the size of one design pixel.
+pub fn detect_pixel_grid(face: &Face, glyph_indices: impl IntoIterator<Item = u16>) -> Option<PixelGrid> {
+    let mut collector = PointCollector::default();
+    for glyph_index in glyph_indices.into_iter().take(MAX_SAMPLE_GLYPHS) {
+        face.outline_glyph(GlyphId(glyph_index), &mut collector);
+        if collector.has_curves {
+            return None;
+        }
+    }
+    collector.grid(face.units_per_em())
+}
+
+/// Hard-thresholds coverage at 50% so grid-aligned edges never produce partial pixels.
+pub fn snap_coverage(gray: &mut [u8]) {
+    for v in gray.iter_mut() {
+        *v = if *v >= 128 { 255 } else { 0 };
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects closed outlines given as corner points, the way `outline_glyph` reports them.
+    fn outlines(contours: &[&[(f32, f32)]]) -> PointCollector {
+        let mut collector = PointCollector::default();
+        for contour in contours {
+            collector.move_to(contour[0].0, contour[0].1);
+            for (x, y) in &contour[1..] {
+                collector.line_to(*x, *y);
+            }
+            collector.close();
+        }
+        collector
+    }
+
+    /// Four 4-point squares of side `unit`, stepping `unit` apart: 16 points on a `unit` grid.
+    fn squares(unit: f32) -> PointCollector {
+        let square = |i: f32| [(i * unit, 0.0), (i * unit, unit), ((i + 1.0) * unit, unit), ((i + 1.0) * unit, 0.0)];
+        let contours: Vec<[(f32, f32); 4]> = (0..4).map(|i| square(i as f32 * 2.0)).collect();
+        outlines(&contours.iter().map(|c| &c[..]).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn detects_the_common_divisor_of_straight_outlines() {
+        let grid = squares(64.0).grid(1024).unwrap();
+        assert_eq!((grid.grid_units, grid.design_px), (64, 16.0));
+        assert_eq!(grid.suggested_sizes, [16, 32, 48, 64, 80, 96, 112, 128]);
+
+        // Negative coordinates count by their distance from the origin.
+        let mut collector = squares(64.0);
+        collector.line_to(-192.0, -128.0);
+        assert_eq!(collector.grid(1024).unwrap().grid_units, 64);
+    }
+
+    #[test]
+    fn rejects_curves_stray_points_and_sparse_samples() {
+        let mut curved = squares(64.0);
+        curved.quad_to(32.0, 32.0, 64.0, 64.0);
+        assert!(curved.grid(1024).is_none());
+
+        let mut off_grid = squares(64.0);
+        off_grid.line_to(65.0, 0.0);
+        assert!(off_grid.grid(1024).is_none());
+
+        let mut fractional = squares(64.0);
+        fractional.line_to(64.5, 0.0);
+        assert!(fractional.grid(1024).is_none());
+
+        let mut sparse = squares(64.0);
+        sparse.coords.truncate((MIN_SAMPLE_POINTS - 1) * 2);
+        assert!(sparse.grid(1024).is_none());
+        assert!(squares(1.0).grid(1024).is_none());
+    }
+
+    #[test]
+    fn rejects_grids_finer_than_max_design_px() {
+        // 2048 / 16 = 128 design pixels is the limit; 2048 / 8 = 256 is an outline font.
+        assert_eq!(squares(16.0).grid(2048).unwrap().design_px, MAX_DESIGN_PX);
+        assert!(squares(8.0).grid(2048).is_none());
+    }
+
+    #[test]
+    fn clean_sizes_map_design_pixels_to_whole_pixels() {
+        let grid = squares(64.0).grid(1024).unwrap();
+        assert!(grid.is_clean_size(16) && grid.is_clean_size(48));
+        assert!(!grid.is_clean_size(24));
+        // Below one output pixel per design pixel nothing is clean.
+        assert!(!grid.is_clean_size(8));
+
+        assert_eq!(grid.snap_size(20), Some(16));
+        assert_eq!(grid.snap_size(24), Some(16));
+        assert_eq!(grid.snap_size(25), Some(32));
+        assert_eq!(grid.snap_size(500), Some(128));
+    }
+
+    #[test]
+    fn fractional_design_pixels_suggest_only_whole_multiples() {
+        // 1000 / 12 = 83.33px: only every third multiple lands on a whole size.
+        let grid = squares(12.0).grid(1000).unwrap();
+        assert!((grid.design_px - 83.333).abs() < 0.001);
+        assert_eq!(grid.suggested_sizes, [250, 500]);
+        assert!(!grid.is_clean_size(83));
+
+        // 2048 / 100 = 20.48px reaches a whole size only at 25 multiples.
+        let grid = squares(100.0).grid(2048).unwrap();
+        assert!(grid.suggested_sizes.is_empty());
+        assert_eq!(grid.snap_size(20), None);
+    }
+}
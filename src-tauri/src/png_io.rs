@@ -0,0 +1,55 @@
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+/// 8-bit RGBA pixels, row-major.
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    pub fn has_transparency(&self) -> bool {
+        self.pixels.chunks_exact(4).any(|p| p[3] < 255)
+    }
+}
+
+/// Decodes any PNG color type and bit depth to 8-bit RGBA.
+pub fn decode_png(data: &[u8]) -> Result<RgbaImage, String> {
+    let mut decoder = Decoder::new(data);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| format!("Failed to read PNG: {}", e))?;
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| format!("Failed to decode PNG: {}", e))?;
+    buf.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        ColorType::Rgba => buf,
+        ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        ColorType::Grayscale => buf.iter().flat_map(|v| [*v, *v, *v, 255]).collect(),
+        ColorType::Indexed => return Err("Failed to decode PNG: palette was not expanded".to_string()),
+    };
+    Ok(RgbaImage {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, image.width, image.height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .write_image_data(&image.pixels)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer.finish().map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(out)
+}
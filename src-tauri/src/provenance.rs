@@ -0,0 +1,94 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use ttf_parser::name_id;
+
+/// What produced a generated font: tool version, job and font identity, and key parameters.
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub generator: String,
+    pub job_hash: String,
+    /// SHA-256 of the font file as read from disk, before any WOFF/WOFF2 decoding.
+    pub font_sha256: String,
+    pub family: String,
+    pub style: String,
+    pub parameters: Vec<(String, String)>,
+}
+
+impl Provenance {
+    /// `job` is hashed through its JSON form, whose object keys serde_json keeps sorted, so equal
+    /// jobs hash equally regardless of field order in the request.
+    pub fn new(
+        job: &impl Serialize,
+        font_sha256: String,
+        face: Option<&ttf_parser::Face>,
+        parameters: Vec<(String, String)>,
+    ) -> Result<Self, String> {
+        let canonical = serde_json::to_value(job)
+            .and_then(|v| serde_json::to_string(&v))
+            .map_err(|e| format!("Failed to serialize job: {}", e))?;
+        let (family, style) = face.map(font_names).unwrap_or_default();
+        Ok(Self {
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            job_hash: sha256_hex(canonical.as_bytes()),
+            font_sha256,
+            family,
+            style,
+            parameters,
+        })
+    }
+
+    /// `key=value` lines; the blob stores these verbatim and the generated sources quote them.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("generator={}", self.generator),
+            format!("job_sha256={}", self.job_hash),
+            format!("font_sha256={}", self.font_sha256),
+            format!("font_family={}", self.family),
+            format!("font_style={}", self.style),
+        ];
+        lines.extend(self.parameters.iter().map(|(k, v)| format!("{}={}", k, v)));
+        lines
+    }
+
+    pub fn comment_block(&self) -> String {
+        let mut out = String::from("// Provenance:\n");
+        for line in self.lines() {
+            // Names come from the font file; keep each entry on its comment line.
+            out.push_str(&format!("//   {}\n", line.replace(['\n', '\r'], " ")));
+        }
+        out
+    }
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Typographic family/subfamily names, falling back to the legacy name IDs.
+pub fn font_names(face: &ttf_parser::Face) -> (String, String) {
+    let find = |ids: [u16; 2]| {
+        ids.iter()
+            .find_map(|id| {
+                face.names()
+                    .into_iter()
+                    .filter(|n| n.name_id == *id)
+                    .find_map(|n| n.to_string())
+            })
+            .unwrap_or_default()
+    };
+    (
+        find([name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]),
+        find([name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]),
+    )
+}
+
+/// CRC-32 (zlib polynomial) over the bitmap bytes followed by `words` as little-endian u32;
+/// matches the verify helper emitted into C++ modules.
+pub fn module_crc32(bitmaps: &[u8], words: impl IntoIterator<Item = u32>) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(bitmaps);
+    for word in words {
+        hasher.update(&word.to_le_bytes());
+    }
+    hasher.finalize()
+}
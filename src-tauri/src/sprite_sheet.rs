@@ -0,0 +1,444 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitmap_fonts::{BitmapFont, BitmapGlyph};
+use crate::png_io::{decode_png, RgbaImage};
+
+/// How glyphs are laid out in a PNG sprite sheet and which codepoints they stand for.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpriteSheetSpec {
+    pub path: String,
+    /// "grid" cuts fixed cells; "strip" splits one row of glyphs at blank columns.
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    /// Characters of the cells or strip glyphs in reading order. When empty, glyphs are
+    /// numbered consecutively from `first_codepoint`.
+    #[serde(default)]
+    pub chars: String,
+    #[serde(default = "default_first_codepoint")]
+    pub first_codepoint: u32,
+    #[serde(default)]
+    pub cell_w: u32,
+    /// Cell height; 0 uses the whole image height for strips.
+    #[serde(default)]
+    pub cell_h: u32,
+    #[serde(default)]
+    pub margin_x: u32,
+    #[serde(default)]
+    pub margin_y: u32,
+    #[serde(default)]
+    pub spacing_x: u32,
+    #[serde(default)]
+    pub spacing_y: u32,
+    /// Baseline row counted from the top of a cell; detected from the row most glyphs stand on
+    /// when unset.
+    pub baseline: Option<u32>,
+    /// "auto", "alpha", "dark" (dark ink on a light background) or "light".
+    #[serde(default = "default_ink")]
+    pub ink: String,
+    #[serde(default = "default_threshold")]
+    pub threshold: u8,
+    /// Grid cells: advance by ink width instead of the cell width.
+    #[serde(default)]
+    pub proportional: bool,
+    /// Blank columns added after the ink of proportional glyphs.
+    #[serde(default = "default_letter_spacing")]
+    pub letter_spacing: u32,
+    /// Blank columns that separate two glyphs in a strip.
+    #[serde(default = "default_min_gap")]
+    pub min_gap: u32,
+    /// Advance of whitespace characters in strips and empty proportional cells.
+    pub space_advance: Option<u32>,
+}
+
+fn default_layout() -> String {
+    "grid".to_string()
+}
+
+fn default_first_codepoint() -> u32 {
+    0x20
+}
+
+fn default_ink() -> String {
+    "auto".to_string()
+}
+
+fn default_threshold() -> u8 {
+    128
+}
+
+fn default_letter_spacing() -> u32 {
+    1
+}
+
+fn default_min_gap() -> u32 {
+    1
+}
+
+/// Cuts the sheet into glyphs with advance and baseline, as a pixel-exact bitmap font.
+pub fn import_sprite_sheet(data: &[u8], spec: &SpriteSheetSpec) -> Result<BitmapFont, String> {
+    let image = decode_png(data)?;
+    let mask = InkMask::new(&image, &spec.ink, spec.threshold)?;
+    let (glyphs, cell_h) = match spec.layout.as_str() {
+        "grid" => cut_grid(&mask, spec)?,
+        "strip" => cut_strip(&mask, spec)?,
+        other => return Err(format!("Unknown sprite sheet layout: {}", other)),
+    };
+    let baseline = spec.baseline.unwrap_or_else(|| detect_baseline(&glyphs, cell_h));
+    if baseline > cell_h {
+        return Err(format!("Sprite sheet baseline {} is below the {}px cell", baseline, cell_h));
+    }
+    let descent = (cell_h - baseline) as i32;
+    let glyphs = glyphs.into_iter().map(|(cp, mut glyph)| {
+        glyph.y_min = -descent;
+        (cp, glyph)
+    });
+    let family = std::path::Path::new(&spec.path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    BitmapFont::from_glyphs("Sprite sheet", family, cell_h, baseline as i32, descent, glyphs)
+}
+
+/// Row below the ink of most glyphs: the baseline the majority shares, so descenders in
+/// g, p or y don't pull it down. Ties go to the lower row; a sheet without ink uses `cell_h`.
+fn detect_baseline(glyphs: &[(u32, BitmapGlyph)], cell_h: u32) -> u32 {
+    let mut bottoms: BTreeMap<u32, usize> = BTreeMap::new();
+    for (_, glyph) in glyphs {
+        let mut rows = glyph.gray.chunks(glyph.width.max(1) as usize);
+        if let Some(bottom) = rows.rposition(|row| row.iter().any(|p| *p != 0)) {
+            *bottoms.entry(bottom as u32 + 1).or_default() += 1;
+        }
+    }
+    bottoms
+        .into_iter()
+        .max_by_key(|(row, count)| (*count, *row))
+        .map_or(cell_h, |(row, _)| row)
+}
+
+/// Binary ink coverage of the whole image.
+struct InkMask {
+    width: u32,
+    height: u32,
+    ink: Vec<bool>,
+}
+
+impl InkMask {
+    fn new(image: &RgbaImage, mode: &str, threshold: u8) -> Result<Self, String> {
+        let luma = |p: [u8; 4]| ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8;
+        let mode = match mode {
+            "auto" if image.has_transparency() => "alpha",
+            "auto" => {
+                let total: u64 = image.pixels.chunks_exact(4).map(|p| luma([p[0], p[1], p[2], p[3]]) as u64).sum();
+                let mean = total / (image.width as u64 * image.height as u64).max(1);
+                if mean >= 128 {
+                    "dark"
+                } else {
+                    "light"
+                }
+            }
+            "alpha" | "dark" | "light" => mode,
+            other => return Err(format!("Unknown sprite sheet ink mode: {}", other)),
+        };
+        let mut ink = Vec::with_capacity(image.width as usize * image.height as usize);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let p = image.pixel(x, y);
+                // Transparent pixels never count as ink, whatever their color.
+                let coverage = match mode {
+                    "alpha" => p[3],
+                    "dark" => ((255 - luma(p) as u32) * p[3] as u32 / 255) as u8,
+                    _ => (luma(p) as u32 * p[3] as u32 / 255) as u8,
+                };
+                ink.push(coverage >= threshold.max(1));
+            }
+        }
+        Ok(Self {
+            width: image.width,
+            height: image.height,
+            ink,
+        })
+    }
+
+    fn at(&self, x: u32, y: u32) -> bool {
+        self.ink[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Cell pixels as 0/255 coverage plus the first and last inked columns, if any.
+    fn cell(&self, x0: u32, y0: u32, w: u32, h: u32) -> (Vec<u8>, Option<(u32, u32)>) {
+        let mut gray = Vec::with_capacity(w as usize * h as usize);
+        let mut columns: Option<(u32, u32)> = None;
+        for y in 0..h {
+            for x in 0..w {
+                let lit = self.at(x0 + x, y0 + y);
+                gray.push(if lit { 255 } else { 0 });
+                if lit {
+                    columns = Some(columns.map_or((x, x), |(l, r)| (l.min(x), r.max(x))));
+                }
+            }
+        }
+        (gray, columns)
+    }
+}
+
+/// Codepoints of `count` glyphs: `chars` when given, otherwise numbered from `first_codepoint`.
+fn codepoints(spec: &SpriteSheetSpec, count: usize) -> Result<Vec<u32>, String> {
+    if !spec.chars.is_empty() {
+        return Ok(spec.chars.chars().map(|c| c as u32).collect());
+    }
+    let last = spec.first_codepoint as u64 + (count as u64).saturating_sub(1);
+    if count > 0 && last > char::MAX as u64 {
+        return Err(format!(
+            "Sprite sheet has {} glyphs, numbering them from U+{:04X} runs past U+10FFFF",
+            count, spec.first_codepoint
+        ));
+    }
+    Ok((spec.first_codepoint..).take(count).collect())
+}
+
+fn cut_grid(mask: &InkMask, spec: &SpriteSheetSpec) -> Result<(Vec<(u32, BitmapGlyph)>, u32), String> {
+    let (cw, ch) = (spec.cell_w, spec.cell_h);
+    if cw == 0 || ch == 0 {
+        return Err("Sprite sheet grid needs cell_w and cell_h".to_string());
+    }
+    let count = |size: u32, margin: u32, cell: u32, spacing: u32| {
+        (size.saturating_sub(margin) + spacing) / (cell + spacing)
+    };
+    let columns = count(mask.width, spec.margin_x, cw, spec.spacing_x);
+    let rows = count(mask.height, spec.margin_y, ch, spec.spacing_y);
+    let cells = (columns * rows) as usize;
+    if cells == 0 {
+        return Err(format!("Sprite sheet {}x{} has no {}x{} cells", mask.width, mask.height, cw, ch));
+    }
+    if !spec.chars.is_empty() && spec.chars.chars().count() > cells {
+        return Err(format!(
+            "Sprite sheet has {} cells but {} characters were given",
+            cells,
+            spec.chars.chars().count()
+        ));
+    }
+
+    let space_advance = spec.space_advance.unwrap_or(cw / 2) as i32;
+    let mut glyphs = Vec::new();
+    for (cell, cp) in codepoints(spec, cells)?.into_iter().enumerate() {
+        let x0 = spec.margin_x + (cell as u32 % columns) * (cw + spec.spacing_x);
+        let y0 = spec.margin_y + (cell as u32 / columns) * (ch + spec.spacing_y);
+        let (gray, ink_columns) = mask.cell(x0, y0, cw, ch);
+        let (x_min, advance) = match (spec.proportional, ink_columns) {
+            (false, _) => (0, cw as i32),
+            (true, Some((left, right))) => (-(left as i32), (right - left + 1 + spec.letter_spacing) as i32),
+            (true, None) => (0, space_advance),
+        };
+        glyphs.push((
+            cp,
+            BitmapGlyph {
+                width: cw,
+                height: ch,
+                x_min,
+                y_min: 0,
+                advance,
+                gray,
+            },
+        ));
+    }
+    Ok((glyphs, ch))
+}
+
+fn cut_strip(mask: &InkMask, spec: &SpriteSheetSpec) -> Result<(Vec<(u32, BitmapGlyph)>, u32), String> {
+    let y0 = spec.margin_y.min(mask.height);
+    let h = match spec.cell_h {
+        0 => mask.height - y0,
+        h if y0 + h <= mask.height => h,
+        h => return Err(format!("Sprite strip of {}px does not fit the {}px image", h, mask.height)),
+    };
+
+    // Runs of inked columns; gaps narrower than `min_gap` stay inside a glyph.
+    let inked: Vec<bool> = (0..mask.width).map(|x| (y0..y0 + h).any(|y| mask.at(x, y))).collect();
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    let mut x = spec.margin_x;
+    while x < mask.width {
+        if !inked[x as usize] {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        let mut end = x;
+        while x < mask.width {
+            if inked[x as usize] {
+                end = x;
+                x += 1;
+            } else if (x..mask.width.min(x + spec.min_gap.max(1))).all(|g| !inked[g as usize]) {
+                break;
+            } else {
+                x += 1;
+            }
+        }
+        runs.push((start, end));
+    }
+
+    let chars = codepoints(spec, runs.len())?;
+    // Only given characters can be spaces; numbered glyphs map one-to-one to runs, even U+0020.
+    let is_space = |cp: &u32| !spec.chars.is_empty() && char::from_u32(*cp).is_some_and(char::is_whitespace);
+    let drawn = chars.iter().filter(|cp| !is_space(cp)).count();
+    if drawn != runs.len() {
+        return Err(format!(
+            "Sprite strip has {} glyphs but {} non-space characters were given",
+            runs.len(),
+            drawn
+        ));
+    }
+
+    let space_advance = spec.space_advance.unwrap_or(h / 4).max(1) as i32;
+    let mut runs = runs.into_iter();
+    let mut glyphs = Vec::new();
+    for cp in chars {
+        let run = if is_space(&cp) { None } else { runs.next() };
+        let glyph = match run {
+            Some((start, end)) => {
+                let w = end - start + 1;
+                let (gray, _) = mask.cell(start, y0, w, h);
+                BitmapGlyph {
+                    width: w,
+                    height: h,
+                    x_min: 0,
+                    y_min: 0,
+                    advance: (w + spec.letter_spacing) as i32,
+                    gray,
+                }
+            }
+            None => BitmapGlyph {
+                advance: space_advance,
+                ..Default::default()
+            },
+        };
+        glyphs.push((cp, glyph));
+    }
+    Ok((glyphs, h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png_io::encode_png;
+
+    /// White PNG with black pixels at `ink`.
+    fn sheet(width: u32, height: u32, ink: impl IntoIterator<Item = (u32, u32)>) -> Vec<u8> {
+        let mut pixels = vec![255u8; width as usize * height as usize * 4];
+        for (x, y) in ink {
+            let i = (y as usize * width as usize + x as usize) * 4;
+            pixels[i..i + 3].fill(0);
+        }
+        encode_png(&RgbaImage { width, height, pixels }).unwrap()
+    }
+
+    fn spec(fields: serde_json::Value) -> SpriteSheetSpec {
+        let mut spec = serde_json::json!({"path": "sheet.png"});
+        spec.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(spec).unwrap()
+    }
+
+    fn glyph(font: &BitmapFont, c: char) -> &BitmapGlyph {
+        font.glyph(font.glyph_index(c as u32)).unwrap()
+    }
+
+    /// 3x2 grid of 4x5 cells with a 1px margin and spacing: a 4x4 block, a dot in the corner of
+    /// the ink area and a one-column glyph with a descender.
+    fn grid_sheet() -> Vec<u8> {
+        let cell = |col: u32, row: u32, x: u32, y: u32| (1 + col * 5 + x, 1 + row * 6 + y);
+        let block = (0..4).flat_map(|y| (0..4).map(move |x| cell(0, 0, x, y)));
+        let dot = [cell(1, 0, 3, 3)];
+        let descender = (1..5).map(|y| cell(2, 0, 0, y));
+        let second_row = [cell(0, 1, 1, 0), cell(0, 1, 1, 3)];
+        sheet(16, 13, block.chain(dot).chain(descender).chain(second_row))
+    }
+
+    #[test]
+    fn grid_cells_are_cut_at_margins_and_spacing() {
+        let grid = |chars: &str, baseline: Option<u32>| {
+            spec(serde_json::json!({
+                "chars": chars,
+                "cell_w": 4,
+                "cell_h": 5,
+                "margin_x": 1,
+                "margin_y": 1,
+                "spacing_x": 1,
+                "spacing_y": 1,
+                "baseline": baseline
+            }))
+        };
+        let font = import_sprite_sheet(&grid_sheet(), &grid("ABgi", None)).unwrap();
+        // Three of the four glyphs end on row 3, so the baseline is row 4 despite the `g`.
+        assert_eq!((font.pixel_size, font.ascent, font.descent), (5, 4, 1));
+        let a = glyph(&font, 'A');
+        assert_eq!((a.width, a.height, a.x_min, a.y_min, a.advance), (4, 4, 0, 0, 4));
+        let b = glyph(&font, 'B');
+        assert_eq!((b.width, b.height, b.x_min, b.y_min), (1, 1, 3, 0));
+        let g = glyph(&font, 'g');
+        assert_eq!((g.width, g.height, g.y_min), (1, 4, -1));
+        let i = glyph(&font, 'i');
+        assert_eq!((i.height, i.x_min, i.gray.as_slice()), (4, 1, &[255, 0, 0, 255][..]));
+
+        let font = import_sprite_sheet(&grid_sheet(), &grid("ABgi", Some(5))).unwrap();
+        assert_eq!((font.ascent, font.descent), (5, 0));
+        assert_eq!(glyph(&font, 'g').y_min, 0);
+
+        let err = import_sprite_sheet(&grid_sheet(), &grid("ABCDEFG", None)).unwrap_err();
+        assert!(err.contains("has 6 cells but 7 characters"), "{}", err);
+    }
+
+    /// 10x5 strip: a 2px glyph, a 1px glyph and a 3px glyph with a blank middle column, all
+    /// standing on row 4 except for a descender pixel under the last one.
+    fn strip_sheet() -> Vec<u8> {
+        let columns = [0, 1, 4, 7, 9];
+        let ink = columns.iter().flat_map(|x| (1..4).map(move |y| (*x, y)));
+        sheet(10, 5, ink.chain([(9, 4)]))
+    }
+
+    #[test]
+    fn strip_runs_split_at_gaps_of_min_gap_columns() {
+        let strip = spec(serde_json::json!({"layout": "strip", "first_codepoint": 0x41, "min_gap": 2}));
+        let font = import_sprite_sheet(&strip_sheet(), &strip).unwrap();
+        assert_eq!((font.ascent, font.descent), (4, 1));
+        assert_eq!((glyph(&font, 'A').width, glyph(&font, 'A').advance), (2, 3));
+        assert_eq!((glyph(&font, 'B').width, glyph(&font, 'B').advance), (1, 2));
+        let c = glyph(&font, 'C');
+        assert_eq!((c.width, c.height, c.y_min, c.advance), (3, 4, -1, 4));
+        assert_eq!(&c.gray[..3], &[255, 0, 255]);
+
+        // A one-column gap splits the last glyph in two.
+        let strip = spec(serde_json::json!({"layout": "strip", "chars": "ab cd"}));
+        let font = import_sprite_sheet(&strip_sheet(), &strip).unwrap();
+        assert_eq!(glyph(&font, ' ').advance, 1);
+        assert_eq!((glyph(&font, 'c').width, glyph(&font, 'd').width), (1, 1));
+
+        let strip = spec(serde_json::json!({"layout": "strip", "chars": "abc"}));
+        let err = import_sprite_sheet(&strip_sheet(), &strip).unwrap_err();
+        assert!(err.contains("has 4 glyphs but 3 non-space characters"), "{}", err);
+    }
+
+    #[test]
+    fn numbered_strips_start_at_space_by_default() {
+        let strip = spec(serde_json::json!({"layout": "strip"}));
+        let font = import_sprite_sheet(&strip_sheet(), &strip).unwrap();
+        assert_eq!((glyph(&font, ' ').width, glyph(&font, ' ').advance), (2, 3));
+        assert_eq!(glyph(&font, '!').width, 1);
+        assert_eq!((glyph(&font, '"').width, glyph(&font, '#').width), (1, 1));
+    }
+
+    #[test]
+    fn numbered_glyphs_stop_at_the_last_codepoint() {
+        // Three glyphs end exactly on U+10FFFF; four run past it.
+        let strip = spec(serde_json::json!({"layout": "strip", "first_codepoint": 0x10FFFD, "min_gap": 2}));
+        let font = import_sprite_sheet(&strip_sheet(), &strip).unwrap();
+        assert_eq!(glyph(&font, char::MAX).width, 3);
+
+        let strip = spec(serde_json::json!({"layout": "strip", "first_codepoint": 0x10FFFD}));
+        let err = import_sprite_sheet(&strip_sheet(), &strip).unwrap_err();
+        assert!(err.contains("has 4 glyphs, numbering them from U+10FFFD runs past U+10FFFF"), "{}", err);
+
+        let grid = spec(serde_json::json!({"first_codepoint": u32::MAX, "cell_w": 4, "cell_h": 5}));
+        let err = import_sprite_sheet(&grid_sheet(), &grid).unwrap_err();
+        assert!(err.contains("runs past U+10FFFF"), "{}", err);
+    }
+}
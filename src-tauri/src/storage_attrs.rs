@@ -0,0 +1,148 @@
+/// Placement of the generated constant arrays: linker section, alignment and a storage qualifier
+/// such as `PROGMEM`, spelled the way the selected toolchain expects.
+pub struct StorageAttrs {
+    toolchain: Toolchain,
+    section: Option<String>,
+    alignment: Option<u32>,
+    qualifier: Option<String>,
+    include: Option<&'static str>,
+    /// The qualifier puts the arrays in AVR program memory, which plain loads cannot read.
+    progmem: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Toolchain {
+    Gcc,
+    Iar,
+}
+
+impl StorageAttrs {
+    /// Resolves a preset (`none`, `avr`, `esp_idf`, `arm_gcc`, `iar`, `keil`) and the explicit
+    /// options; explicit values win over the preset defaults.
+    pub fn from_options(preset: &str, section: &str, alignment: u32, qualifier: &str) -> Result<Self, String> {
+        let (toolchain, default_qualifier, include) = match preset {
+            "" | "none" | "arm_gcc" | "keil" => (Toolchain::Gcc, None, None),
+            "avr" => (Toolchain::Gcc, Some("PROGMEM"), Some("<avr/pgmspace.h>")),
+            "esp_idf" => (Toolchain::Gcc, Some("DRAM_ATTR"), Some("\"esp_attr.h\"")),
+            "iar" => (Toolchain::Iar, None, None),
+            other => return Err(format!("Unknown storage preset: {}", other)),
+        };
+        let section = section.trim();
+        if section.chars().any(|c| c == '"' || c == '\\' || c.is_whitespace()) {
+            return Err(format!("Invalid linker section name: {}", section));
+        }
+        if alignment != 0 && !alignment.is_power_of_two() {
+            return Err(format!("Storage alignment must be a power of two, got {}", alignment));
+        }
+        let qualifier = qualifier.trim();
+        if qualifier.contains(['\n', '\r']) {
+            return Err("Storage qualifier must be a single line".to_string());
+        }
+        let qualifier = if qualifier.is_empty() {
+            default_qualifier.map(str::to_string)
+        } else {
+            Some(qualifier.to_string())
+        };
+        let progmem = qualifier
+            .as_deref()
+            .is_some_and(|q| q.split_whitespace().any(|word| word == "PROGMEM"));
+        Ok(Self {
+            toolchain,
+            section: (!section.is_empty()).then(|| section.to_string()),
+            alignment: (alignment != 0).then_some(alignment),
+            qualifier,
+            include: include.or(progmem.then_some("<avr/pgmspace.h>")),
+            progmem,
+        })
+    }
+
+    /// Header the qualifier macro comes from, as an `#include` operand.
+    pub fn include(&self) -> Option<&'static str> {
+        self.include
+    }
+
+    pub fn progmem(&self) -> bool {
+        self.progmem
+    }
+
+    /// Specifier for functions that read the arrays; `pgm_read_*` is not a constant expression.
+    pub fn function_specifier(&self) -> &'static str {
+        if self.progmem {
+            "inline"
+        } else {
+            "constexpr"
+        }
+    }
+
+    /// Expression reading the integer array element `place` of type `ty`.
+    pub fn load(&self, ty: &str, place: &str) -> String {
+        if !self.progmem {
+            return place.to_string();
+        }
+        let read = match ty {
+            "uint8_t" | "int8_t" => "pgm_read_byte",
+            "uint16_t" | "int16_t" => "pgm_read_word",
+            _ => "pgm_read_dword",
+        };
+        format!("({}){}(&{})", ty, read, place)
+    }
+
+    /// Statement binding the struct array element `place` to a local `var`, copied out of
+    /// program memory when needed.
+    pub fn load_struct(&self, ty: &str, var: &str, place: &str) -> String {
+        if self.progmem {
+            format!("{} {}; memcpy_P(&{}, &{}, sizeof {});", ty, var, var, place, var)
+        } else {
+            format!("const {}& {} = {};", ty, var, place)
+        }
+    }
+
+    /// Opening line of a constant array definition, including any preceding pragmas. The array is
+    /// `inline` rather than `static` so the module's exported accessors may reference it.
+    pub fn array_decl(&self, ty: &str, name: &str, dims: &str) -> String {
+        self.declaration("inline constexpr", ty, name, dims)
+    }
+
+    /// `array_decl` for an array the module exports.
+    pub fn exported_array_decl(&self, ty: &str, name: &str, dims: &str) -> String {
+        self.declaration("export inline constexpr", ty, name, dims)
+    }
+
+    /// `array_decl` for a plain C header.
+    pub fn c_array_decl(&self, ty: &str, name: &str, dims: &str) -> String {
+        self.declaration("static const", ty, name, dims)
+    }
+
+    fn declaration(&self, specifiers: &str, ty: &str, name: &str, dims: &str) -> String {
+        let mut out = String::new();
+        let mut suffix = String::new();
+        match self.toolchain {
+            Toolchain::Gcc => {
+                let mut attrs = Vec::new();
+                if let Some(section) = &self.section {
+                    attrs.push(format!("section(\"{}\")", section));
+                }
+                if let Some(alignment) = self.alignment {
+                    attrs.push(format!("aligned({})", alignment));
+                }
+                if !attrs.is_empty() {
+                    suffix.push_str(&format!(" __attribute__(({}))", attrs.join(", ")));
+                }
+            }
+            Toolchain::Iar => {
+                if let Some(section) = &self.section {
+                    out.push_str(&format!("#pragma location = \"{}\"\n", section));
+                }
+                if let Some(alignment) = self.alignment {
+                    out.push_str(&format!("#pragma data_alignment = {}\n", alignment));
+                }
+            }
+        }
+        if let Some(qualifier) = &self.qualifier {
+            suffix.push(' ');
+            suffix.push_str(qualifier);
+        }
+        out.push_str(&format!("{} {} {}[]{}{} = {{\n", specifiers, ty, name, dims, suffix));
+        out
+    }
+}
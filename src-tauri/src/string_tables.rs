@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::storage_attrs::StorageAttrs;
+
+/// A UI string to ship as glyph indices instead of UTF-8.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedString {
+    pub name: String,
+    pub text: String,
+}
+
+/// A string encoded against the generated glyph table.
+pub struct EncodedString {
+    pub constant: String,
+    pub glyphs: Vec<usize>,
+    /// Sum of the glyphs' `x_advance` in pixels. The exported font has no kerning, so neither
+    /// does the width the firmware draws.
+    pub width: i32,
+}
+
+/// Reads a strings file: either a JSON object of name to text, or `name = text` lines where
+/// blank lines and lines starting with `#` are skipped and text may be wrapped in double quotes
+/// to keep leading or trailing spaces.
+pub fn load_strings_file(path: &str) -> Result<Vec<NamedString>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read strings file {}: {}", path, e))?;
+    if contents.trim_start().starts_with('{') {
+        let value: Value =
+            serde_json::from_str(&contents).map_err(|e| format!("Failed to parse strings file {}: {}", path, e))?;
+        let object = value.as_object().ok_or_else(|| format!("Strings file {} is not a JSON object", path))?;
+        return object
+            .iter()
+            .map(|(name, text)| match text.as_str() {
+                Some(text) => Ok(NamedString {
+                    name: name.clone(),
+                    text: text.to_string(),
+                }),
+                None => Err(format!("String \"{}\" in {} is not a JSON string", name, path)),
+            })
+            .collect();
+    }
+
+    let mut strings = Vec::new();
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, text)) = line.split_once('=') else {
+            return Err(format!("{}:{}: expected `name = text`", path, line_no + 1));
+        };
+        let text = text.trim();
+        let text = text
+            .strip_prefix('"')
+            .and_then(|t| t.strip_suffix('"'))
+            .unwrap_or(text);
+        strings.push(NamedString {
+            name: name.trim().to_string(),
+            text: text.to_string(),
+        });
+    }
+    Ok(strings)
+}
+
+/// One error line per string and character the font cannot draw.
+pub fn missing_glyphs(strings: &[NamedString], has_glyph: impl Fn(char) -> bool) -> Vec<String> {
+    let mut errors = Vec::new();
+    for string in strings {
+        let mut reported: HashSet<char> = HashSet::new();
+        for ch in string.text.chars().filter(|ch| !has_glyph(*ch)) {
+            if reported.insert(ch) {
+                errors.push(format!(
+                    "String \"{}\" uses U+{:04X} {:?}, which is not in the font",
+                    string.name, ch as u32, ch
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// Encodes every string as glyph table indices. `glyph_id` maps a character to its index in the
+/// glyph table and `advance` gives an entry's `x_advance`.
+pub fn encode_strings(
+    strings: &[NamedString],
+    glyph_id: impl Fn(char) -> Option<usize>,
+    advance: impl Fn(usize) -> i32,
+) -> Result<Vec<EncodedString>, String> {
+    let mut encoded: Vec<EncodedString> = Vec::new();
+    for string in strings {
+        if string.name.trim().is_empty() {
+            return Err(format!("String \"{}\" has no name", string.text));
+        }
+        if string.text.is_empty() {
+            return Err(format!("String \"{}\" is empty", string.name));
+        }
+        let constant = constant_name(&string.name);
+        if encoded.iter().any(|e| e.constant == constant) {
+            return Err(format!("String \"{}\" duplicates constant {}", string.name, constant));
+        }
+        let glyphs = string
+            .text
+            .chars()
+            .map(|ch| glyph_id(ch).ok_or_else(|| format!("String \"{}\": U+{:04X} is not in the glyph table", string.name, ch as u32)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let width = glyphs.iter().map(|id| advance(*id)).sum::<i32>();
+        encoded.push(EncodedString { constant, glyphs, width });
+    }
+    Ok(encoded)
+}
+
+/// `STR_` plus the name in upper snake case, e.g. "menu.title" -> `STR_MENU_TITLE`.
+fn constant_name(name: &str) -> String {
+    let body: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("STR_{}", body)
+}
+
+/// Narrowest unsigned type that holds every glyph table index.
+fn index_type(glyph_count: usize) -> &'static str {
+    if glyph_count <= 0x100 {
+        "uint8_t"
+    } else if glyph_count <= 0x1_0000 {
+        "uint16_t"
+    } else {
+        "uint32_t"
+    }
+}
+
+/// The array body after its opening line: the indices and the closing brace.
+fn index_list(string: &EncodedString) -> String {
+    let ids: Vec<String> = string.glyphs.iter().map(|id| id.to_string()).collect();
+    format!("    {},\n}};\n", ids.join(", "))
+}
+
+/// String tables for a C++ module, placed like the module's other arrays.
+pub fn cpp_strings(strings: &[EncodedString], glyph_count: usize, storage: &StorageAttrs) -> String {
+    if strings.is_empty() {
+        return String::new();
+    }
+    let ty = index_type(glyph_count);
+    let mut out = String::from("// Pre-encoded strings: glyph_table indices and pixel width.\n");
+    if storage.progmem() {
+        out.push_str("// The indices are in program memory; read them with pgm_read_byte/pgm_read_word.\n");
+    }
+    for string in strings {
+        out.push_str(&storage.exported_array_decl(ty, &string.constant, ""));
+        out.push_str(&index_list(string));
+        out.push_str(&format!(
+            "export constexpr uint32_t {}_LEN = {};\n",
+            string.constant,
+            string.glyphs.len()
+        ));
+        out.push_str(&format!("export constexpr int32_t {}_WIDTH = {};\n", string.constant, string.width));
+    }
+    out.push('\n');
+    out
+}
+
+/// String tables for the plain C blob header, indexing the blob's glyph table. They are the
+/// header's only arrays, so the storage qualifier's include comes with them.
+pub fn c_strings(strings: &[EncodedString], glyph_count: usize, storage: &StorageAttrs) -> String {
+    if strings.is_empty() {
+        return String::new();
+    }
+    let ty = index_type(glyph_count);
+    let mut out = String::from("/* Pre-encoded strings: glyph table indices and pixel width. */\n");
+    if let Some(include) = storage.include() {
+        out.push_str(&format!("#include {}\n", include));
+    }
+    if storage.progmem() {
+        out.push_str("/* The indices are in program memory; read them with pgm_read_byte/pgm_read_word. */\n");
+    }
+    for string in strings {
+        out.push_str(&storage.c_array_decl(ty, &string.constant, ""));
+        out.push_str(&index_list(string));
+        out.push_str(&format!("#define {}_LEN {}u\n", string.constant, string.glyphs.len()));
+        out.push_str(&format!("#define {}_WIDTH {}\n", string.constant, string.width));
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded() -> Vec<EncodedString> {
+        let strings = [NamedString {
+            name: "menu.title".to_string(),
+            text: "AB".to_string(),
+        }];
+        encode_strings(&strings, |ch| Some(ch as usize - 0x40), |_| 5).unwrap()
+    }
+
+    #[test]
+    fn cpp_strings_use_the_storage_placement() {
+        let avr = StorageAttrs::from_options("avr", "", 0, "").unwrap();
+        let out = cpp_strings(&encoded(), 3, &avr);
+        assert!(out.contains("export inline constexpr uint8_t STR_MENU_TITLE[] PROGMEM = {\n    1, 2,\n};\n"), "{}", out);
+        assert!(out.contains("read them with pgm_read_byte"));
+        assert!(out.contains("export constexpr uint32_t STR_MENU_TITLE_LEN = 2;\n"));
+        assert!(out.contains("export constexpr int32_t STR_MENU_TITLE_WIDTH = 10;\n"));
+
+        let plain = StorageAttrs::from_options("none", "", 0, "").unwrap();
+        let out = cpp_strings(&encoded(), 300, &plain);
+        assert!(out.contains("export inline constexpr uint16_t STR_MENU_TITLE[] = {\n"), "{}", out);
+        assert!(!out.contains("pgm_read"));
+    }
+
+    #[test]
+    fn c_strings_use_the_storage_placement() {
+        let iar = StorageAttrs::from_options("iar", ".ext_flash", 8, "").unwrap();
+        let out = c_strings(&encoded(), 3, &iar);
+        let expected = "#pragma location = \".ext_flash\"\n#pragma data_alignment = 8\n\
+                        static const uint8_t STR_MENU_TITLE[] = {\n    1, 2,\n};\n";
+        assert!(out.contains(expected), "{}", out);
+        assert!(out.contains("#define STR_MENU_TITLE_LEN 2u\n"));
+
+        let avr = StorageAttrs::from_options("avr", "", 0, "").unwrap();
+        let out = c_strings(&encoded(), 3, &avr);
+        assert!(out.contains("#include <avr/pgmspace.h>\n"));
+        assert!(out.contains("static const uint8_t STR_MENU_TITLE[] PROGMEM = {\n"), "{}", out);
+
+        assert!(c_strings(&[], 3, &avr).is_empty());
+    }
+}
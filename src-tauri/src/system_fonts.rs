@@ -1,20 +1,134 @@
+use font_kit::handle::Handle;
 use font_kit::source::SystemSource;
 use serde::Serialize;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use ttf_parser::{name_id, Face, Style};
 
-#[derive(Debug, Serialize)]
+use crate::font_inspect::{compress_ranges, mapped_codepoints};
+
+// Parsing every installed face is slow, so the listing is kept until a refresh is requested.
+static FONT_CACHE: Mutex<Option<Arc<Vec<SystemFontInfo>>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
 pub struct SystemFontInfo {
     family: String,
+    faces: Vec<SystemFaceInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemFaceInfo {
+    path: Option<String>,
+    font_index: u32,
+    postscript_name: Option<String>,
+    weight: u16,
+    style: &'static str,
+    monospace: bool,
+    #[serde(skip)]
+    coverage: Vec<[u32; 2]>,
+}
+
+impl SystemFaceInfo {
+    fn covers(&self, codepoint: u32) -> bool {
+        let idx = self.coverage.partition_point(|r| r[1] < codepoint);
+        self.coverage.get(idx).is_some_and(|r| r[0] <= codepoint)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoveringFont {
+    family: String,
+    path: Option<String>,
+    font_index: u32,
+    postscript_name: Option<String>,
 }
 
 #[tauri::command]
-pub fn list_system_fonts() -> Result<Vec<SystemFontInfo>, String> {
+pub fn list_system_fonts(refresh: Option<bool>) -> Result<Vec<SystemFontInfo>, String> {
+    Ok(cached_fonts(refresh.unwrap_or(false))?.as_ref().clone())
+}
+
+/// Lists installed faces whose cmap covers every non-control character of `text`.
+#[tauri::command]
+pub fn find_fonts_covering(text: String) -> Result<Vec<CoveringFont>, String> {
+    let mut wanted: Vec<u32> = text.chars().filter(|c| !c.is_control()).map(|c| c as u32).collect();
+    wanted.sort_unstable();
+    wanted.dedup();
+    if wanted.is_empty() {
+        return Err("No characters to search for".to_string());
+    }
+
+    let fonts = cached_fonts(false)?;
+    Ok(fonts
+        .iter()
+        .flat_map(|font| font.faces.iter().map(move |face| (font, face)))
+        .filter(|(_, face)| wanted.iter().all(|cp| face.covers(*cp)))
+        .map(|(font, face)| CoveringFont {
+            family: font.family.clone(),
+            path: face.path.clone(),
+            font_index: face.font_index,
+            postscript_name: face.postscript_name.clone(),
+        })
+        .collect())
+}
+
+fn cached_fonts(refresh: bool) -> Result<Arc<Vec<SystemFontInfo>>, String> {
+    let mut cache = FONT_CACHE
+        .lock()
+        .map_err(|_| "System font cache is poisoned".to_string())?;
+    if refresh || cache.is_none() {
+        *cache = Some(Arc::new(enumerate_fonts()?));
+    }
+    Ok(cache.as_ref().map(Arc::clone).unwrap_or_default())
+}
+
+fn enumerate_fonts() -> Result<Vec<SystemFontInfo>, String> {
     let source = SystemSource::new();
     let mut families = source
         .all_families()
         .map_err(|e| format!("Failed to list system fonts: {}", e))?;
-    families.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    families.sort_by_key(|family| family.to_lowercase());
+    families.dedup();
     Ok(families
         .into_iter()
-        .map(|family| SystemFontInfo { family })
+        .map(|family| {
+            let faces = source
+                .select_family_by_name(&family)
+                .map(|handle| handle.fonts().iter().filter_map(face_info).collect())
+                .unwrap_or_default();
+            SystemFontInfo { family, faces }
+        })
         .collect())
 }
+
+fn face_info(handle: &Handle) -> Option<SystemFaceInfo> {
+    let (bytes, path, font_index) = match handle {
+        Handle::Path { path, font_index } => (
+            fs::read(path).ok()?,
+            Some(path.to_string_lossy().to_string()),
+            *font_index,
+        ),
+        Handle::Memory { bytes, font_index } => ((**bytes).clone(), None, *font_index),
+    };
+    let face = Face::parse(&bytes, font_index).ok()?;
+    Some(SystemFaceInfo {
+        path,
+        font_index,
+        postscript_name: postscript_name(&face),
+        weight: face.weight().to_number(),
+        style: match face.style() {
+            Style::Normal => "normal",
+            Style::Italic => "italic",
+            Style::Oblique => "oblique",
+        },
+        monospace: face.is_monospaced(),
+        coverage: compress_ranges(&mapped_codepoints(&face)),
+    })
+}
+
+pub(crate) fn postscript_name(face: &Face) -> Option<String> {
+    face.names()
+        .into_iter()
+        .filter(|n| n.name_id == name_id::POST_SCRIPT_NAME)
+        .find_map(|n| n.to_string())
+}
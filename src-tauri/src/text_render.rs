@@ -0,0 +1,237 @@
+use crate::png_io::RgbaImage;
+
+/// A packed glyph as the firmware sees it: 1-bit rows, MSB-first, `(width + 7) / 8` bytes each.
+pub struct SampleGlyph<'a> {
+    pub bits: &'a [u8],
+    pub width: i32,
+    pub height: i32,
+    pub x_advance: i32,
+    pub x_offset: i32,
+    /// Top edge of the bitmap above the baseline.
+    pub y_offset: i32,
+}
+
+pub struct SampleOptions {
+    /// Display size in pixels; 0 sizes the image to the text.
+    pub width: u32,
+    pub height: u32,
+    pub fg: [u8; 4],
+    pub bg: [u8; 4],
+    /// Breaks lines at spaces, or inside words that are wider than the display.
+    pub wrap: bool,
+}
+
+pub struct RenderedText {
+    pub image: RgbaImage,
+    pub lines: u32,
+    /// Text ran past the right or bottom edge of the display.
+    pub overflow: bool,
+}
+
+/// Lays out and draws `text` line by line: the pen starts at `baseline` below the top edge,
+/// moves by `x_advance`, and drops by `line_height` on `\n` or a wrap.
+pub fn render_text<'a>(
+    text: &str,
+    options: &SampleOptions,
+    line_height: i32,
+    baseline: i32,
+    glyph: impl Fn(char) -> Option<SampleGlyph<'a>>,
+) -> RenderedText {
+    let advance = |ch: char| glyph(ch).map_or(0, |g| g.x_advance);
+    let max_width = (options.wrap && options.width > 0).then_some(options.width as i32);
+    let lines = layout_lines(text, max_width, &advance);
+    let line_width = |line: &[char]| line.iter().map(|ch| advance(*ch)).sum::<i32>();
+    let widest = lines.iter().map(|line| line_width(line)).max().unwrap_or(0);
+    let text_height = lines.len() as i32 * line_height;
+    let width = if options.width > 0 { options.width } else { widest.max(1) as u32 };
+    let height = if options.height > 0 { options.height } else { text_height.max(1) as u32 };
+    let overflow = widest > width as i32 || text_height > height as i32;
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for _ in 0..width as usize * height as usize {
+        pixels.extend_from_slice(&options.bg);
+    }
+    let mut image = RgbaImage { width, height, pixels };
+    for (row, line) in lines.iter().enumerate() {
+        let pen_y = baseline + row as i32 * line_height;
+        let mut pen_x = 0;
+        for ch in line {
+            let Some(g) = glyph(*ch) else {
+                continue;
+            };
+            draw_glyph(&mut image, &g, pen_x + g.x_offset, pen_y - g.y_offset, options.fg);
+            pen_x += g.x_advance;
+        }
+    }
+    RenderedText {
+        image,
+        lines: lines.len() as u32,
+        overflow,
+    }
+}
+
+/// Splits `text` at `\n` and, with `max_width`, wraps each paragraph greedily at the last
+/// space that fits.
+fn layout_lines(text: &str, max_width: Option<i32>, advance: &dyn Fn(char) -> i32) -> Vec<Vec<char>> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let chars: Vec<char> = paragraph.trim_end_matches('\r').chars().collect();
+        let Some(max_width) = max_width else {
+            lines.push(chars);
+            continue;
+        };
+        if chars.is_empty() {
+            lines.push(chars);
+            continue;
+        }
+        let mut start = 0;
+        while start < chars.len() {
+            let mut end = start;
+            let mut pen = 0;
+            let mut last_space = None;
+            while end < chars.len() {
+                let ch = chars[end];
+                let next = pen + advance(ch);
+                if next > max_width && end > start && ch != ' ' {
+                    break;
+                }
+                if ch == ' ' {
+                    last_space = Some(end);
+                }
+                pen = next;
+                end += 1;
+            }
+            match last_space.filter(|space| end < chars.len() && *space > start) {
+                Some(space) => {
+                    lines.push(chars[start..space].to_vec());
+                    start = space + 1;
+                }
+                None => {
+                    lines.push(chars[start..end].to_vec());
+                    start = end;
+                }
+            }
+        }
+    }
+    lines
+}
+
+fn draw_glyph(image: &mut RgbaImage, glyph: &SampleGlyph, left: i32, top: i32, color: [u8; 4]) {
+    let stride = (glyph.width as usize).div_ceil(8);
+    for y in 0..glyph.height {
+        for x in 0..glyph.width {
+            let byte = glyph.bits[y as usize * stride + (x as usize >> 3)];
+            if byte & (0x80 >> (x & 7)) == 0 {
+                continue;
+            }
+            let (px, py) = (left + x, top + y);
+            if px < 0 || py < 0 || px >= image.width as i32 || py >= image.height as i32 {
+                continue;
+            }
+            let i = (py as usize * image.width as usize + px as usize) * 4;
+            image.pixels[i..i + 4].copy_from_slice(&color);
+        }
+    }
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBAA`.
+pub fn parse_color(value: &str) -> Result<[u8; 4], String> {
+    let hex = value.trim().trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    // `from_str_radix` takes a sign, so check the digits first.
+    let digits = hex.bytes().all(|b| b.is_ascii_hexdigit());
+    let parsed = match hex.len() {
+        6 if digits => (channel(0), channel(2), channel(4), Ok(255)),
+        8 if digits => (channel(0), channel(2), channel(4), channel(6)),
+        _ => return Err(format!("Invalid color: {}", value)),
+    };
+    match parsed {
+        (Ok(r), Ok(g), Ok(b), Ok(a)) => Ok([r, g, b, a]),
+        _ => Err(format!("Invalid color: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str, max_width: Option<i32>) -> Vec<String> {
+        layout_lines(text, max_width, &|_| 1).into_iter().map(|line| line.into_iter().collect()).collect()
+    }
+
+    #[test]
+    fn wraps_at_the_last_space_that_fits() {
+        assert_eq!(lines("hello world foo", Some(11)), ["hello world", "foo"]);
+        assert_eq!(lines("hello world foo", Some(8)), ["hello", "world", "foo"]);
+        assert_eq!(lines("hello world foo", None), ["hello world foo"]);
+        // A space that would start the next line is dropped with the break.
+        assert_eq!(lines("ab cd", Some(2)), ["ab", "cd"]);
+    }
+
+    #[test]
+    fn breaks_words_wider_than_the_line() {
+        assert_eq!(lines("abcdefghij", Some(4)), ["abcd", "efgh", "ij"]);
+        assert_eq!(lines("a abcdefgh", Some(4)), ["a", "abcd", "efgh"]);
+    }
+
+    #[test]
+    fn splits_paragraphs_at_line_feeds_and_crlf() {
+        assert_eq!(lines("ab\r\ncd", None), ["ab", "cd"]);
+        assert_eq!(lines("ab\r\ncd", Some(4)), ["ab", "cd"]);
+        assert_eq!(lines("a\n\nb\n", Some(4)), ["a", "", "b", ""]);
+    }
+
+    #[test]
+    fn wraps_by_each_glyph_advance() {
+        let advance = |ch: char| if ch == 'W' { 3 } else { 1 };
+        let wrapped = layout_lines("WiW iii", Some(5), &advance);
+        assert_eq!(wrapped, [vec!['W', 'i'], vec!['W'], vec!['i', 'i', 'i']]);
+    }
+
+    #[test]
+    fn draws_glyphs_at_the_pen_and_reports_overflow() {
+        const DOT: [u8; 1] = [0x80];
+        let glyph = |ch: char| {
+            (ch == '.').then_some(SampleGlyph {
+                bits: &DOT,
+                width: 1,
+                height: 1,
+                x_advance: 2,
+                x_offset: 1,
+                y_offset: 1,
+            })
+        };
+        let options = SampleOptions {
+            width: 0,
+            height: 0,
+            fg: [0, 0, 0, 255],
+            bg: [255, 255, 255, 255],
+            wrap: false,
+        };
+        let rendered = render_text("..\n.", &options, 3, 2, glyph);
+        assert_eq!((rendered.image.width, rendered.image.height, rendered.lines), (4, 6, 2));
+        assert!(!rendered.overflow);
+        let inked: Vec<(u32, u32)> = (0..6)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|(x, y)| rendered.image.pixel(*x, *y) == options.fg)
+            .collect();
+        assert_eq!(inked, [(1, 1), (3, 1), (1, 4)]);
+
+        let small = SampleOptions { width: 3, height: 3, ..options };
+        assert!(render_text("..\n.", &small, 3, 2, glyph).overflow);
+    }
+
+    #[test]
+    fn parses_six_and_eight_digit_colors() {
+        assert_eq!(parse_color("#112233"), Ok([0x11, 0x22, 0x33, 0xFF]));
+        assert_eq!(parse_color(" aabbcc80 "), Ok([0xAA, 0xBB, 0xCC, 0x80]));
+        assert_eq!(parse_color("#FfFfFf"), Ok([0xFF; 4]));
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for value in ["", "#", "#12345", "#1234567", "#GG0000", "#ééé", "#+12345"] {
+            assert_eq!(parse_color(value), Err(format!("Invalid color: {}", value)), "{:?}", value);
+        }
+    }
+}
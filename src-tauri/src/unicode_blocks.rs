@@ -0,0 +1,324 @@
+// Generated from the Unicode Character Database Blocks.txt (Unicode 14.0).
+// Columns: first codepoint, last codepoint, assigned codepoints, block name.
+pub const UNICODE_BLOCKS: &[(u32, u32, u32, &str)] = &[
+    (0x0000, 0x007F, 128, "Basic Latin"),
+    (0x0080, 0x00FF, 128, "Latin-1 Supplement"),
+    (0x0100, 0x017F, 128, "Latin Extended-A"),
+    (0x0180, 0x024F, 208, "Latin Extended-B"),
+    (0x0250, 0x02AF, 96, "IPA Extensions"),
+    (0x02B0, 0x02FF, 80, "Spacing Modifier Letters"),
+    (0x0300, 0x036F, 112, "Combining Diacritical Marks"),
+    (0x0370, 0x03FF, 135, "Greek and Coptic"),
+    (0x0400, 0x04FF, 256, "Cyrillic"),
+    (0x0500, 0x052F, 48, "Cyrillic Supplement"),
+    (0x0530, 0x058F, 91, "Armenian"),
+    (0x0590, 0x05FF, 88, "Hebrew"),
+    (0x0600, 0x06FF, 256, "Arabic"),
+    (0x0700, 0x074F, 77, "Syriac"),
+    (0x0750, 0x077F, 48, "Arabic Supplement"),
+    (0x0780, 0x07BF, 50, "Thaana"),
+    (0x07C0, 0x07FF, 62, "NKo"),
+    (0x0800, 0x083F, 61, "Samaritan"),
+    (0x0840, 0x085F, 29, "Mandaic"),
+    (0x0860, 0x086F, 11, "Syriac Supplement"),
+    (0x0870, 0x089F, 41, "Arabic Extended-B"),
+    (0x08A0, 0x08FF, 96, "Arabic Extended-A"),
+    (0x0900, 0x097F, 128, "Devanagari"),
+    (0x0980, 0x09FF, 96, "Bengali"),
+    (0x0A00, 0x0A7F, 80, "Gurmukhi"),
+    (0x0A80, 0x0AFF, 91, "Gujarati"),
+    (0x0B00, 0x0B7F, 91, "Oriya"),
+    (0x0B80, 0x0BFF, 72, "Tamil"),
+    (0x0C00, 0x0C7F, 100, "Telugu"),
+    (0x0C80, 0x0CFF, 90, "Kannada"),
+    (0x0D00, 0x0D7F, 118, "Malayalam"),
+    (0x0D80, 0x0DFF, 91, "Sinhala"),
+    (0x0E00, 0x0E7F, 87, "Thai"),
+    (0x0E80, 0x0EFF, 82, "Lao"),
+    (0x0F00, 0x0FFF, 211, "Tibetan"),
+    (0x1000, 0x109F, 160, "Myanmar"),
+    (0x10A0, 0x10FF, 88, "Georgian"),
+    (0x1100, 0x11FF, 256, "Hangul Jamo"),
+    (0x1200, 0x137F, 358, "Ethiopic"),
+    (0x1380, 0x139F, 26, "Ethiopic Supplement"),
+    (0x13A0, 0x13FF, 92, "Cherokee"),
+    (0x1400, 0x167F, 640, "Unified Canadian Aboriginal Syllabics"),
+    (0x1680, 0x169F, 29, "Ogham"),
+    (0x16A0, 0x16FF, 89, "Runic"),
+    (0x1700, 0x171F, 23, "Tagalog"),
+    (0x1720, 0x173F, 23, "Hanunoo"),
+    (0x1740, 0x175F, 20, "Buhid"),
+    (0x1760, 0x177F, 18, "Tagbanwa"),
+    (0x1780, 0x17FF, 114, "Khmer"),
+    (0x1800, 0x18AF, 158, "Mongolian"),
+    (0x18B0, 0x18FF, 70, "Unified Canadian Aboriginal Syllabics Extended"),
+    (0x1900, 0x194F, 68, "Limbu"),
+    (0x1950, 0x197F, 35, "Tai Le"),
+    (0x1980, 0x19DF, 83, "New Tai Lue"),
+    (0x19E0, 0x19FF, 32, "Khmer Symbols"),
+    (0x1A00, 0x1A1F, 30, "Buginese"),
+    (0x1A20, 0x1AAF, 127, "Tai Tham"),
+    (0x1AB0, 0x1AFF, 31, "Combining Diacritical Marks Extended"),
+    (0x1B00, 0x1B7F, 124, "Balinese"),
+    (0x1B80, 0x1BBF, 64, "Sundanese"),
+    (0x1BC0, 0x1BFF, 56, "Batak"),
+    (0x1C00, 0x1C4F, 74, "Lepcha"),
+    (0x1C50, 0x1C7F, 48, "Ol Chiki"),
+    (0x1C80, 0x1C8F, 9, "Cyrillic Extended-C"),
+    (0x1C90, 0x1CBF, 46, "Georgian Extended"),
+    (0x1CC0, 0x1CCF, 8, "Sundanese Supplement"),
+    (0x1CD0, 0x1CFF, 43, "Vedic Extensions"),
+    (0x1D00, 0x1D7F, 128, "Phonetic Extensions"),
+    (0x1D80, 0x1DBF, 64, "Phonetic Extensions Supplement"),
+    (0x1DC0, 0x1DFF, 64, "Combining Diacritical Marks Supplement"),
+    (0x1E00, 0x1EFF, 256, "Latin Extended Additional"),
+    (0x1F00, 0x1FFF, 233, "Greek Extended"),
+    (0x2000, 0x206F, 111, "General Punctuation"),
+    (0x2070, 0x209F, 42, "Superscripts and Subscripts"),
+    (0x20A0, 0x20CF, 33, "Currency Symbols"),
+    (0x20D0, 0x20FF, 33, "Combining Diacritical Marks for Symbols"),
+    (0x2100, 0x214F, 80, "Letterlike Symbols"),
+    (0x2150, 0x218F, 60, "Number Forms"),
+    (0x2190, 0x21FF, 112, "Arrows"),
+    (0x2200, 0x22FF, 256, "Mathematical Operators"),
+    (0x2300, 0x23FF, 256, "Miscellaneous Technical"),
+    (0x2400, 0x243F, 39, "Control Pictures"),
+    (0x2440, 0x245F, 11, "Optical Character Recognition"),
+    (0x2460, 0x24FF, 160, "Enclosed Alphanumerics"),
+    (0x2500, 0x257F, 128, "Box Drawing"),
+    (0x2580, 0x259F, 32, "Block Elements"),
+    (0x25A0, 0x25FF, 96, "Geometric Shapes"),
+    (0x2600, 0x26FF, 256, "Miscellaneous Symbols"),
+    (0x2700, 0x27BF, 192, "Dingbats"),
+    (0x27C0, 0x27EF, 48, "Miscellaneous Mathematical Symbols-A"),
+    (0x27F0, 0x27FF, 16, "Supplemental Arrows-A"),
+    (0x2800, 0x28FF, 256, "Braille Patterns"),
+    (0x2900, 0x297F, 128, "Supplemental Arrows-B"),
+    (0x2980, 0x29FF, 128, "Miscellaneous Mathematical Symbols-B"),
+    (0x2A00, 0x2AFF, 256, "Supplemental Mathematical Operators"),
+    (0x2B00, 0x2BFF, 253, "Miscellaneous Symbols and Arrows"),
+    (0x2C00, 0x2C5F, 96, "Glagolitic"),
+    (0x2C60, 0x2C7F, 32, "Latin Extended-C"),
+    (0x2C80, 0x2CFF, 123, "Coptic"),
+    (0x2D00, 0x2D2F, 40, "Georgian Supplement"),
+    (0x2D30, 0x2D7F, 59, "Tifinagh"),
+    (0x2D80, 0x2DDF, 79, "Ethiopic Extended"),
+    (0x2DE0, 0x2DFF, 32, "Cyrillic Extended-A"),
+    (0x2E00, 0x2E7F, 94, "Supplemental Punctuation"),
+    (0x2E80, 0x2EFF, 115, "CJK Radicals Supplement"),
+    (0x2F00, 0x2FDF, 214, "Kangxi Radicals"),
+    (0x2FF0, 0x2FFF, 12, "Ideographic Description Characters"),
+    (0x3000, 0x303F, 64, "CJK Symbols and Punctuation"),
+    (0x3040, 0x309F, 93, "Hiragana"),
+    (0x30A0, 0x30FF, 96, "Katakana"),
+    (0x3100, 0x312F, 43, "Bopomofo"),
+    (0x3130, 0x318F, 94, "Hangul Compatibility Jamo"),
+    (0x3190, 0x319F, 16, "Kanbun"),
+    (0x31A0, 0x31BF, 32, "Bopomofo Extended"),
+    (0x31C0, 0x31EF, 36, "CJK Strokes"),
+    (0x31F0, 0x31FF, 16, "Katakana Phonetic Extensions"),
+    (0x3200, 0x32FF, 255, "Enclosed CJK Letters and Months"),
+    (0x3300, 0x33FF, 256, "CJK Compatibility"),
+    (0x3400, 0x4DBF, 6592, "CJK Unified Ideographs Extension A"),
+    (0x4DC0, 0x4DFF, 64, "Yijing Hexagram Symbols"),
+    (0x4E00, 0x9FFF, 20992, "CJK Unified Ideographs"),
+    (0xA000, 0xA48F, 1165, "Yi Syllables"),
+    (0xA490, 0xA4CF, 55, "Yi Radicals"),
+    (0xA4D0, 0xA4FF, 48, "Lisu"),
+    (0xA500, 0xA63F, 300, "Vai"),
+    (0xA640, 0xA69F, 96, "Cyrillic Extended-B"),
+    (0xA6A0, 0xA6FF, 88, "Bamum"),
+    (0xA700, 0xA71F, 32, "Modifier Tone Letters"),
+    (0xA720, 0xA7FF, 193, "Latin Extended-D"),
+    (0xA800, 0xA82F, 45, "Syloti Nagri"),
+    (0xA830, 0xA83F, 10, "Common Indic Number Forms"),
+    (0xA840, 0xA87F, 56, "Phags-pa"),
+    (0xA880, 0xA8DF, 82, "Saurashtra"),
+    (0xA8E0, 0xA8FF, 32, "Devanagari Extended"),
+    (0xA900, 0xA92F, 48, "Kayah Li"),
+    (0xA930, 0xA95F, 37, "Rejang"),
+    (0xA960, 0xA97F, 29, "Hangul Jamo Extended-A"),
+    (0xA980, 0xA9DF, 91, "Javanese"),
+    (0xA9E0, 0xA9FF, 31, "Myanmar Extended-B"),
+    (0xAA00, 0xAA5F, 83, "Cham"),
+    (0xAA60, 0xAA7F, 32, "Myanmar Extended-A"),
+    (0xAA80, 0xAADF, 72, "Tai Viet"),
+    (0xAAE0, 0xAAFF, 23, "Meetei Mayek Extensions"),
+    (0xAB00, 0xAB2F, 32, "Ethiopic Extended-A"),
+    (0xAB30, 0xAB6F, 60, "Latin Extended-E"),
+    (0xAB70, 0xABBF, 80, "Cherokee Supplement"),
+    (0xABC0, 0xABFF, 56, "Meetei Mayek"),
+    (0xAC00, 0xD7AF, 11172, "Hangul Syllables"),
+    (0xD7B0, 0xD7FF, 72, "Hangul Jamo Extended-B"),
+    (0xD800, 0xDB7F, 0, "High Surrogates"),
+    (0xDB80, 0xDBFF, 0, "High Private Use Surrogates"),
+    (0xDC00, 0xDFFF, 0, "Low Surrogates"),
+    (0xE000, 0xF8FF, 6400, "Private Use Area"),
+    (0xF900, 0xFAFF, 472, "CJK Compatibility Ideographs"),
+    (0xFB00, 0xFB4F, 58, "Alphabetic Presentation Forms"),
+    (0xFB50, 0xFDFF, 631, "Arabic Presentation Forms-A"),
+    (0xFE00, 0xFE0F, 16, "Variation Selectors"),
+    (0xFE10, 0xFE1F, 10, "Vertical Forms"),
+    (0xFE20, 0xFE2F, 16, "Combining Half Marks"),
+    (0xFE30, 0xFE4F, 32, "CJK Compatibility Forms"),
+    (0xFE50, 0xFE6F, 26, "Small Form Variants"),
+    (0xFE70, 0xFEFF, 141, "Arabic Presentation Forms-B"),
+    (0xFF00, 0xFFEF, 225, "Halfwidth and Fullwidth Forms"),
+    (0xFFF0, 0xFFFF, 5, "Specials"),
+    (0x10000, 0x1007F, 88, "Linear B Syllabary"),
+    (0x10080, 0x100FF, 123, "Linear B Ideograms"),
+    (0x10100, 0x1013F, 57, "Aegean Numbers"),
+    (0x10140, 0x1018F, 79, "Ancient Greek Numbers"),
+    (0x10190, 0x101CF, 14, "Ancient Symbols"),
+    (0x101D0, 0x101FF, 46, "Phaistos Disc"),
+    (0x10280, 0x1029F, 29, "Lycian"),
+    (0x102A0, 0x102DF, 49, "Carian"),
+    (0x102E0, 0x102FF, 28, "Coptic Epact Numbers"),
+    (0x10300, 0x1032F, 39, "Old Italic"),
+    (0x10330, 0x1034F, 27, "Gothic"),
+    (0x10350, 0x1037F, 43, "Old Permic"),
+    (0x10380, 0x1039F, 31, "Ugaritic"),
+    (0x103A0, 0x103DF, 50, "Old Persian"),
+    (0x10400, 0x1044F, 80, "Deseret"),
+    (0x10450, 0x1047F, 48, "Shavian"),
+    (0x10480, 0x104AF, 40, "Osmanya"),
+    (0x104B0, 0x104FF, 72, "Osage"),
+    (0x10500, 0x1052F, 40, "Elbasan"),
+    (0x10530, 0x1056F, 53, "Caucasian Albanian"),
+    (0x10570, 0x105BF, 70, "Vithkuqi"),
+    (0x10600, 0x1077F, 341, "Linear A"),
+    (0x10780, 0x107BF, 57, "Latin Extended-F"),
+    (0x10800, 0x1083F, 55, "Cypriot Syllabary"),
+    (0x10840, 0x1085F, 31, "Imperial Aramaic"),
+    (0x10860, 0x1087F, 32, "Palmyrene"),
+    (0x10880, 0x108AF, 40, "Nabataean"),
+    (0x108E0, 0x108FF, 26, "Hatran"),
+    (0x10900, 0x1091F, 29, "Phoenician"),
+    (0x10920, 0x1093F, 27, "Lydian"),
+    (0x10980, 0x1099F, 32, "Meroitic Hieroglyphs"),
+    (0x109A0, 0x109FF, 90, "Meroitic Cursive"),
+    (0x10A00, 0x10A5F, 68, "Kharoshthi"),
+    (0x10A60, 0x10A7F, 32, "Old South Arabian"),
+    (0x10A80, 0x10A9F, 32, "Old North Arabian"),
+    (0x10AC0, 0x10AFF, 51, "Manichaean"),
+    (0x10B00, 0x10B3F, 61, "Avestan"),
+    (0x10B40, 0x10B5F, 30, "Inscriptional Parthian"),
+    (0x10B60, 0x10B7F, 27, "Inscriptional Pahlavi"),
+    (0x10B80, 0x10BAF, 29, "Psalter Pahlavi"),
+    (0x10C00, 0x10C4F, 73, "Old Turkic"),
+    (0x10C80, 0x10CFF, 108, "Old Hungarian"),
+    (0x10D00, 0x10D3F, 50, "Hanifi Rohingya"),
+    (0x10E60, 0x10E7F, 31, "Rumi Numeral Symbols"),
+    (0x10E80, 0x10EBF, 47, "Yezidi"),
+    (0x10F00, 0x10F2F, 40, "Old Sogdian"),
+    (0x10F30, 0x10F6F, 42, "Sogdian"),
+    (0x10F70, 0x10FAF, 26, "Old Uyghur"),
+    (0x10FB0, 0x10FDF, 28, "Chorasmian"),
+    (0x10FE0, 0x10FFF, 23, "Elymaic"),
+    (0x11000, 0x1107F, 115, "Brahmi"),
+    (0x11080, 0x110CF, 68, "Kaithi"),
+    (0x110D0, 0x110FF, 35, "Sora Sompeng"),
+    (0x11100, 0x1114F, 71, "Chakma"),
+    (0x11150, 0x1117F, 39, "Mahajani"),
+    (0x11180, 0x111DF, 96, "Sharada"),
+    (0x111E0, 0x111FF, 20, "Sinhala Archaic Numbers"),
+    (0x11200, 0x1124F, 62, "Khojki"),
+    (0x11280, 0x112AF, 38, "Multani"),
+    (0x112B0, 0x112FF, 69, "Khudawadi"),
+    (0x11300, 0x1137F, 86, "Grantha"),
+    (0x11400, 0x1147F, 97, "Newa"),
+    (0x11480, 0x114DF, 82, "Tirhuta"),
+    (0x11580, 0x115FF, 92, "Siddham"),
+    (0x11600, 0x1165F, 79, "Modi"),
+    (0x11660, 0x1167F, 13, "Mongolian Supplement"),
+    (0x11680, 0x116CF, 68, "Takri"),
+    (0x11700, 0x1174F, 65, "Ahom"),
+    (0x11800, 0x1184F, 60, "Dogra"),
+    (0x118A0, 0x118FF, 84, "Warang Citi"),
+    (0x11900, 0x1195F, 72, "Dives Akuru"),
+    (0x119A0, 0x119FF, 65, "Nandinagari"),
+    (0x11A00, 0x11A4F, 72, "Zanabazar Square"),
+    (0x11A50, 0x11AAF, 83, "Soyombo"),
+    (0x11AB0, 0x11ABF, 16, "Unified Canadian Aboriginal Syllabics Extended-A"),
+    (0x11AC0, 0x11AFF, 57, "Pau Cin Hau"),
+    (0x11C00, 0x11C6F, 97, "Bhaiksuki"),
+    (0x11C70, 0x11CBF, 68, "Marchen"),
+    (0x11D00, 0x11D5F, 75, "Masaram Gondi"),
+    (0x11D60, 0x11DAF, 63, "Gunjala Gondi"),
+    (0x11EE0, 0x11EFF, 25, "Makasar"),
+    (0x11FB0, 0x11FBF, 1, "Lisu Supplement"),
+    (0x11FC0, 0x11FFF, 51, "Tamil Supplement"),
+    (0x12000, 0x123FF, 922, "Cuneiform"),
+    (0x12400, 0x1247F, 116, "Cuneiform Numbers and Punctuation"),
+    (0x12480, 0x1254F, 196, "Early Dynastic Cuneiform"),
+    (0x12F90, 0x12FFF, 99, "Cypro-Minoan"),
+    (0x13000, 0x1342F, 1071, "Egyptian Hieroglyphs"),
+    (0x13430, 0x1343F, 9, "Egyptian Hieroglyph Format Controls"),
+    (0x14400, 0x1467F, 583, "Anatolian Hieroglyphs"),
+    (0x16800, 0x16A3F, 569, "Bamum Supplement"),
+    (0x16A40, 0x16A6F, 43, "Mro"),
+    (0x16A70, 0x16ACF, 89, "Tangsa"),
+    (0x16AD0, 0x16AFF, 36, "Bassa Vah"),
+    (0x16B00, 0x16B8F, 127, "Pahawh Hmong"),
+    (0x16E40, 0x16E9F, 91, "Medefaidrin"),
+    (0x16F00, 0x16F9F, 149, "Miao"),
+    (0x16FE0, 0x16FFF, 7, "Ideographic Symbols and Punctuation"),
+    (0x17000, 0x187FF, 6136, "Tangut"),
+    (0x18800, 0x18AFF, 768, "Tangut Components"),
+    (0x18B00, 0x18CFF, 470, "Khitan Small Script"),
+    (0x18D00, 0x18D7F, 9, "Tangut Supplement"),
+    (0x1AFF0, 0x1AFFF, 13, "Kana Extended-B"),
+    (0x1B000, 0x1B0FF, 256, "Kana Supplement"),
+    (0x1B100, 0x1B12F, 35, "Kana Extended-A"),
+    (0x1B130, 0x1B16F, 7, "Small Kana Extension"),
+    (0x1B170, 0x1B2FF, 396, "Nushu"),
+    (0x1BC00, 0x1BC9F, 143, "Duployan"),
+    (0x1BCA0, 0x1BCAF, 4, "Shorthand Format Controls"),
+    (0x1CF00, 0x1CFCF, 185, "Znamenny Musical Notation"),
+    (0x1D000, 0x1D0FF, 246, "Byzantine Musical Symbols"),
+    (0x1D100, 0x1D1FF, 233, "Musical Symbols"),
+    (0x1D200, 0x1D24F, 70, "Ancient Greek Musical Notation"),
+    (0x1D2E0, 0x1D2FF, 20, "Mayan Numerals"),
+    (0x1D300, 0x1D35F, 87, "Tai Xuan Jing Symbols"),
+    (0x1D360, 0x1D37F, 25, "Counting Rod Numerals"),
+    (0x1D400, 0x1D7FF, 996, "Mathematical Alphanumeric Symbols"),
+    (0x1D800, 0x1DAAF, 672, "Sutton SignWriting"),
+    (0x1DF00, 0x1DFFF, 31, "Latin Extended-G"),
+    (0x1E000, 0x1E02F, 38, "Glagolitic Supplement"),
+    (0x1E100, 0x1E14F, 71, "Nyiakeng Puachue Hmong"),
+    (0x1E290, 0x1E2BF, 31, "Toto"),
+    (0x1E2C0, 0x1E2FF, 59, "Wancho"),
+    (0x1E7E0, 0x1E7FF, 28, "Ethiopic Extended-B"),
+    (0x1E800, 0x1E8DF, 213, "Mende Kikakui"),
+    (0x1E900, 0x1E95F, 88, "Adlam"),
+    (0x1EC70, 0x1ECBF, 68, "Indic Siyaq Numbers"),
+    (0x1ED00, 0x1ED4F, 61, "Ottoman Siyaq Numbers"),
+    (0x1EE00, 0x1EEFF, 143, "Arabic Mathematical Alphabetic Symbols"),
+    (0x1F000, 0x1F02F, 44, "Mahjong Tiles"),
+    (0x1F030, 0x1F09F, 100, "Domino Tiles"),
+    (0x1F0A0, 0x1F0FF, 82, "Playing Cards"),
+    (0x1F100, 0x1F1FF, 200, "Enclosed Alphanumeric Supplement"),
+    (0x1F200, 0x1F2FF, 64, "Enclosed Ideographic Supplement"),
+    (0x1F300, 0x1F5FF, 768, "Miscellaneous Symbols and Pictographs"),
+    (0x1F600, 0x1F64F, 80, "Emoticons"),
+    (0x1F650, 0x1F67F, 48, "Ornamental Dingbats"),
+    (0x1F680, 0x1F6FF, 117, "Transport and Map Symbols"),
+    (0x1F700, 0x1F77F, 116, "Alchemical Symbols"),
+    (0x1F780, 0x1F7FF, 102, "Geometric Shapes Extended"),
+    (0x1F800, 0x1F8FF, 150, "Supplemental Arrows-C"),
+    (0x1F900, 0x1F9FF, 256, "Supplemental Symbols and Pictographs"),
+    (0x1FA00, 0x1FA6F, 98, "Chess Symbols"),
+    (0x1FA70, 0x1FAFF, 88, "Symbols and Pictographs Extended-A"),
+    (0x1FB00, 0x1FBFF, 212, "Symbols for Legacy Computing"),
+    (0x20000, 0x2A6DF, 42720, "CJK Unified Ideographs Extension B"),
+    (0x2A700, 0x2B73F, 4153, "CJK Unified Ideographs Extension C"),
+    (0x2B740, 0x2B81F, 222, "CJK Unified Ideographs Extension D"),
+    (0x2B820, 0x2CEAF, 5762, "CJK Unified Ideographs Extension E"),
+    (0x2CEB0, 0x2EBEF, 7473, "CJK Unified Ideographs Extension F"),
+    (0x2F800, 0x2FA1F, 542, "CJK Compatibility Ideographs Supplement"),
+    (0x30000, 0x3134F, 4939, "CJK Unified Ideographs Extension G"),
+    (0xE0000, 0xE007F, 97, "Tags"),
+    (0xE0100, 0xE01EF, 240, "Variation Selectors Supplement"),
+    (0xF0000, 0xFFFFF, 65536, "Supplementary Private Use Area-A"),
+    (0x100000, 0x10FFFF, 65536, "Supplementary Private Use Area-B"),
+];
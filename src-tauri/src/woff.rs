@@ -0,0 +1,750 @@
+use std::io::Read;
+
+// WOFF/WOFF2 containers are unwrapped back into a plain SFNT (TrueType/OpenType) file, so the rest
+// of the pipeline only ever sees fonts fontdue and ttf-parser can parse.
+
+const WOFF_HEADER_LEN: usize = 44;
+const WOFF2_HEADER_LEN: usize = 48;
+
+// Table tags addressable by index in a WOFF2 table directory entry.
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm", b"glyf",
+    b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT",
+    b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH", b"CBDT",
+    b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar", b"bdat", b"bloc", b"bsln", b"cvar",
+    b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty", b"just", b"lcar", b"mort", b"morx", b"opbd",
+    b"prop", b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill",
+];
+
+// Simple glyph point flags.
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const X_IS_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE: u8 = 0x20;
+const OVERLAP_SIMPLE: u8 = 0x40;
+
+// Composite glyph component flags.
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+/// Returns the SFNT bytes of `data`, decoding WOFF/WOFF2 containers and passing anything else
+/// through unchanged.
+pub fn unwrap_web_font(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    match data.get(0..4) {
+        Some(b"wOFF") => decode_woff(&data).map_err(|e| format!("WOFF decoding failed: {}", e)),
+        Some(b"wOF2") => decode_woff2(&data).map_err(|e| format!("WOFF2 decoding failed: {}", e)),
+        _ => Ok(data),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len());
+        let end = end.ok_or_else(|| format!("unexpected end of data at offset {}", self.pos))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn tag(&mut self) -> Result<[u8; 4], String> {
+        let b = self.bytes(4)?;
+        Ok([b[0], b[1], b[2], b[3]])
+    }
+
+    /// WOFF2 UIntBase128: big-endian groups of 7 bits, at most five bytes, no leading zeros.
+    fn base128(&mut self) -> Result<u32, String> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let b = self.u8()?;
+            if i == 0 && b == 0x80 {
+                return Err("UIntBase128 with leading zeros".to_string());
+            }
+            if value & 0xFE00_0000 != 0 {
+                return Err("UIntBase128 overflow".to_string());
+            }
+            value = (value << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("UIntBase128 longer than five bytes".to_string())
+    }
+
+    /// WOFF2 255UInt16.
+    fn uint255(&mut self) -> Result<u16, String> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 506,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16,
+        })
+    }
+}
+
+fn decode_woff(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(data);
+    r.bytes(4)?;
+    let flavor = r.u32()?;
+    r.u32()?; // length
+    let num_tables = r.u16()? as usize;
+    r.pos = WOFF_HEADER_LEN;
+
+    let mut tables = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let tag = r.tag()?;
+        let offset = r.u32()? as usize;
+        let comp_length = r.u32()? as usize;
+        let orig_length = r.u32()? as usize;
+        r.u32()?; // origChecksum
+        let compressed = data
+            .get(offset..offset.saturating_add(comp_length))
+            .ok_or_else(|| format!("table {} is out of bounds", tag_name(&tag)))?;
+        let table = if comp_length == orig_length {
+            compressed.to_vec()
+        } else {
+            // origLength is untrusted, so the buffer grows with what the stream actually holds.
+            let mut out = Vec::new();
+            // One byte past origLength is enough to tell an oversized table from a complete one.
+            flate2::read::ZlibDecoder::new(compressed)
+                .take(orig_length as u64 + 1)
+                .read_to_end(&mut out)
+                .map_err(|e| format!("zlib error in table {}: {}", tag_name(&tag), e))?;
+            out
+        };
+        if table.len() != orig_length {
+            return Err(format!("table {} decompressed to the wrong size", tag_name(&tag)));
+        }
+        tables.push((tag, table));
+    }
+    Ok(build_sfnt(flavor, tables))
+}
+
+struct Woff2Entry {
+    tag: [u8; 4],
+    orig_length: usize,
+    transformed: bool,
+    stored_length: usize,
+}
+
+fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(data);
+    r.bytes(4)?;
+    let flavor = r.u32()?;
+    r.u32()?; // length
+    let num_tables = r.u16()? as usize;
+    r.u16()?; // reserved
+    r.u32()?; // totalSfntSize
+    let compressed_size = r.u32()? as usize;
+    r.pos = WOFF2_HEADER_LEN;
+    if flavor == u32::from_be_bytes(*b"ttcf") {
+        return Err("font collections are not supported".to_string());
+    }
+
+    let mut entries = Vec::with_capacity(num_tables);
+    for _ in 0..num_tables {
+        let flags = r.u8()?;
+        let tag = match (flags & 0x3F) as usize {
+            63 => r.tag()?,
+            index => *WOFF2_KNOWN_TAGS[index],
+        };
+        let version = flags >> 6;
+        let orig_length = r.base128()? as usize;
+        // glyf/loca use version 0 for their transform and 3 for none; other tables the opposite.
+        let transformed = if &tag == b"glyf" || &tag == b"loca" { version == 0 } else { version != 0 };
+        let stored_length = if transformed { r.base128()? as usize } else { orig_length };
+        entries.push(Woff2Entry {
+            tag,
+            orig_length,
+            transformed,
+            stored_length,
+        });
+    }
+
+    let compressed = r.bytes(compressed_size)?;
+    let stream_size = entries.iter().map(|e| e.stored_length as u64).sum::<u64>();
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(stream_size + 1)
+        .read_to_end(&mut stream)
+        .map_err(|e| format!("Brotli error: {}", e))?;
+    if stream.len() as u64 > stream_size {
+        return Err("Brotli stream is larger than its tables".to_string());
+    }
+
+    let mut raw: Vec<(&Woff2Entry, &[u8])> = Vec::with_capacity(entries.len());
+    let mut offset = 0usize;
+    for entry in &entries {
+        let table = stream
+            .get(offset..offset + entry.stored_length)
+            .ok_or_else(|| format!("table {} is out of bounds", tag_name(&entry.tag)))?;
+        raw.push((entry, table));
+        offset += entry.stored_length;
+    }
+
+    let find = |tag: &[u8; 4]| raw.iter().find(|(e, _)| &e.tag == tag);
+    let mut rebuilt: Option<RebuiltGlyf> = None;
+    if let Some((entry, table)) = find(b"glyf") {
+        if entry.transformed {
+            rebuilt = Some(reconstruct_glyf(table)?);
+        }
+    }
+
+    let mut tables = Vec::with_capacity(raw.len());
+    for (entry, table) in &raw {
+        let table = match (&entry.tag, &rebuilt) {
+            (b"glyf", Some(rebuilt)) => rebuilt.glyf.clone(),
+            (b"loca", Some(rebuilt)) => rebuilt.loca.clone(),
+            (b"loca", None) if entry.transformed => {
+                return Err("transformed loca without transformed glyf".to_string())
+            }
+            (b"hmtx", _) if entry.transformed => {
+                let x_mins = rebuilt
+                    .as_ref()
+                    .map(|rebuilt| rebuilt.x_mins.as_slice())
+                    .ok_or_else(|| "transformed hmtx requires a transformed glyf".to_string())?;
+                let hhea = find(b"hhea").ok_or_else(|| "missing hhea table".to_string())?.1;
+                let num_h_metrics = Reader { data: hhea, pos: 34 }.u16()? as usize;
+                reconstruct_hmtx(table, num_h_metrics, x_mins)?
+            }
+            _ if entry.transformed => {
+                return Err(format!("unsupported transform for table {}", tag_name(&entry.tag)))
+            }
+            _ => table.to_vec(),
+        };
+        // Rebuilt glyf/loca may be encoded differently from the original, so only check the rest.
+        if !entry.transformed && table.len() != entry.orig_length {
+            return Err(format!("table {} has the wrong size", tag_name(&entry.tag)));
+        }
+        tables.push((entry.tag, table));
+    }
+    Ok(build_sfnt(flavor, tables))
+}
+
+struct RebuiltGlyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// Per-glyph xMin, the implied left side bearing for the hmtx transform.
+    x_mins: Vec<i16>,
+}
+
+/// Rebuilds `glyf` and `loca` from the WOFF2 glyf transform.
+fn reconstruct_glyf(data: &[u8]) -> Result<RebuiltGlyf, String> {
+    let mut header = Reader::new(data);
+    header.u16()?; // reserved
+    let option_flags = header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut sizes = [0usize; 7];
+    for size in sizes.iter_mut() {
+        *size = header.u32()? as usize;
+    }
+    let mut n_contours = Reader::new(header.bytes(sizes[0])?);
+    let mut n_points = Reader::new(header.bytes(sizes[1])?);
+    let mut flags = Reader::new(header.bytes(sizes[2])?);
+    let mut glyphs = Reader::new(header.bytes(sizes[3])?);
+    let mut composites = Reader::new(header.bytes(sizes[4])?);
+    let mut bbox_stream = Reader::new(header.bytes(sizes[5])?);
+    let mut instructions = Reader::new(header.bytes(sizes[6])?);
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        Some(header.bytes(num_glyphs.div_ceil(8))?)
+    } else {
+        None
+    };
+    let bbox_bitmap = bbox_stream.bytes(num_glyphs.div_ceil(32) * 4)?;
+    let has_bit = |bitmap: &[u8], i: usize| bitmap[i / 8] & (0x80 >> (i % 8)) != 0;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(num_glyphs + 1);
+    let mut x_mins = Vec::with_capacity(num_glyphs);
+    for glyph_id in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let contours = n_contours.i16()?;
+        let mut x_min = 0;
+        let explicit_bbox = if has_bit(bbox_bitmap, glyph_id) {
+            Some([bbox_stream.i16()?, bbox_stream.i16()?, bbox_stream.i16()?, bbox_stream.i16()?])
+        } else {
+            None
+        };
+        match contours {
+            0 => {
+                if explicit_bbox.is_some() {
+                    return Err(format!("empty glyph {} has a bounding box", glyph_id));
+                }
+            }
+            -1 => {
+                let bbox = explicit_bbox.ok_or_else(|| format!("composite glyph {} has no bounding box", glyph_id))?;
+                let start = composites.pos;
+                let mut have_instructions = false;
+                loop {
+                    let component_flags = composites.u16()?;
+                    composites.u16()?; // glyphIndex
+                    let mut skip = if component_flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+                    if component_flags & WE_HAVE_A_SCALE != 0 {
+                        skip += 2;
+                    } else if component_flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                        skip += 4;
+                    } else if component_flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                        skip += 8;
+                    }
+                    composites.bytes(skip)?;
+                    have_instructions |= component_flags & WE_HAVE_INSTRUCTIONS != 0;
+                    if component_flags & MORE_COMPONENTS == 0 {
+                        break;
+                    }
+                }
+                let components = &composites.data[start..composites.pos];
+                push_i16s(&mut glyf, &[-1, bbox[0], bbox[1], bbox[2], bbox[3]]);
+                glyf.extend_from_slice(components);
+                if have_instructions {
+                    let len = glyphs.uint255()?;
+                    glyf.extend_from_slice(&len.to_be_bytes());
+                    glyf.extend_from_slice(instructions.bytes(len as usize)?);
+                }
+                x_min = bbox[0];
+            }
+            contours if contours > 0 => {
+                let mut end_points = Vec::with_capacity(contours as usize);
+                let mut total = 0usize;
+                for _ in 0..contours {
+                    total += n_points.uint255()? as usize;
+                    let end = total.checked_sub(1);
+                    end_points.push(end.ok_or_else(|| format!("glyph {} starts with an empty contour", glyph_id))?);
+                }
+                let mut points = Vec::with_capacity(total);
+                let (mut x, mut y) = (0i32, 0i32);
+                for _ in 0..total {
+                    let flag = flags.u8()?;
+                    let (dx, dy) = decode_triplet(flag & 0x7F, &mut glyphs)?;
+                    x += dx;
+                    y += dy;
+                    points.push((x, y, flag & 0x80 == 0));
+                }
+                let instruction_len = glyphs.uint255()? as usize;
+                let bbox = match explicit_bbox {
+                    Some(bbox) => bbox,
+                    None => point_bbox(&points)?,
+                };
+                let overlap = overlap_bitmap.is_some_and(|bitmap| has_bit(bitmap, glyph_id));
+                push_i16s(&mut glyf, &[contours, bbox[0], bbox[1], bbox[2], bbox[3]]);
+                for end in end_points {
+                    let end = u16::try_from(end).map_err(|_| format!("glyph {} has too many points", glyph_id))?;
+                    glyf.extend_from_slice(&end.to_be_bytes());
+                }
+                glyf.extend_from_slice(&(instruction_len as u16).to_be_bytes());
+                glyf.extend_from_slice(instructions.bytes(instruction_len)?);
+                push_points(&mut glyf, &points, overlap)?;
+                x_min = bbox[0];
+            }
+            other => return Err(format!("glyph {} has invalid contour count {}", glyph_id, other)),
+        }
+        x_mins.push(x_min);
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+    }
+    offsets.push(glyf.len());
+
+    let mut loca = Vec::with_capacity(offsets.len() * 4);
+    for offset in offsets {
+        if index_format == 0 {
+            let half = u16::try_from(offset / 2).map_err(|_| "glyf too large for short loca".to_string())?;
+            loca.extend_from_slice(&half.to_be_bytes());
+        } else {
+            loca.extend_from_slice(&(offset as u32).to_be_bytes());
+        }
+    }
+    Ok(RebuiltGlyf { glyf, loca, x_mins })
+}
+
+fn decode_triplet(flag: u8, stream: &mut Reader) -> Result<(i32, i32), String> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag_i = flag as i32;
+    Ok(match flag {
+        0..=9 => (0, with_sign(flag, ((flag_i & 14) << 7) + stream.u8()? as i32)),
+        10..=19 => (with_sign(flag, (((flag_i - 10) & 14) << 7) + stream.u8()? as i32), 0),
+        20..=83 => {
+            let b0 = flag_i - 20;
+            let b1 = stream.u8()? as i32;
+            (
+                with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+                with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+            )
+        }
+        84..=119 => {
+            let b0 = flag_i - 84;
+            let b1 = stream.u8()? as i32;
+            let b2 = stream.u8()? as i32;
+            (
+                with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+                with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+            )
+        }
+        120..=123 => {
+            let b = stream.bytes(3)?;
+            let (b0, b1, b2) = (b[0] as i32, b[1] as i32, b[2] as i32);
+            (with_sign(flag, (b0 << 4) + (b1 >> 4)), with_sign(flag >> 1, ((b1 & 0x0F) << 8) + b2))
+        }
+        _ => {
+            let b = stream.bytes(4)?;
+            (
+                with_sign(flag, ((b[0] as i32) << 8) + b[1] as i32),
+                with_sign(flag >> 1, ((b[2] as i32) << 8) + b[3] as i32),
+            )
+        }
+    })
+}
+
+fn point_bbox(points: &[(i32, i32, bool)]) -> Result<[i16; 4], String> {
+    let (mut x_min, mut y_min, mut x_max, mut y_max) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for (x, y, _) in points {
+        x_min = x_min.min(*x);
+        y_min = y_min.min(*y);
+        x_max = x_max.max(*x);
+        y_max = y_max.max(*y);
+    }
+    if points.is_empty() {
+        return Ok([0; 4]);
+    }
+    let to_i16 = |v: i32| i16::try_from(v).map_err(|_| format!("coordinate {} out of range", v));
+    Ok([to_i16(x_min)?, to_i16(y_min)?, to_i16(x_max)?, to_i16(y_max)?])
+}
+
+/// Writes flags and coordinates in the standard simple glyph encoding, without flag repeats.
+fn push_points(out: &mut Vec<u8>, points: &[(i32, i32, bool)], overlap: bool) -> Result<(), String> {
+    let mut flags = Vec::with_capacity(points.len());
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+    let (mut last_x, mut last_y) = (0i32, 0i32);
+    for (i, (x, y, on_curve)) in points.iter().enumerate() {
+        let mut flag = if *on_curve { ON_CURVE_POINT } else { 0 };
+        if i == 0 && overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+        flag |= push_delta(&mut xs, x - last_x, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE)?;
+        flag |= push_delta(&mut ys, y - last_y, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE)?;
+        flags.push(flag);
+        last_x = *x;
+        last_y = *y;
+    }
+    out.extend_from_slice(&flags);
+    out.extend_from_slice(&xs);
+    out.extend_from_slice(&ys);
+    Ok(())
+}
+
+fn push_delta(out: &mut Vec<u8>, delta: i32, short: u8, same_or_positive: u8) -> Result<u8, String> {
+    if delta == 0 {
+        Ok(same_or_positive)
+    } else if delta.abs() < 256 {
+        out.push(delta.unsigned_abs() as u8);
+        Ok(short | if delta > 0 { same_or_positive } else { 0 })
+    } else {
+        let delta = i16::try_from(delta).map_err(|_| format!("coordinate delta {} out of range", delta))?;
+        out.extend_from_slice(&delta.to_be_bytes());
+        Ok(0)
+    }
+}
+
+/// Rebuilds `hmtx` from the WOFF2 hmtx transform, taking omitted side bearings from glyph xMin.
+fn reconstruct_hmtx(data: &[u8], num_h_metrics: usize, x_mins: &[i16]) -> Result<Vec<u8>, String> {
+    let mut r = Reader::new(data);
+    let flags = r.u8()?;
+    let num_glyphs = x_mins.len();
+    if num_h_metrics == 0 || num_h_metrics > num_glyphs {
+        return Err("invalid numberOfHMetrics".to_string());
+    }
+    let advances = (0..num_h_metrics).map(|_| r.u16()).collect::<Result<Vec<_>, _>>()?;
+    let mut lsbs = Vec::with_capacity(num_glyphs);
+    for (glyph_id, x_min) in x_mins.iter().enumerate() {
+        let omitted = if glyph_id < num_h_metrics { flags & 1 != 0 } else { flags & 2 != 0 };
+        lsbs.push(if omitted { *x_min } else { r.i16()? });
+    }
+
+    let mut out = Vec::with_capacity(num_h_metrics * 4 + (num_glyphs - num_h_metrics) * 2);
+    for (glyph_id, lsb) in lsbs.iter().enumerate() {
+        if let Some(advance) = advances.get(glyph_id) {
+            out.extend_from_slice(&advance.to_be_bytes());
+        }
+        out.extend_from_slice(&lsb.to_be_bytes());
+    }
+    Ok(out)
+}
+
+fn push_i16s(out: &mut Vec<u8>, values: &[i16]) {
+    for v in values {
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+}
+
+/// Assembles an SFNT file: offset table, sorted table directory with checksums, padded tables.
+pub(crate) fn build_sfnt(flavor: u32, mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = if num_tables == 0 { 0 } else { 15 - num_tables.leading_zeros() as u16 };
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&flavor.to_be_bytes());
+    out.extend_from_slice(&num_tables.to_be_bytes());
+    out.extend_from_slice(&search_range.to_be_bytes());
+    out.extend_from_slice(&entry_selector.to_be_bytes());
+    out.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, table) in &tables {
+        out.extend_from_slice(tag);
+        out.extend_from_slice(&table_checksum(table).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        out.extend_from_slice(table);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    out
+}
+
+fn table_checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn tag_name(tag: &[u8; 4]) -> String {
+    String::from_utf8_lossy(tag).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // One glyph: an empty glyph 0 and a triangle (0,0) (100,0) (50,100) as glyph 1, in the
+    // standard glyf encoding the decoder writes back out.
+    const GLYF: [u8; 20] = [
+        0, 1, 0, 0, 0, 0, 0, 100, 0, 100, // numberOfContours, bbox
+        0, 2, // endPtsOfContours
+        0, 0, // instructionLength
+        0x31, 0x33, 0x27, // flags
+        100, 50, // x deltas
+        100, // y delta
+    ];
+    const LOCA: [u8; 6] = [0, 0, 0, 0, 0, 10];
+    const CMAP: &[u8] = b"cmap bytes";
+    const NAME: &[u8] = b"name table, not a multiple of four";
+
+    fn source_tables() -> Vec<([u8; 4], Vec<u8>)> {
+        vec![
+            (*b"cmap", CMAP.to_vec()),
+            (*b"glyf", GLYF.to_vec()),
+            (*b"loca", LOCA.to_vec()),
+            (*b"name", NAME.to_vec()),
+        ]
+    }
+
+    /// Reads the table directory of an SFNT file, checking offsets and checksums on the way.
+    fn sfnt_tables(data: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut r = Reader::new(data);
+        assert_eq!(r.u32().unwrap(), 0x0001_0000);
+        let num_tables = r.u16().unwrap();
+        r.pos = 12;
+        (0..num_tables)
+            .map(|_| {
+                let tag = r.tag().unwrap();
+                let checksum = r.u32().unwrap();
+                let offset = r.u32().unwrap() as usize;
+                let length = r.u32().unwrap() as usize;
+                assert_eq!(offset % 4, 0);
+                let table = data[offset..offset + length].to_vec();
+                assert_eq!(table_checksum(&table), checksum);
+                (tag, table)
+            })
+            .collect()
+    }
+
+    fn base128(out: &mut Vec<u8>, value: u32) {
+        let groups = (0..5).rev().map(|i| (value >> (7 * i)) as u8 & 0x7F).skip_while(|g| *g == 0).collect::<Vec<_>>();
+        let groups = if groups.is_empty() { vec![0] } else { groups };
+        for (i, group) in groups.iter().enumerate() {
+            out.push(if i + 1 < groups.len() { group | 0x80 } else { *group });
+        }
+    }
+
+    /// A Brotli stream holding `data` in one uncompressed meta-block.
+    fn brotli_stored(data: &[u8]) -> Vec<u8> {
+        assert!(!data.is_empty() && data.len() <= 1 << 16);
+        // WBITS = 16 (0), ISLAST = 0, MNIBBLES = 4 (00), MLEN - 1, ISUNCOMPRESSED = 1.
+        let header = ((data.len() as u32 - 1) << 4) | 1 << 20;
+        let mut out = header.to_le_bytes()[..3].to_vec();
+        out.extend_from_slice(data);
+        out.push(0x03); // ISLAST, ISLASTEMPTY
+        out
+    }
+
+    fn woff(tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut body = Vec::new();
+        let data_start = WOFF_HEADER_LEN + tables.len() * 20;
+        for (i, (tag, table)) in tables.iter().enumerate() {
+            // Compress every other table to cover both storage forms.
+            let stored = if i % 2 == 0 {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(table).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                table.clone()
+            };
+            directory.extend_from_slice(tag);
+            directory.extend_from_slice(&((data_start + body.len()) as u32).to_be_bytes());
+            directory.extend_from_slice(&(stored.len() as u32).to_be_bytes());
+            directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            directory.extend_from_slice(&table_checksum(table).to_be_bytes());
+            body.extend_from_slice(&stored);
+            body.resize(body.len().next_multiple_of(4), 0);
+        }
+        let mut out = b"wOFF".to_vec();
+        out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        out.extend_from_slice(&((data_start + body.len()) as u32).to_be_bytes());
+        out.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        out.resize(WOFF_HEADER_LEN, 0);
+        out.extend_from_slice(&directory);
+        out.extend_from_slice(&body);
+        out
+    }
+
+    /// The WOFF2 glyf transform of `GLYF`/`LOCA`, with `points` as the triangle's point count.
+    fn glyf_transform(points: u8) -> Vec<u8> {
+        let streams: [&[u8]; 7] = [
+            &[0, 0, 0, 1],        // nContour
+            &[points],            // nPoints
+            &[1, 11, 86],         // flags: (0,+0) (+100,0) (-50,+100)
+            &[0, 100, 49, 99, 0], // glyphs: triplet bytes, instructionLength
+            &[],                  // composite
+            &[0, 0, 0, 0],        // bboxBitmap, no explicit boxes
+            &[],                  // instruction
+        ];
+        let mut out = Vec::new();
+        push_i16s(&mut out, &[0, 0, 2, 0]); // reserved, optionFlags, numGlyphs, indexFormat
+        for stream in streams {
+            out.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+        }
+        for stream in streams {
+            out.extend_from_slice(stream);
+        }
+        out
+    }
+
+    fn woff2(glyf: &[u8], stream_padding: usize) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        // Known tag indices: cmap 0, glyf 10 (transform 0), loca 11 (transform 0), name 5.
+        for (flags, table) in [(0u8, CMAP), (10, glyf), (11, &[][..]), (5, NAME)] {
+            directory.push(flags);
+            match flags {
+                10 => {
+                    base128(&mut directory, GLYF.len() as u32);
+                    base128(&mut directory, table.len() as u32);
+                }
+                11 => {
+                    base128(&mut directory, LOCA.len() as u32);
+                    base128(&mut directory, 0);
+                }
+                _ => base128(&mut directory, table.len() as u32),
+            }
+            stream.extend_from_slice(table);
+        }
+        stream.resize(stream.len() + stream_padding, 0);
+        let compressed = brotli_stored(&stream);
+        let mut out = b"wOF2".to_vec();
+        out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        out.extend_from_slice(&((WOFF2_HEADER_LEN + directory.len() + compressed.len()) as u32).to_be_bytes());
+        out.extend_from_slice(&4u16.to_be_bytes());
+        out.extend_from_slice(&0u16.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes()); // totalSfntSize
+        out.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        out.resize(WOFF2_HEADER_LEN, 0);
+        out.extend_from_slice(&directory);
+        out.extend_from_slice(&compressed);
+        out
+    }
+
+    #[test]
+    fn woff_decodes_to_the_source_tables() {
+        let sfnt = unwrap_web_font(woff(&source_tables())).unwrap();
+        assert_eq!(sfnt_tables(&sfnt), source_tables());
+    }
+
+    #[test]
+    fn woff2_decodes_to_the_source_tables() {
+        let sfnt = unwrap_web_font(woff2(&glyf_transform(3), 0)).unwrap();
+        assert_eq!(sfnt_tables(&sfnt), source_tables());
+    }
+
+    #[test]
+    fn plain_sfnt_passes_through() {
+        let sfnt = build_sfnt(0x0001_0000, source_tables());
+        assert_eq!(unwrap_web_font(sfnt.clone()).unwrap(), sfnt);
+    }
+
+    #[test]
+    fn woff_table_larger_than_its_length_is_rejected() {
+        let mut tables = source_tables();
+        tables[0].1 = vec![7; 4096];
+        let mut data = woff(&tables);
+        // Shrink the declared origLength of the compressed cmap.
+        let orig_length = WOFF_HEADER_LEN + 12;
+        data[orig_length..orig_length + 4].copy_from_slice(&16u32.to_be_bytes());
+        let err = unwrap_web_font(data.clone()).unwrap_err();
+        assert!(err.contains("table cmap decompressed to the wrong size"), "{}", err);
+
+        // A 4 GiB claim is checked against the stream without reserving it up front.
+        data[orig_length..orig_length + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = unwrap_web_font(data).unwrap_err();
+        assert!(err.contains("table cmap decompressed to the wrong size"), "{}", err);
+    }
+
+    #[test]
+    fn woff2_stream_larger_than_its_tables_is_rejected() {
+        let err = unwrap_web_font(woff2(&glyf_transform(3), 4096)).unwrap_err();
+        assert!(err.contains("Brotli stream is larger than its tables"), "{}", err);
+    }
+
+    #[test]
+    fn woff2_contour_without_points_is_rejected() {
+        let err = unwrap_web_font(woff2(&glyf_transform(0), 0)).unwrap_err();
+        assert!(err.contains("glyph 1 starts with an empty contour"), "{}", err);
+    }
+}